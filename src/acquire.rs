@@ -0,0 +1,229 @@
+//! Adding indexer releases to the debrid account, keeping only ones the provider already
+//! has cached, and remembering what was grabbed so it isn't added twice.
+
+use crate::indexer::Release;
+use crate::provider::DebridProvider;
+use crate::rd_client::TorrentInfo;
+use crate::subtitles::is_subtitle_file;
+use crate::vfs::{extra_folder, is_video_file};
+use redb::{ReadableDatabase, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Releases added by automated acquisition, keyed by what they were grabbed for (see
+/// `watchlist::grab_key`). Checked before searching so an item is only grabbed once,
+/// even while the new torrent is still waiting to be identified.
+pub const GRABS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("grabs");
+
+/// Time for the provider to resolve a fresh magnet's file list.
+const ADD_WAIT: Duration = Duration::from_secs(2);
+/// Time for the provider to process file selection.
+const SELECT_WAIT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grab {
+    pub release: String,
+    pub hash: String,
+    pub torrent_id: String,
+    /// Unix timestamp (seconds).
+    pub grabbed_at: i64,
+}
+
+/// Add `release` to the account and keep it only if the provider reports it already
+/// downloaded (i.e. cached). Uncached additions are deleted again so a search never
+/// leaves a slow download behind. Returns the torrent info when kept.
+pub async fn add_if_cached(
+    provider: &Arc<dyn DebridProvider>,
+    release: &Release,
+) -> Result<Option<TorrentInfo>, String> {
+    let Some(magnet) = release.magnet_uri() else {
+        return Ok(None);
+    };
+    let added = provider
+        .add_magnet(&magnet)
+        .await
+        .map_err(|e| format!("Failed to add magnet: {}", e))?;
+    tokio::time::sleep(ADD_WAIT).await;
+
+    let info = match provider.get_torrent_info(&added.id).await {
+        Ok(info) => info,
+        Err(e) => {
+            discard(provider, &added.id).await;
+            return Err(format!("Failed to get torrent info: {}", e));
+        }
+    };
+    if !info.files.iter().any(|f| is_video_file(&f.path)) {
+        info!("Release {} has no video files, discarding", release.title);
+        discard(provider, &added.id).await;
+        return Ok(None);
+    }
+    // Subtitles and extras are selected too so the VFS can expose them
+    let file_ids: Vec<String> = info
+        .files
+        .iter()
+        .filter(|f| {
+            is_video_file(&f.path) || is_subtitle_file(&f.path) || extra_folder(&f.path).is_some()
+        })
+        .map(|f| f.id.to_string())
+        .collect();
+    if let Err(e) = provider.select_files(&added.id, &file_ids.join(",")).await {
+        discard(provider, &added.id).await;
+        return Err(format!("Failed to select files: {}", e));
+    }
+    tokio::time::sleep(SELECT_WAIT).await;
+
+    match provider.get_torrent_info(&added.id).await {
+        Ok(info) if info.status == "downloaded" => Ok(Some(info)),
+        Ok(info) => {
+            info!(
+                "Release {} not cached (status: {}), discarding",
+                release.title, info.status
+            );
+            discard(provider, &added.id).await;
+            Ok(None)
+        }
+        Err(e) => {
+            discard(provider, &added.id).await;
+            Err(format!("Failed to get final torrent info: {}", e))
+        }
+    }
+}
+
+async fn discard(provider: &Arc<dyn DebridProvider>, torrent_id: &str) {
+    if let Err(e) = provider.delete_torrent(torrent_id).await {
+        warn!("Failed to delete probe torrent {}: {}", torrent_id, e);
+    }
+}
+
+/// Load every recorded grab. A missing table or undecodable entries yield nothing.
+pub async fn load_grabs(db: &Arc<redb::Database>) -> HashMap<String, Grab> {
+    let db_clone = db.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut grabs = HashMap::new();
+        if let Ok(read_txn) = db_clone.begin_read() {
+            if let Ok(table) = read_txn.open_table(GRABS_TABLE) {
+                if let Ok(iter) = table.iter() {
+                    for (key, value) in iter.flatten() {
+                        if let Ok(grab) = serde_json::from_slice::<Grab>(value.value()) {
+                            grabs.insert(key.value().to_string(), grab);
+                        }
+                    }
+                }
+            }
+        }
+        grabs
+    })
+    .await
+    .unwrap_or_else(|e| {
+        error!("Failed to load grabs: {:?}", e);
+        HashMap::new()
+    })
+}
+
+pub async fn record_grab(
+    db: &Arc<redb::Database>,
+    key: &str,
+    grab: &Grab,
+) -> Result<(), redb::Error> {
+    let db = db.clone();
+    let key = key.to_string();
+    let bytes = serde_json::to_vec(grab).map_err(|e| redb::Error::Io(std::io::Error::other(e)))?;
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(GRABS_TABLE)?;
+            table.insert(key.as_str(), bytes.as_slice())?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::MockProvider;
+    use crate::rd_client::{AddMagnetResponse, TorrentFile};
+
+    fn release() -> Release {
+        Release {
+            title: "Inception.2010.1080p".to_string(),
+            info_hash: Some("abc".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn provider(status: &str, path: &str) -> Arc<dyn DebridProvider> {
+        Arc::new(MockProvider {
+            add_magnet: Some(AddMagnetResponse {
+                id: "new".to_string(),
+                uri: String::new(),
+            }),
+            torrent_info: Some(TorrentInfo {
+                id: "new".to_string(),
+                status: status.to_string(),
+                files: vec![TorrentFile {
+                    id: 1,
+                    path: path.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn add_if_cached_keeps_downloaded_release() {
+        let info = add_if_cached(&provider("downloaded", "/Inception.mkv"), &release())
+            .await
+            .unwrap();
+        assert_eq!(info.map(|i| i.id), Some("new".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn add_if_cached_discards_uncached_or_videoless_release() {
+        let uncached = add_if_cached(&provider("queued", "/Inception.mkv"), &release())
+            .await
+            .unwrap();
+        assert!(uncached.is_none());
+        let no_video = add_if_cached(&provider("downloaded", "/Inception.rar"), &release())
+            .await
+            .unwrap();
+        assert!(no_video.is_none());
+        let no_magnet = add_if_cached(
+            &provider("downloaded", "/Inception.mkv"),
+            &Release::default(),
+        )
+        .await
+        .unwrap();
+        assert!(no_magnet.is_none());
+    }
+
+    #[tokio::test]
+    async fn grabs_round_trip() {
+        let db = Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        );
+        {
+            let write_txn = db.begin_write().unwrap();
+            write_txn.open_table(GRABS_TABLE).unwrap();
+            write_txn.commit().unwrap();
+        }
+        let grab = Grab {
+            release: "Inception.2010.1080p".to_string(),
+            hash: "abc".to_string(),
+            torrent_id: "new".to_string(),
+            grabbed_at: 1,
+        };
+        record_grab(&db, "movie:27205", &grab).await.unwrap();
+        assert_eq!(load_grabs(&db).await.get("movie:27205"), Some(&grab));
+    }
+}
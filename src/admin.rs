@@ -0,0 +1,826 @@
+//! JSON admin API for auditing and correcting the library, served under `/_api/` on the
+//! same port as WebDAV, plus a small embedded dashboard at `/_admin` that drives it.
+//! Disabled unless `ADMIN_API_TOKEN` is set; every API request must carry
+//! `Authorization: Bearer <token>` because the API can delete torrents from the account.
+//! The dashboard page itself is static and asks for the token in the browser.
+//!
+//! Corrections (overrides, hidden torrents, re-identification) are written to redb or
+//! queued on the shared `ScanState` and take effect on the next scan, which is woken early.
+
+use crate::identification::Identification;
+use crate::provider::DebridProvider;
+use crate::rd_client::TorrentInfo;
+use crate::repair::{RepairManager, RepairState};
+use crate::tasks::{
+    load_corrections, load_identifications, set_hidden, set_override, Corrections, ScanProgress,
+    ScanState,
+};
+use crate::tmdb_client::{TmdbClient, TmdbSearchResult};
+use crate::vfs::{DebridVfs, MediaMetadata, MediaType};
+use bytes::Bytes;
+use dav_server::body::Body;
+use http_body_util::{BodyExt, Limited};
+use hyper::{Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Path prefix the admin API is mounted under.
+pub const API_PREFIX: &str = "/_api/";
+
+/// Path the dashboard is served at.
+pub const UI_PATH: &str = "/_admin";
+
+const UI_HTML: &str = include_str!("admin.html");
+
+/// Request bodies are small JSON documents; anything larger is rejected.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Whether a request path belongs to the admin API or dashboard rather than WebDAV.
+pub fn is_admin_path(path: &str) -> bool {
+    path.starts_with(API_PREFIX) || path.trim_end_matches('/') == UI_PATH
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    Status,
+    Search {
+        query: String,
+        media_type: MediaType,
+        year: Option<String>,
+    },
+    ListTorrents,
+    Review,
+    GetTorrent(String),
+    Reidentify(String),
+    SetOverride(String),
+    ClearOverride(String),
+    Hide(String),
+    Unhide(String),
+    DeleteTorrent(String),
+}
+
+fn route(method: &Method, path: &str, query: Option<&str>) -> Option<Route> {
+    let rest = path.strip_prefix(API_PREFIX)?.trim_end_matches('/');
+    let parts: Vec<&str> = rest.split('/').collect();
+    match (method, parts.as_slice()) {
+        (&Method::GET, ["status"]) => Some(Route::Status),
+        (&Method::GET, ["search"]) => Some(search_route(query.unwrap_or_default())),
+        (&Method::GET, ["torrents"]) => Some(Route::ListTorrents),
+        (&Method::GET, ["review"]) => Some(Route::Review),
+        (&Method::GET, ["torrents", id]) => Some(Route::GetTorrent(id.to_string())),
+        (&Method::DELETE, ["torrents", id]) => Some(Route::DeleteTorrent(id.to_string())),
+        (&Method::POST, ["torrents", id, "reidentify"]) => Some(Route::Reidentify(id.to_string())),
+        (&Method::PUT, ["torrents", id, "override"]) => Some(Route::SetOverride(id.to_string())),
+        (&Method::DELETE, ["torrents", id, "override"]) => {
+            Some(Route::ClearOverride(id.to_string()))
+        }
+        (&Method::PUT, ["torrents", id, "hidden"]) => Some(Route::Hide(id.to_string())),
+        (&Method::DELETE, ["torrents", id, "hidden"]) => Some(Route::Unhide(id.to_string())),
+        _ => None,
+    }
+}
+
+/// `?query=<title>&type=movie|show&year=<yyyy>`; `type` defaults to movie.
+fn search_route(query: &str) -> Route {
+    let mut search = String::new();
+    let mut media_type = MediaType::Movie;
+    let mut year = None;
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "query" => search = value.trim().to_string(),
+            "type" if value.eq_ignore_ascii_case("show") || value.eq_ignore_ascii_case("tv") => {
+                media_type = MediaType::Show
+            }
+            "year" if !value.trim().is_empty() => year = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    Route::Search {
+        query: search,
+        media_type,
+        year,
+    }
+}
+
+/// One torrent as reported by the API: what the provider holds, what the mapper decided,
+/// and where it ended up in the VFS.
+#[derive(Debug, Serialize)]
+struct TorrentEntry {
+    id: String,
+    info: TorrentInfo,
+    metadata: MediaMetadata,
+    /// Scores and strategy behind the match; absent for overrides and older matches.
+    identification: Option<Identification>,
+    /// A doubtful match that is neither overridden nor hidden yet.
+    needs_review: bool,
+    overridden: bool,
+    hidden: bool,
+    repair_state: RepairState,
+    paths: Vec<String>,
+}
+
+/// Scan progress, repair counts, the number of torrents TMDB could not identify and the
+/// length of the review queue.
+#[derive(Debug, Serialize)]
+struct Status {
+    progress: ScanProgress,
+    healthy: usize,
+    repairing: usize,
+    failed: usize,
+    unidentified: usize,
+    needs_review: usize,
+}
+
+/// A TMDB search result, pre-shaped as the `MediaMetadata` an override would pin.
+#[derive(Debug, Serialize)]
+struct SearchHit {
+    metadata: MediaMetadata,
+    original_title: Option<String>,
+    popularity: f64,
+}
+
+impl SearchHit {
+    fn new(result: TmdbSearchResult, media_type: MediaType) -> Self {
+        let year = result
+            .release_date
+            .map(|d| d.chars().filter(|c| c.is_ascii_digit()).take(4).collect())
+            .filter(|y: &String| !y.is_empty());
+        Self {
+            metadata: MediaMetadata {
+                title: result.title,
+                year,
+                media_type,
+                external_id: Some(format!("tmdb:{}", result.id)),
+            },
+            original_title: result.original_title,
+            popularity: result.popularity,
+        }
+    }
+}
+
+pub struct AdminApi {
+    token: String,
+    provider: Arc<dyn DebridProvider>,
+    tmdb: Arc<TmdbClient>,
+    vfs: Arc<RwLock<DebridVfs>>,
+    db: Arc<redb::Database>,
+    repair_manager: Arc<RepairManager>,
+    state: Arc<ScanState>,
+}
+
+impl AdminApi {
+    pub fn new(
+        token: String,
+        provider: Arc<dyn DebridProvider>,
+        tmdb: Arc<TmdbClient>,
+        vfs: Arc<RwLock<DebridVfs>>,
+        db: Arc<redb::Database>,
+        repair_manager: Arc<RepairManager>,
+        state: Arc<ScanState>,
+    ) -> Self {
+        Self {
+            token,
+            provider,
+            tmdb,
+            vfs,
+            db,
+            repair_manager,
+            state,
+        }
+    }
+
+    fn is_authorized<B>(&self, req: &Request<B>) -> bool {
+        req.headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .is_some_and(|t| {
+                // Constant-time, so response timing doesn't reveal how much of a guess matched.
+                !self.token.is_empty()
+                    && bool::from(t.trim().as_bytes().ct_eq(self.token.as_bytes()))
+            })
+    }
+
+    pub async fn handle<B>(&self, req: Request<B>) -> Response<Body>
+    where
+        B: hyper::body::Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if req.uri().path().trim_end_matches('/') == UI_PATH && req.method() == Method::GET {
+            let mut resp = Response::new(Body::from(UI_HTML));
+            resp.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static("text/html; charset=utf-8"),
+            );
+            return resp;
+        }
+        if !self.is_authorized(&req) {
+            return json_response(StatusCode::UNAUTHORIZED, &error_body("unauthorized"));
+        }
+        let Some(route) = route(req.method(), req.uri().path(), req.uri().query()) else {
+            return json_response(StatusCode::NOT_FOUND, &error_body("not found"));
+        };
+        let body = match Limited::new(req.into_body(), MAX_BODY_SIZE).collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(e) => {
+                warn!("Admin API: failed to read request body: {}", e);
+                return json_response(StatusCode::BAD_REQUEST, &error_body("unreadable body"));
+            }
+        };
+        let (status, value) = self.dispatch(route, body).await;
+        json_response(status, &value)
+    }
+
+    async fn dispatch(&self, route: Route, body: Bytes) -> (StatusCode, serde_json::Value) {
+        match route {
+            Route::Status => {
+                let (healthy, repairing, failed) = self.repair_manager.get_status_summary().await;
+                let library = self.state.library().await;
+                let unidentified = library
+                    .values()
+                    .filter(|(_, metadata)| metadata.external_id.is_none())
+                    .count();
+                // Polled often: count from the records alone, without building entries.
+                let corrections = load_corrections(&self.db).await;
+                let identifications = load_identifications(&self.db).await;
+                let needs_review = library
+                    .iter()
+                    .filter(|(id, (info, _))| {
+                        queued_for_review(&corrections, &info.hash, identifications.get(*id))
+                    })
+                    .count();
+                let status = Status {
+                    progress: self.state.progress().await,
+                    healthy,
+                    repairing,
+                    failed,
+                    unidentified,
+                    needs_review,
+                };
+                (StatusCode::OK, to_json(&status))
+            }
+            Route::Search {
+                query,
+                media_type,
+                year,
+            } => {
+                if query.is_empty() {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        error_body("query must not be empty"),
+                    );
+                }
+                let results = match media_type {
+                    MediaType::Movie => self.tmdb.search_movie(&query, year.as_deref()).await,
+                    MediaType::Show => self.tmdb.search_tv(&query, year.as_deref()).await,
+                };
+                let results = match results {
+                    Ok(results) => results,
+                    Err(e) => return (StatusCode::BAD_GATEWAY, error_body(&e.to_string())),
+                };
+                let hits: Vec<SearchHit> = results
+                    .into_iter()
+                    .map(|r| SearchHit::new(r, media_type.clone()))
+                    .collect();
+                (StatusCode::OK, to_json(&hits))
+            }
+            Route::ListTorrents => {
+                let mut entries = self.entries().await;
+                entries.sort_by(|a, b| a.info.filename.cmp(&b.info.filename));
+                (StatusCode::OK, to_json(&entries))
+            }
+            Route::Review => {
+                // Least certain first: unscored matches, then by ascending score.
+                let mut entries: Vec<TorrentEntry> = self
+                    .entries()
+                    .await
+                    .into_iter()
+                    .filter(|e| e.needs_review)
+                    .collect();
+                entries.sort_by(|a, b| {
+                    let score = |e: &TorrentEntry| e.identification.as_ref().and_then(|i| i.score);
+                    score(a)
+                        .partial_cmp(&score(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then_with(|| a.info.filename.cmp(&b.info.filename))
+                });
+                (StatusCode::OK, to_json(&entries))
+            }
+            Route::GetTorrent(id) => match self.entries().await.into_iter().find(|e| e.id == id) {
+                Some(entry) => (StatusCode::OK, to_json(&entry)),
+                None => not_found(&id),
+            },
+            Route::Reidentify(id) => {
+                if self.state.entry(&id).await.is_none() {
+                    return not_found(&id);
+                }
+                info!("Admin API: re-identification requested for {}", id);
+                self.state.request_reidentify(&id).await;
+                (
+                    StatusCode::ACCEPTED,
+                    serde_json::json!({ "status": "queued" }),
+                )
+            }
+            Route::SetOverride(id) => {
+                let Some((info, _)) = self.state.entry(&id).await else {
+                    return not_found(&id);
+                };
+                let metadata: MediaMetadata = match serde_json::from_slice(&body) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            error_body(&format!("invalid metadata: {}", e)),
+                        )
+                    }
+                };
+                if metadata.title.trim().is_empty() {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        error_body("title must not be empty"),
+                    );
+                }
+                info!(
+                    "Admin API: overriding {} ({}) as {:?}",
+                    info.filename, info.hash, metadata
+                );
+                self.write_result(set_override(&self.db, &info.hash, Some(&metadata)).await)
+            }
+            Route::ClearOverride(id) => {
+                let Some((info, _)) = self.state.entry(&id).await else {
+                    return not_found(&id);
+                };
+                info!("Admin API: clearing override for {}", info.filename);
+                let result = set_override(&self.db, &info.hash, None).await;
+                // The persisted match may have come from the override rather than TMDB, so
+                // identify afresh once the override is gone.
+                if result.is_ok() {
+                    self.state.request_reidentify(&id).await;
+                }
+                self.write_result(result)
+            }
+            Route::Hide(id) => {
+                let Some((info, _)) = self.state.entry(&id).await else {
+                    return not_found(&id);
+                };
+                info!("Admin API: hiding {}", info.filename);
+                self.write_result(set_hidden(&self.db, &info.hash, Some(&info.filename)).await)
+            }
+            Route::Unhide(id) => {
+                let Some((info, _)) = self.state.entry(&id).await else {
+                    return not_found(&id);
+                };
+                info!("Admin API: unhiding {}", info.filename);
+                self.write_result(set_hidden(&self.db, &info.hash, None).await)
+            }
+            Route::DeleteTorrent(id) => {
+                if self.state.entry(&id).await.is_none() {
+                    return not_found(&id);
+                }
+                info!("Admin API: deleting torrent {}", id);
+                match self.provider.delete_torrent(&id).await {
+                    Ok(()) => {
+                        self.state.request_rescan();
+                        (StatusCode::OK, serde_json::json!({ "status": "deleted" }))
+                    }
+                    Err(e) => (
+                        StatusCode::BAD_GATEWAY,
+                        error_body(&format!("{} delete failed: {}", self.provider.name(), e)),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Acknowledge a database write and wake the scan loop so it applies promptly.
+    fn write_result(&self, result: Result<(), redb::Error>) -> (StatusCode, serde_json::Value) {
+        match result {
+            Ok(()) => {
+                self.state.request_rescan();
+                (StatusCode::OK, serde_json::json!({ "status": "ok" }))
+            }
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_body(&format!("database error: {}", e)),
+            ),
+        }
+    }
+
+    async fn entries(&self) -> Vec<TorrentEntry> {
+        let library = self.state.library().await;
+        let corrections = load_corrections(&self.db).await;
+        let mut identifications = load_identifications(&self.db).await;
+        let repair_states = self.repair_manager.repair_states().await;
+        let mut paths = self.vfs.read().await.torrent_paths();
+        library
+            .into_iter()
+            .map(|(id, (info, metadata))| {
+                let mut torrent_paths = paths.remove(&id).unwrap_or_default();
+                torrent_paths.sort();
+                let overridden = corrections.override_for(&info.hash).is_some();
+                let hidden = corrections.is_hidden(&info.hash);
+                let identification = identifications.remove(&id);
+                TorrentEntry {
+                    needs_review: queued_for_review(
+                        &corrections,
+                        &info.hash,
+                        identification.as_ref(),
+                    ),
+                    identification,
+                    overridden,
+                    hidden,
+                    repair_state: repair_states
+                        .get(&id)
+                        .cloned()
+                        .unwrap_or(RepairState::Healthy),
+                    paths: torrent_paths,
+                    id,
+                    info,
+                    metadata,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A doubtful match that is neither overridden nor hidden yet.
+fn queued_for_review(
+    corrections: &Corrections,
+    hash: &str,
+    identification: Option<&Identification>,
+) -> bool {
+    corrections.override_for(hash).is_none()
+        && !corrections.is_hidden(hash)
+        && identification.is_some_and(|i| i.needs_review())
+}
+
+fn to_json<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_else(|e| error_body(&e.to_string()))
+}
+
+fn error_body(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+fn not_found(id: &str) -> (StatusCode, serde_json::Value) {
+    (
+        StatusCode::NOT_FOUND,
+        error_body(&format!("unknown torrent {}", id)),
+    )
+}
+
+fn json_response(status: StatusCode, value: &serde_json::Value) -> Response<Body> {
+    let mut resp = Response::new(Body::from(value.to_string()));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identification::MatchStrategy;
+    use crate::provider::MockProvider;
+    use crate::tasks::IDENTIFICATIONS_TABLE;
+    use http_body_util::Full;
+
+    #[test]
+    fn route_parses_known_paths() {
+        assert_eq!(
+            route(&Method::GET, "/_api/torrents", None),
+            Some(Route::ListTorrents)
+        );
+        assert_eq!(
+            route(&Method::GET, "/_api/torrents/", None),
+            Some(Route::ListTorrents)
+        );
+        assert_eq!(
+            route(&Method::GET, "/_api/review", None),
+            Some(Route::Review)
+        );
+        assert_eq!(
+            route(&Method::GET, "/_api/torrents/abc", None),
+            Some(Route::GetTorrent("abc".to_string()))
+        );
+        assert_eq!(
+            route(&Method::POST, "/_api/torrents/abc/reidentify", None),
+            Some(Route::Reidentify("abc".to_string()))
+        );
+        assert_eq!(
+            route(&Method::PUT, "/_api/torrents/abc/override", None),
+            Some(Route::SetOverride("abc".to_string()))
+        );
+        assert_eq!(
+            route(&Method::DELETE, "/_api/torrents/abc/hidden", None),
+            Some(Route::Unhide("abc".to_string()))
+        );
+        assert_eq!(
+            route(&Method::DELETE, "/_api/torrents/abc", None),
+            Some(Route::DeleteTorrent("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn route_parses_search_query() {
+        assert_eq!(
+            route(
+                &Method::GET,
+                "/_api/search",
+                Some("query=The%20Office&type=show&year=2005")
+            ),
+            Some(Route::Search {
+                query: "The Office".to_string(),
+                media_type: MediaType::Show,
+                year: Some("2005".to_string()),
+            })
+        );
+        assert_eq!(
+            route(&Method::GET, "/_api/search", Some("query=Heat&year=")),
+            Some(Route::Search {
+                query: "Heat".to_string(),
+                media_type: MediaType::Movie,
+                year: None,
+            })
+        );
+    }
+
+    #[test]
+    fn admin_paths_cover_api_and_dashboard_only() {
+        assert!(is_admin_path("/_api/torrents"));
+        assert!(is_admin_path("/_admin"));
+        assert!(is_admin_path("/_admin/"));
+        assert!(!is_admin_path("/Movies"));
+        assert!(!is_admin_path("/_administrator"));
+    }
+
+    #[test]
+    fn search_hit_shapes_override_metadata() {
+        let result = TmdbSearchResult {
+            id: 1396,
+            title: "Breaking Bad".to_string(),
+            original_title: None,
+            release_date: Some("2008-01-20".to_string()),
+            popularity: 1.0,
+            vote_average: None,
+            vote_count: None,
+            alternative_titles: Vec::new(),
+        };
+        let hit = SearchHit::new(result, MediaType::Show);
+        assert_eq!(hit.metadata.title, "Breaking Bad");
+        assert_eq!(hit.metadata.year.as_deref(), Some("2008"));
+        assert_eq!(hit.metadata.external_id.as_deref(), Some("tmdb:1396"));
+
+        let undated = TmdbSearchResult {
+            release_date: Some(String::new()),
+            ..hit_result()
+        };
+        assert_eq!(
+            SearchHit::new(undated, MediaType::Movie).metadata.year,
+            None
+        );
+    }
+
+    fn hit_result() -> TmdbSearchResult {
+        TmdbSearchResult {
+            id: 1,
+            title: "X".to_string(),
+            original_title: None,
+            release_date: None,
+            popularity: 0.0,
+            vote_average: None,
+            vote_count: None,
+            alternative_titles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn route_rejects_unknown_paths_and_methods() {
+        assert_eq!(route(&Method::GET, "/Movies/x", None), None);
+        assert_eq!(route(&Method::POST, "/_api/torrents", None), None);
+        assert_eq!(
+            route(&Method::GET, "/_api/torrents/abc/override", None),
+            None
+        );
+        assert_eq!(route(&Method::GET, "/_api/nope", None), None);
+    }
+
+    async fn test_api() -> (AdminApi, Arc<ScanState>, Arc<redb::Database>) {
+        let provider: Arc<dyn DebridProvider> = Arc::new(MockProvider::default());
+        let db = Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        );
+        let state = Arc::new(ScanState::default());
+        let info = TorrentInfo {
+            id: "t1".to_string(),
+            hash: "HASH1".to_string(),
+            filename: "Wrong.Movie.2010.mkv".to_string(),
+            ..Default::default()
+        };
+        let metadata = MediaMetadata {
+            title: "Wrong Movie".to_string(),
+            year: Some("2010".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:1".to_string()),
+        };
+        state.publish(&[(info, metadata)]).await;
+        let api = AdminApi::new(
+            "secret".to_string(),
+            provider.clone(),
+            Arc::new(TmdbClient::new(String::new()).unwrap()),
+            Arc::new(RwLock::new(DebridVfs::new())),
+            db.clone(),
+            Arc::new(RepairManager::new(provider)),
+            state.clone(),
+        );
+        (api, state, db)
+    }
+
+    fn request(
+        method: Method,
+        path: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> Request<Full<Bytes>> {
+        let mut builder = Request::builder().method(method).uri(path);
+        if let Some(t) = token {
+            builder = builder.header("Authorization", format!("Bearer {}", t));
+        }
+        builder
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn handle_requires_bearer_token() {
+        let (api, _, _) = test_api().await;
+        let resp = api
+            .handle(request(Method::GET, "/_api/torrents", None, ""))
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = api
+            .handle(request(Method::GET, "/_api/torrents", Some("wrong"), ""))
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = api
+            .handle(request(Method::GET, "/_api/torrents", Some("secret"), ""))
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn list_reports_metadata_and_correction_flags() {
+        let (api, _, db) = test_api().await;
+        set_hidden(&db, "hash1", Some("Wrong.Movie.2010.mkv"))
+            .await
+            .unwrap();
+        let (status, value) = api.dispatch(Route::ListTorrents, Bytes::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["id"], "t1");
+        assert_eq!(entries[0]["metadata"]["title"], "Wrong Movie");
+        assert_eq!(entries[0]["hidden"], true);
+        assert_eq!(entries[0]["overridden"], false);
+        assert_eq!(entries[0]["repair_state"], "Healthy");
+    }
+
+    #[tokio::test]
+    async fn override_is_persisted_by_hash() {
+        let (api, _, db) = test_api().await;
+        let body = r#"{"title":"Inception","year":"2010","media_type":"Movie","external_id":"tmdb:27205"}"#;
+        let (status, _) = api
+            .dispatch(Route::SetOverride("t1".to_string()), Bytes::from(body))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let corrections = load_corrections(&db).await;
+        assert_eq!(
+            corrections
+                .override_for("hash1")
+                .and_then(|m| m.external_id.clone()),
+            Some("tmdb:27205".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn override_rejects_invalid_body() {
+        let (api, _, _) = test_api().await;
+        let (status, _) = api
+            .dispatch(Route::SetOverride("t1".to_string()), Bytes::from("{}"))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn clearing_override_queues_reidentification() {
+        let (api, state, _) = test_api().await;
+        let (status, _) = api
+            .dispatch(Route::ClearOverride("t1".to_string()), Bytes::new())
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(state.take_reidentify().await.contains("t1"));
+    }
+
+    #[tokio::test]
+    async fn dashboard_is_served_without_token() {
+        let (api, _, _) = test_api().await;
+        let resp = api.handle(request(Method::GET, "/_admin", None, "")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(hyper::header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn status_counts_unidentified_torrents() {
+        let (api, state, _) = test_api().await;
+        let info = TorrentInfo {
+            id: "t2".to_string(),
+            hash: "hash2".to_string(),
+            filename: "mystery.mkv".to_string(),
+            ..Default::default()
+        };
+        let unidentified = MediaMetadata {
+            title: "mystery".to_string(),
+            year: None,
+            media_type: MediaType::Movie,
+            external_id: None,
+        };
+        let mut library: Vec<_> = state.library().await.into_values().collect();
+        library.push((info, unidentified));
+        state.publish(&library).await;
+
+        let (status, value) = api.dispatch(Route::Status, Bytes::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(value["unidentified"], 1);
+        assert_eq!(value["progress"]["library_size"], 2);
+        assert_eq!(value["progress"]["scanning"], false);
+    }
+
+    #[tokio::test]
+    async fn review_lists_doubtful_matches_until_overridden() {
+        let (api, _, db) = test_api().await;
+        let record = Identification {
+            metadata: MediaMetadata {
+                title: "Wrong Movie".to_string(),
+                year: Some("2010".to_string()),
+                media_type: MediaType::Movie,
+                external_id: Some("tmdb:1".to_string()),
+            },
+            query: "Wrong.Movie.2010.mkv".to_string(),
+            strategy: MatchStrategy::PrefixStripped,
+            score: Some(320.0),
+            runner_up: Some(310.0),
+        };
+        let bytes = serde_json::to_vec(&record).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(IDENTIFICATIONS_TABLE)
+            .unwrap()
+            .insert("t1", bytes.as_slice())
+            .unwrap();
+        write_txn.commit().unwrap();
+
+        let (status, value) = api.dispatch(Route::Review, Bytes::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["id"], "t1");
+        assert_eq!(entries[0]["identification"]["strategy"], "prefix_stripped");
+        assert_eq!(entries[0]["identification"]["runner_up"], 310.0);
+        let (_, status_value) = api.dispatch(Route::Status, Bytes::new()).await;
+        assert_eq!(status_value["needs_review"], 1);
+
+        let body = r#"{"title":"Inception","year":"2010","media_type":"Movie","external_id":"tmdb:27205"}"#;
+        api.dispatch(Route::SetOverride("t1".to_string()), Bytes::from(body))
+            .await;
+        let (_, value) = api.dispatch(Route::Review, Bytes::new()).await;
+        assert!(value.as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_requires_a_query() {
+        let (api, _, _) = test_api().await;
+        let route = search_route("type=movie");
+        let (status, _) = api.dispatch(route, Bytes::new()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn unknown_torrent_is_not_found() {
+        let (api, state, _) = test_api().await;
+        let (status, _) = api
+            .dispatch(Route::Reidentify("nope".to_string()), Bytes::new())
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(state.take_reidentify().await.is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+//! Anime mode: maps absolute episode numbers (`Show - 137.mkv`) onto TMDB seasons. Each
+//! show's absolute episode order comes from TMDB and is cached in redb, so the VFS can
+//! place an absolute-numbered file in `Season XX` as `SxxEyy` without a request. The
+//! order is taken from the show's absolute (or "Seasons") episode group when TMDB has
+//! one, since season episode counts alone miscount shows with recaps or split cours.
+
+use crate::rd_client::TorrentInfo;
+use crate::tmdb_client::{TmdbClient, TmdbEpisodeGroup, TmdbEpisodeGroupSummary};
+use crate::vfs::{env_flag, is_video_file, parse_absolute_episode, MediaMetadata, MediaType};
+use redb::{ReadableDatabase, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+/// Absolute episode order per show, keyed by TMDB id, value a JSON [`StoredOrder`].
+/// Entries in the older `[season, episode_count]` list format are ignored and refetched.
+pub const ANIME_SEASONS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("anime_seasons");
+
+/// Shows fetched per refresh.
+const MAX_SHOWS_PER_REFRESH: usize = 20;
+/// Before refetching a show whose cached seasons don't reach an episode in the library.
+const RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+/// TMDB's episode group type for absolute ordering.
+const ABSOLUTE_GROUP: u32 = 2;
+
+/// A show's episodes as `(season, episode)` in absolute order: absolute episode `n` is
+/// `episodes[n - 1]`.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredOrder {
+    episodes: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Default)]
+pub struct AnimeMapping {
+    orders: RwLock<HashMap<u32, Vec<(u32, u32)>>>,
+    attempted: Mutex<HashMap<u32, Instant>>,
+}
+
+impl AnimeMapping {
+    /// Enabled by `ANIME_MODE=true`; loads the episode orders cached in `db`.
+    pub async fn from_env(db: &Arc<redb::Database>) -> Option<Self> {
+        if !env_flag("ANIME_MODE") {
+            return None;
+        }
+        Some(Self::load(db).await)
+    }
+
+    pub async fn load(db: &Arc<redb::Database>) -> Self {
+        let db_clone = db.clone();
+        let orders = tokio::task::spawn_blocking(move || {
+            let mut orders = HashMap::new();
+            if let Ok(read_txn) = db_clone.begin_read() {
+                if let Ok(table) = read_txn.open_table(ANIME_SEASONS_TABLE) {
+                    if let Ok(iter) = table.iter() {
+                        for (key, value) in iter.flatten() {
+                            let Ok(tmdb_id) = key.value().parse() else {
+                                continue;
+                            };
+                            if let Ok(stored) = serde_json::from_slice::<StoredOrder>(value.value())
+                            {
+                                orders.insert(tmdb_id, stored.episodes);
+                            }
+                        }
+                    }
+                }
+            }
+            orders
+        })
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to load anime episode orders: {:?}", e);
+            HashMap::new()
+        });
+        Self {
+            orders: RwLock::new(orders),
+            attempted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Season and episode of absolute episode `absolute` of a show, if its order is
+    /// cached and reaches that far.
+    pub fn map(&self, tmdb_id: u32, absolute: u32) -> Option<(u32, u32)> {
+        let orders = self.orders.read().unwrap_or_else(|e| e.into_inner());
+        let index = usize::try_from(absolute.checked_sub(1)?).ok()?;
+        orders.get(&tmdb_id)?.get(index).copied()
+    }
+
+    /// Fetch episode orders for shows with absolute-numbered files that the cache can't
+    /// place. Returns whether any order changed, i.e. whether the VFS should be rebuilt.
+    pub async fn refresh(
+        &self,
+        db: &Arc<redb::Database>,
+        tmdb: &TmdbClient,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> bool {
+        let unmapped: Vec<u32> = absolute_shows(library)
+            .into_iter()
+            .filter(|&(tmdb_id, highest)| self.map(tmdb_id, highest).is_none())
+            .map(|(tmdb_id, _)| tmdb_id)
+            .collect();
+        let due: Vec<u32> = {
+            let mut attempted = self.attempted.lock().await;
+            let due: Vec<_> = unmapped
+                .into_iter()
+                .filter(|id| {
+                    attempted
+                        .get(id)
+                        .is_none_or(|at| at.elapsed() >= RETRY_AFTER)
+                })
+                .take(MAX_SHOWS_PER_REFRESH)
+                .collect();
+            for id in &due {
+                attempted.insert(*id, Instant::now());
+            }
+            due
+        };
+
+        let mut changed = false;
+        for tmdb_id in due {
+            let Some(order) = fetch_order(tmdb, tmdb_id).await else {
+                continue;
+            };
+            if let Err(e) = store(db, tmdb_id, &order).await {
+                error!("Failed to cache episode order for tmdb:{}: {}", tmdb_id, e);
+            }
+            let mut orders = self.orders.write().unwrap_or_else(|e| e.into_inner());
+            if orders.get(&tmdb_id) != Some(&order) {
+                orders.insert(tmdb_id, order);
+                changed = true;
+            }
+        }
+        if changed {
+            info!("Anime: season mapping updated, rebuilding VFS");
+        }
+        changed
+    }
+}
+
+/// A show's absolute episode order: from its absolute episode group, else its "Seasons"
+/// group, else its regular seasons one after another. `None` if TMDB can't be reached.
+async fn fetch_order(tmdb: &TmdbClient, tmdb_id: u32) -> Option<Vec<(u32, u32)>> {
+    let groups = tmdb.tv_episode_groups(tmdb_id).await.unwrap_or_default();
+    if let Some(preferred) = preferred_group(&groups) {
+        if let Some(group) = tmdb.episode_group(&preferred.id).await {
+            let order = group_order(group);
+            if !order.is_empty() {
+                return Some(order);
+            }
+        }
+    }
+    let details = tmdb.tv_details(tmdb_id).await?;
+    let mut counts: Vec<(u32, u32)> = details
+        .seasons
+        .iter()
+        .filter(|s| s.season_number > 0 && s.episode_count > 0)
+        .map(|s| (s.season_number, s.episode_count))
+        .collect();
+    counts.sort_unstable();
+    Some(order_from_counts(&counts))
+}
+
+/// The episode group that numbers a show the way absolute releases do.
+fn preferred_group(groups: &[TmdbEpisodeGroupSummary]) -> Option<&TmdbEpisodeGroupSummary> {
+    groups
+        .iter()
+        .find(|g| g.kind == ABSOLUTE_GROUP)
+        .or_else(|| {
+            groups
+                .iter()
+                .find(|g| g.name.trim().eq_ignore_ascii_case("seasons"))
+        })
+}
+
+/// The group's episodes in its order, by their regular season and episode numbers.
+fn group_order(mut group: TmdbEpisodeGroup) -> Vec<(u32, u32)> {
+    group.groups.sort_by_key(|part| part.order);
+    group
+        .groups
+        .into_iter()
+        .flat_map(|mut part| {
+            part.episodes.sort_by_key(|e| e.order);
+            part.episodes
+        })
+        .map(|e| (e.season_number, e.episode_number))
+        .collect()
+}
+
+/// Seasons one after another, from `(season, episode_count)` in season order.
+fn order_from_counts(counts: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    counts
+        .iter()
+        .flat_map(|&(season, count)| (1..=count).map(move |episode| (season, episode)))
+        .collect()
+}
+
+/// TMDB ids of shows with absolute-numbered video files, with the highest absolute
+/// number each provides.
+fn absolute_shows(library: &HashMap<String, (TorrentInfo, MediaMetadata)>) -> BTreeMap<u32, u32> {
+    let mut shows = BTreeMap::new();
+    for (info, metadata) in library.values() {
+        if metadata.media_type != MediaType::Show {
+            continue;
+        }
+        let Some(tmdb_id) = metadata.tmdb_id() else {
+            continue;
+        };
+        for file in info
+            .files
+            .iter()
+            .filter(|f| f.selected == 1 && is_video_file(&f.path))
+        {
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            if let Some(absolute) = parse_absolute_episode(filename) {
+                let highest = shows.entry(tmdb_id).or_insert(0);
+                *highest = absolute.max(*highest);
+            }
+        }
+    }
+    shows
+}
+
+async fn store(
+    db: &Arc<redb::Database>,
+    tmdb_id: u32,
+    order: &[(u32, u32)],
+) -> Result<(), redb::Error> {
+    let db = db.clone();
+    let key = tmdb_id.to_string();
+    let stored = StoredOrder {
+        episodes: order.to_vec(),
+    };
+    let bytes =
+        serde_json::to_vec(&stored).map_err(|e| redb::Error::Io(std::io::Error::other(e)))?;
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(ANIME_SEASONS_TABLE)?;
+            table.insert(key.as_str(), bytes.as_slice())?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rd_client::TorrentFile;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer each request with the body routed to its path (404 for others); yields the
+    /// number of requests served.
+    async fn spawn_routes(
+        routes: &'static [(&'static str, &'static str)],
+    ) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = served.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut sock, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 2048];
+                let n = sock.read(&mut buf).await.unwrap();
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let target = request.split_whitespace().nth(1).unwrap_or_default();
+                let path = target.split('?').next().unwrap_or_default();
+                let (status, body) = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map_or(("404 Not Found", "{}"), |(_, body)| ("200 OK", *body));
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = sock.write_all(head.as_bytes()).await;
+                let _ = sock.write_all(body.as_bytes()).await;
+                let _ = sock.flush().await;
+            }
+        });
+        (format!("http://{}", addr), served)
+    }
+
+    fn in_memory_db() -> Arc<redb::Database> {
+        Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn order_from_counts_walks_season_counts() {
+        let mapping = AnimeMapping::default();
+        mapping
+            .orders
+            .write()
+            .unwrap()
+            .insert(1, order_from_counts(&[(1, 12), (2, 13), (3, 24)]));
+        assert_eq!(mapping.map(1, 1), Some((1, 1)));
+        assert_eq!(mapping.map(1, 12), Some((1, 12)));
+        assert_eq!(mapping.map(1, 13), Some((2, 1)));
+        assert_eq!(mapping.map(1, 49), Some((3, 24)));
+        assert_eq!(mapping.map(1, 50), None);
+        assert_eq!(mapping.map(1, 0), None);
+    }
+
+    #[test]
+    fn preferred_group_takes_absolute_then_seasons() {
+        let group = |id: &str, name: &str, kind| TmdbEpisodeGroupSummary {
+            id: id.to_string(),
+            name: name.to_string(),
+            kind,
+        };
+        let groups = vec![
+            group("dvd", "DVD Order", 3),
+            group("seasons", "Seasons", 6),
+            group("abs", "Absolute Order", 2),
+        ];
+        assert_eq!(preferred_group(&groups).unwrap().id, "abs");
+        assert_eq!(preferred_group(&groups[..2]).unwrap().id, "seasons");
+        assert!(preferred_group(&groups[..1]).is_none());
+    }
+
+    #[tokio::test]
+    async fn refresh_prefers_the_absolute_episode_group() {
+        // TMDB's seasons would put absolute 3 at S01E03, but the absolute ordering skips
+        // the recap S01E02 and continues into season 2.
+        static ROUTES: &[(&str, &str)] = &[
+            (
+                "/tv/37854/episode_groups",
+                r#"{"results":[{"id":"dvd","name":"DVD","type":3},
+                    {"id":"abs","name":"Absolute","type":2}]}"#,
+            ),
+            (
+                "/tv/episode_group/abs",
+                r#"{"id":"abs","groups":[
+                    {"order":2,"episodes":[{"season_number":2,"episode_number":1,"order":0}]},
+                    {"order":1,"episodes":[
+                        {"season_number":1,"episode_number":3,"order":1},
+                        {"season_number":1,"episode_number":1,"order":0}]}]}"#,
+            ),
+        ];
+        let (base, served) = spawn_routes(ROUTES).await;
+        let tmdb = TmdbClient::with_base_url("key".to_string(), base).unwrap();
+        let db = in_memory_db();
+        let library = HashMap::from([one_piece("One Piece - 003.mkv")]);
+
+        let mapping = AnimeMapping::load(&db).await;
+        assert!(mapping.refresh(&db, &tmdb, &library).await);
+        assert_eq!(mapping.map(37854, 1), Some((1, 1)));
+        assert_eq!(mapping.map(37854, 2), Some((1, 3)));
+        assert_eq!(mapping.map(37854, 3), Some((2, 1)));
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(AnimeMapping::load(&db).await.map(37854, 3), Some((2, 1)));
+    }
+
+    fn one_piece(filename: &str) -> (String, (TorrentInfo, MediaMetadata)) {
+        let info = TorrentInfo {
+            files: vec![TorrentFile {
+                id: 1,
+                path: format!("/OP/{}", filename),
+                bytes: 1,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let metadata = MediaMetadata {
+            title: "One Piece".to_string(),
+            year: Some("1999".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:37854".to_string()),
+        };
+        ("t1".to_string(), (info, metadata))
+    }
+
+    #[tokio::test]
+    async fn refresh_falls_back_to_season_counts() {
+        static ROUTES: &[(&str, &str)] = &[
+            ("/tv/37854/episode_groups", r#"{"results":[]}"#),
+            (
+                "/tv/37854",
+                r#"{"id":37854,"name":"One Piece","seasons":[
+                    {"season_number":0,"episode_count":30,"air_date":null},
+                    {"season_number":2,"episode_count":16,"air_date":"2000-01-01"},
+                    {"season_number":1,"episode_count":61,"air_date":"1999-10-20"}]}"#,
+            ),
+        ];
+        let (base, served) = spawn_routes(ROUTES).await;
+        let tmdb = TmdbClient::with_base_url("key".to_string(), base).unwrap();
+        let db = in_memory_db();
+        // A cache entry in the old season-count format is ignored and refetched.
+        {
+            let write_txn = db.begin_write().unwrap();
+            write_txn
+                .open_table(ANIME_SEASONS_TABLE)
+                .unwrap()
+                .insert("37854", br#"[[1,100]]"#.as_slice())
+                .unwrap();
+            write_txn.commit().unwrap();
+        }
+        let library = HashMap::from([one_piece("[Sub] One Piece - 063 [1080p].mkv")]);
+
+        let mapping = AnimeMapping::load(&db).await;
+        assert_eq!(mapping.map(37854, 63), None);
+        assert!(mapping.refresh(&db, &tmdb, &library).await);
+        assert_eq!(mapping.map(37854, 63), Some((2, 2)));
+        // Already placeable: no further requests.
+        assert!(!mapping.refresh(&db, &tmdb, &library).await);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(AnimeMapping::load(&db).await.map(37854, 61), Some((1, 61)));
+    }
+
+    #[test]
+    fn build_places_absolute_episodes_in_their_season() {
+        let mapping = AnimeMapping::default();
+        mapping
+            .orders
+            .write()
+            .unwrap()
+            .insert(37854, order_from_counts(&[(1, 61), (2, 16)]));
+        let info = TorrentInfo {
+            id: "t1".to_string(),
+            files: [
+                "One Piece - 001.mkv",
+                "One Piece - 063.mkv",
+                "One Piece - 999.mkv",
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| TorrentFile {
+                id: i as u32 + 1,
+                path: format!("/OP/{}", name),
+                bytes: 1,
+                selected: 1,
+            })
+            .collect(),
+            ..Default::default()
+        };
+        let metadata = MediaMetadata {
+            title: "One Piece".to_string(),
+            year: Some("1999".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:37854".to_string()),
+        };
+        let options = crate::vfs::VfsOptions {
+            anime: Some(Arc::new(mapping)),
+            ..Default::default()
+        };
+        let vfs = crate::vfs::DebridVfs::build_with(vec![(info, metadata)], Arc::new(options));
+        let mut paths = vfs.torrent_paths().remove("t1").unwrap();
+        paths.sort();
+        let show = "Shows/One Piece [tmdbid-37854]";
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/Season 01/One Piece - 999.mkv", show),
+                format!("{}/Season 01/One Piece - S01E01.mkv", show),
+                format!("{}/Season 02/One Piece - S02E02.mkv", show),
+            ]
+        );
+    }
+}
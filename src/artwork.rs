@@ -0,0 +1,314 @@
+//! Artwork files (`poster.jpg`, `fanart.jpg`, `seasonXX-poster.jpg`) exposed in media
+//! folders. Images are fetched from TMDB's image CDN the first time a client reads them,
+//! and kept on disk so later reads don't touch the network. Listings only need a size,
+//! which comes from the cached file or a `HEAD` request.
+
+use crate::vfs::env_flag;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Largest image accepted from the CDN; TMDB originals are a few MB at most.
+const MAX_IMAGE_SIZE: usize = 20 * 1024 * 1024;
+/// Listing a folder waits on `HEAD` requests, so they get a much shorter timeout than
+/// image downloads.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+/// Before retrying an image the CDN failed to serve.
+const RETRY_FAILED_AFTER: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug)]
+pub struct ArtworkCache {
+    dir: PathBuf,
+    http_client: reqwest::Client,
+    /// Byte sizes of images on disk or reported by the CDN, by URL.
+    sizes: RwLock<HashMap<String, u64>>,
+    /// When the CDN last failed to serve an image, by URL.
+    failed: RwLock<HashMap<String, Instant>>,
+}
+
+impl ArtworkCache {
+    pub fn new(dir: impl Into<PathBuf>, http_client: reqwest::Client) -> Self {
+        Self {
+            dir: dir.into(),
+            http_client,
+            sizes: RwLock::new(HashMap::new()),
+            failed: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Enabled by `ARTWORK=true`; images are cached under `ARTWORK_CACHE_DIR`
+    /// (default `artwork`).
+    pub fn from_env() -> Option<Self> {
+        if !env_flag("ARTWORK") {
+            return None;
+        }
+        let dir = std::env::var("ARTWORK_CACHE_DIR")
+            .ok()
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or_else(|| "artwork".to_string());
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .ok()?;
+        Some(Self::new(dir, http_client))
+    }
+
+    /// Disk path for an image URL: its last path segment (TMDB image names are unique
+    /// hashes), or `None` if that isn't a plain file name.
+    fn path_for(&self, url: &str) -> Option<PathBuf> {
+        let name = url.rsplit('/').next()?;
+        let plain = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        plain.then(|| self.dir.join(name))
+    }
+
+    /// Size of the image without downloading it: from the cached file, else the CDN's
+    /// `Content-Length`. `None` if the image can't be served.
+    pub async fn size(&self, url: &str) -> Option<u64> {
+        if let Some(&size) = self
+            .sizes
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(url)
+        {
+            return Some(size);
+        }
+        if self.recently_failed(url) {
+            return None;
+        }
+        let path = self.path_for(url)?;
+        if let Ok(Ok(meta)) = tokio::task::spawn_blocking(move || std::fs::metadata(path)).await {
+            self.remember(url, meta.len() as usize);
+            return Some(meta.len());
+        }
+
+        let resp = match self
+            .http_client
+            .head(url)
+            .timeout(HEAD_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => resp,
+            Ok(resp) => {
+                warn!("Artwork HEAD for {} returned {}", url, resp.status());
+                self.remember_failure(url);
+                return None;
+            }
+            Err(e) => {
+                warn!("Artwork HEAD for {} failed: {}", url, e);
+                self.remember_failure(url);
+                return None;
+            }
+        };
+        // `Response::content_length` reports the (empty) body of a HEAD, not the header.
+        let length = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&len| len > 0);
+        match length {
+            Some(len) if len as usize <= MAX_IMAGE_SIZE => {
+                self.remember(url, len as usize);
+                Some(len)
+            }
+            Some(len) => {
+                warn!("Artwork {} has unexpected size {}", url, len);
+                self.remember_failure(url);
+                None
+            }
+            // No length advertised: only the body can tell.
+            None => self.fetch(url).await.map(|bytes| bytes.len() as u64),
+        }
+    }
+
+    /// The image's bytes, from disk if cached, otherwise from the CDN (then cached).
+    pub async fn fetch(&self, url: &str) -> Option<Bytes> {
+        let path = self.path_for(url)?;
+        let cached = path.clone();
+        if let Ok(Ok(content)) = tokio::task::spawn_blocking(move || std::fs::read(cached)).await {
+            self.remember(url, content.len());
+            return Some(Bytes::from(content));
+        }
+        if self.recently_failed(url) {
+            return None;
+        }
+
+        let resp = match self.http_client.get(url).send().await {
+            Ok(resp) if resp.status().is_success() => resp,
+            Ok(resp) => {
+                warn!("Artwork fetch for {} returned {}", url, resp.status());
+                self.remember_failure(url);
+                return None;
+            }
+            Err(e) => {
+                warn!("Artwork fetch for {} failed: {}", url, e);
+                self.remember_failure(url);
+                return None;
+            }
+        };
+        let content = resp.bytes().await.ok()?;
+        if content.is_empty() || content.len() > MAX_IMAGE_SIZE {
+            warn!("Artwork {} has unexpected size {}", url, content.len());
+            self.remember_failure(url);
+            return None;
+        }
+        if let Err(e) = self.store(path.clone(), content.clone()).await {
+            warn!("Failed to cache artwork {}: {}", path.display(), e);
+        } else {
+            debug!("Cached artwork {}", path.display());
+        }
+        self.remember(url, content.len());
+        Some(content)
+    }
+
+    fn remember(&self, url: &str, size: usize) {
+        self.sizes
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(url.to_string(), size as u64);
+    }
+
+    fn remember_failure(&self, url: &str) {
+        self.failed
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(url.to_string(), Instant::now());
+    }
+
+    fn recently_failed(&self, url: &str) -> bool {
+        self.failed
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(url)
+            .is_some_and(|at| at.elapsed() < RETRY_FAILED_AFTER)
+    }
+
+    /// Write through a temporary file so a crash never leaves a truncated image behind.
+    async fn store(&self, path: PathBuf, content: Bytes) -> std::io::Result<()> {
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(dir)?;
+            let tmp = path.with_extension("part");
+            std::fs::write(&tmp, &content)?;
+            std::fs::rename(&tmp, &path)
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer every request with `status` and `body` (headers only for `HEAD`); yields
+    /// the methods of the requests served.
+    async fn spawn_image(
+        status: &'static str,
+        body: &'static [u8],
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let seen = methods.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut sock, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 2048];
+                let n = sock.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let method = request.split_whitespace().next().unwrap_or_default();
+                seen.lock().unwrap().push(method.to_string());
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = sock.write_all(head.as_bytes()).await;
+                if method != "HEAD" {
+                    let _ = sock.write_all(body).await;
+                }
+                let _ = sock.flush().await;
+            }
+        });
+        (format!("http://{}", addr), methods)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("artwork-{}-{}", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn fetch_caches_images_on_disk() {
+        let dir = temp_dir("fetch");
+        let (base, methods) = spawn_image("200 OK", b"\xff\xd8jpeg").await;
+        let url = format!("{}/t/p/original/abc123.jpg", base);
+
+        let cache = ArtworkCache::new(&dir, reqwest::Client::new());
+        assert_eq!(
+            cache.fetch(&url).await.as_deref(),
+            Some(&b"\xff\xd8jpeg"[..])
+        );
+        assert!(dir.join("abc123.jpg").exists());
+
+        // A fresh cache sizes and reads the image from disk.
+        let reloaded = ArtworkCache::new(&dir, reqwest::Client::new());
+        assert_eq!(reloaded.size(&url).await, Some(6));
+        assert_eq!(
+            reloaded.fetch(&url).await.as_deref(),
+            Some(&b"\xff\xd8jpeg"[..])
+        );
+        assert_eq!(*methods.lock().unwrap(), vec!["GET"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn size_asks_for_the_length_without_downloading() {
+        let dir = temp_dir("size");
+        let (base, methods) = spawn_image("200 OK", b"\xff\xd8jpeg").await;
+        let url = format!("{}/t/p/original/def456.jpg", base);
+
+        let cache = ArtworkCache::new(&dir, reqwest::Client::new());
+        assert_eq!(cache.size(&url).await, Some(6));
+        assert_eq!(cache.size(&url).await, Some(6));
+        assert!(!dir.join("def456.jpg").exists());
+        assert_eq!(*methods.lock().unwrap(), vec!["HEAD"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn failed_images_are_not_requested_again() {
+        let dir = temp_dir("failed");
+        let (base, methods) = spawn_image("404 Not Found", b"").await;
+        let url = format!("{}/t/p/original/gone.jpg", base);
+
+        let cache = ArtworkCache::new(&dir, reqwest::Client::new());
+        assert_eq!(cache.size(&url).await, None);
+        assert_eq!(cache.size(&url).await, None);
+        assert!(cache.fetch(&url).await.is_none());
+        assert_eq!(*methods.lock().unwrap(), vec!["HEAD"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn path_for_rejects_unsafe_names() {
+        let cache = ArtworkCache::new("/tmp/art", reqwest::Client::new());
+        assert_eq!(
+            cache.path_for("https://image.tmdb.org/t/p/original/x1-Y_2.jpg"),
+            Some(PathBuf::from("/tmp/art/x1-Y_2.jpg"))
+        );
+        assert_eq!(cache.path_for("https://image.tmdb.org/t/p/original/"), None);
+        assert_eq!(cache.path_for("https://host/..%2F..%2Fetc"), None);
+        assert_eq!(cache.path_for("https://host/.."), None);
+    }
+}
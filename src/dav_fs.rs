@@ -1,4 +1,5 @@
-use crate::provider::DebridProvider;
+use crate::artwork::ArtworkCache;
+use crate::provider::{DebridProvider, FileLocator};
 use crate::repair::RepairManager;
 use crate::vfs::{DebridVfs, VfsNode};
 use bytes::Bytes;
@@ -38,38 +39,66 @@ impl DebridFileSystem {
         }
     }
 
-    /// Resolve a path to a VfsNode reference without cloning.
-    fn find_node_ref<'v>(vfs: &'v DebridVfs, path: &DavPath) -> Option<&'v VfsNode> {
-        let mut current = &vfs.root;
+    /// Resolve a path to a VfsNode reference without cloning, along with its canonical
+    /// path (a collection link's movie folder, for paths below `Collections/`).
+    fn find_node_ref<'v>(vfs: &'v DebridVfs, path: &DavPath) -> Option<(&'v VfsNode, String)> {
         let path_osstr = path.as_rel_ospath();
-        let path_str = path_osstr.to_str()?;
-        if path_str == "." || path_str.is_empty() {
-            return Some(current);
-        }
-        for component in path_str.split('/') {
-            if component.is_empty() || component == "." {
-                continue;
-            }
-            if component == ".." {
-                return None;
-            }
-            if let VfsNode::Directory { children } = current {
-                current = children.get(component)?;
-            } else {
-                return None;
-            }
-        }
-        Some(current)
+        vfs.lookup(path_osstr.to_str()?)
     }
 
     fn find_node_in(vfs: &DebridVfs, path: &DavPath) -> Option<VfsNode> {
-        Self::find_node_ref(vfs, path).cloned()
+        Self::find_node_ref(vfs, path).map(|(node, _)| node.clone())
     }
 
     async fn find_node(&self, path: &DavPath) -> Option<VfsNode> {
         let vfs = self.vfs.read().await;
         Self::find_node_in(&vfs, path)
     }
+
+    /// Read up to `len` bytes at `pos` of a media file the way WebDAV reads do (resolving,
+    /// and if needed repairing, its link). Shorter only at the end of the file.
+    pub async fn read_range(
+        &self,
+        locator: FileLocator,
+        file_size: u64,
+        pos: u64,
+        len: usize,
+    ) -> Result<Bytes, FsError> {
+        let mut file = ProxiedMediaFile {
+            name: locator.file_path.clone(),
+            locator,
+            file_size,
+            repair_manager: self.repair_manager.clone(),
+            rd_client: self.rd_client.clone(),
+            http_client: self.http_client.clone(),
+            pos,
+            cdn_url: None,
+            buffer: Bytes::new(),
+            buffer_start: 0,
+        };
+        let mut out = Vec::with_capacity(len);
+        while out.len() < len {
+            let chunk = file.read_bytes(len - out.len()).await?;
+            if chunk.is_empty() {
+                break;
+            }
+            out.extend_from_slice(&chunk);
+        }
+        Ok(Bytes::from(out))
+    }
+
+    async fn artwork(&self) -> Option<Arc<ArtworkCache>> {
+        self.vfs.read().await.options.artwork.clone()
+    }
+
+    /// Artwork size from the cache's record, the cached file or a `HEAD` request; the image
+    /// itself is only downloaded when opened.
+    async fn artwork_size(&self, url: &str) -> u64 {
+        match self.artwork().await {
+            Some(artwork) => artwork.size(url).await.unwrap_or(0),
+            None => 0,
+        }
+    }
 }
 
 impl DavFileSystem for DebridFileSystem {
@@ -104,7 +133,22 @@ impl DavFileSystem for DebridFileSystem {
                     content: Bytes::from(content),
                     pos: 0,
                 }) as Box<dyn DavFile>),
-                VfsNode::Directory { .. } => Err(FsError::Forbidden),
+                VfsNode::CachedFile { path, .. } => {
+                    let content = tokio::task::spawn_blocking(move || std::fs::read(path))
+                        .await
+                        .map_err(|_| FsError::GeneralFailure)?
+                        .map_err(|_| FsError::NotFound)?;
+                    Ok(Box::new(VirtualFile {
+                        content: Bytes::from(content),
+                        pos: 0,
+                    }) as Box<dyn DavFile>)
+                }
+                VfsNode::Artwork { url } => {
+                    let artwork = self.artwork().await.ok_or(FsError::NotFound)?;
+                    let content = artwork.fetch(&url).await.ok_or(FsError::GeneralFailure)?;
+                    Ok(Box::new(VirtualFile { content, pos: 0 }) as Box<dyn DavFile>)
+                }
+                VfsNode::Directory { .. } | VfsNode::Link { .. } => Err(FsError::Forbidden),
             }
         }
         .boxed()
@@ -117,17 +161,12 @@ impl DavFileSystem for DebridFileSystem {
     ) -> FsFuture<'a, FsStream<Box<dyn DavDirEntry>>> {
         async move {
             let vfs = self.vfs.read().await;
-            let node = Self::find_node_ref(&vfs, path).ok_or(FsError::NotFound)?;
-            let path_str = path
-                .as_rel_ospath()
-                .to_str()
-                .unwrap_or("")
-                .trim_matches('/')
-                .trim_start_matches("./");
+            let (node, path_str) = Self::find_node_ref(&vfs, path).ok_or(FsError::NotFound)?;
             if let VfsNode::Directory { children } = node {
-                let mut entries: Vec<Box<dyn DavDirEntry>> = Vec::new();
+                let mut entries = Vec::new();
+                let mut artwork = Vec::new();
                 for (name, child) in children {
-                    let child_path = if path_str.is_empty() || path_str == "." {
+                    let child_path = if path_str.is_empty() {
                         name.clone()
                     } else {
                         format!("{}/{}", path_str, name)
@@ -137,12 +176,28 @@ impl DavFileSystem for DebridFileSystem {
                         .get(&child_path)
                         .copied()
                         .unwrap_or(UNIX_EPOCH);
-                    entries.push(Box::new(DebridDirEntry {
+                    if let VfsNode::Artwork { url } = child {
+                        artwork.push((entries.len(), url.clone()));
+                    }
+                    entries.push(DebridDirEntry {
                         name: name.clone(),
                         metadata: DebridMetaData::from_node(child, modified_time),
-                    }));
+                    });
+                }
+                // Don't hold the VFS lock while images are sized.
+                drop(vfs);
+                let sizes = futures_util::future::join_all(
+                    artwork.iter().map(|(_, url)| self.artwork_size(url)),
+                )
+                .await;
+                for ((i, _), size) in artwork.into_iter().zip(sizes) {
+                    entries[i].metadata.size = size;
                 }
-                let stream = futures_util::stream::iter(entries.into_iter().map(Ok));
+                let stream = futures_util::stream::iter(
+                    entries
+                        .into_iter()
+                        .map(|e| Ok(Box::new(e) as Box<dyn DavDirEntry>)),
+                );
                 Ok(Box::pin(stream) as FsStream<Box<dyn DavDirEntry>>)
             } else {
                 Err(FsError::Forbidden)
@@ -154,15 +209,15 @@ impl DavFileSystem for DebridFileSystem {
     fn metadata<'a>(&'a self, path: &'a DavPath) -> FsFuture<'a, Box<dyn DavMetaData>> {
         async move {
             let vfs = self.vfs.read().await;
-            let node = Self::find_node_ref(&vfs, path).ok_or(FsError::NotFound)?;
-            let path_str = path
-                .as_rel_ospath()
-                .to_str()
-                .unwrap_or("")
-                .trim_matches('/')
-                .trim_start_matches("./");
-            let modified_time = vfs.timestamps.get(path_str).copied().unwrap_or(UNIX_EPOCH);
-            Ok(Box::new(DebridMetaData::from_node(node, modified_time)) as Box<dyn DavMetaData>)
+            let (node, path_str) = Self::find_node_ref(&vfs, path).ok_or(FsError::NotFound)?;
+            let modified_time = vfs.timestamps.get(&path_str).copied().unwrap_or(UNIX_EPOCH);
+            let mut metadata = DebridMetaData::from_node(node, modified_time);
+            if let VfsNode::Artwork { url } = node {
+                let url = url.clone();
+                drop(vfs);
+                metadata.size = self.artwork_size(&url).await;
+            }
+            Ok(Box::new(metadata) as Box<dyn DavMetaData>)
         }
         .boxed()
     }
@@ -180,7 +235,10 @@ impl DebridMetaData {
         let (is_directory, size) = match node {
             VfsNode::MediaFile { file_size, .. } => (false, *file_size),
             VfsNode::VirtualFile { content, .. } => (false, content.len() as u64),
-            VfsNode::Directory { .. } => (true, 0),
+            VfsNode::CachedFile { file_size, .. } => (false, *file_size),
+            // Filled in by the caller from the artwork cache.
+            VfsNode::Artwork { .. } => (false, 0),
+            VfsNode::Directory { .. } | VfsNode::Link { .. } => (true, 0),
         };
         Self {
             is_directory,
@@ -566,14 +624,9 @@ mod tests {
         assert!(result.is_some(), "Normal path should resolve");
 
         // DavPath normalizes paths containing "..", so /Movies/../etc/passwd
-        // becomes /etc/passwd. Our find_node_in guard is defense-in-depth
+        // becomes /etc/passwd. The guard in DebridVfs::lookup is defense-in-depth
         // against any future code path that might bypass DavPath construction.
-        // Verify the guard exists in the source code.
-        let source = include_str!("dav_fs.rs");
-        assert!(
-            source.contains(r#"if component == ".." {"#),
-            "find_node_in must contain the .. traversal guard"
-        );
+        assert!(vfs.lookup("Movies/../Shows").is_none());
     }
 
     #[test]
@@ -0,0 +1,387 @@
+//! Details (plot, genres, cast, artwork URLs, collection, ...) for every identified
+//! title, from the metadata source that identified it, cached in redb so full NFOs,
+//! artwork files and collections need no request per VFS build. They are meant for Kodi
+//! and Infuse, which read local metadata without a scraper, and for media servers
+//! building box sets.
+
+use crate::metadata_source::MetadataSources;
+use crate::rd_client::TorrentInfo;
+use crate::tmdb_client::TitleDetails;
+use crate::vfs::{env_flag, MediaMetadata, MediaType};
+use redb::{ReadableDatabase, ReadableTable, TableDefinition};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+/// Details per title, keyed `movie:<id>` or `tv:<id>` for TMDB ids and
+/// `movie:<source>:<id>` or `tv:<source>:<id>` for other sources' (`tv:tvdb:81189`), value
+/// JSON `TitleDetails`.
+pub const DETAILS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("title_details");
+
+/// Titles fetched per refresh, so a large library fills the cache over a few scans.
+const MAX_TITLES_PER_REFRESH: usize = 50;
+/// Before retrying a title its source couldn't provide.
+const RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Default)]
+pub struct DetailsCache {
+    titles: RwLock<HashMap<String, TitleDetails>>,
+    attempted: Mutex<HashMap<String, Instant>>,
+}
+
+/// Cache key for an identified title.
+fn details_key(metadata: &MediaMetadata) -> Option<String> {
+    let kind = match metadata.media_type {
+        MediaType::Movie => "movie",
+        MediaType::Show => "tv",
+    };
+    let external_id = metadata.external_id.as_deref()?;
+    let id = external_id.strip_prefix("tmdb:").unwrap_or(external_id);
+    Some(format!("{}:{}", kind, id))
+}
+
+impl DetailsCache {
+    /// Enabled by `FULL_NFO`, `ARTWORK`, `COLLECTIONS` or `COLLECTION_FOLDERS` set to
+    /// `true`; loads the details cached in `db`.
+    pub async fn from_env(db: &Arc<redb::Database>) -> Option<Self> {
+        let enabled = ["FULL_NFO", "ARTWORK", "COLLECTIONS", "COLLECTION_FOLDERS"]
+            .into_iter()
+            .any(env_flag);
+        if !enabled {
+            return None;
+        }
+        Some(Self::load(db).await)
+    }
+
+    pub async fn load(db: &Arc<redb::Database>) -> Self {
+        let db_clone = db.clone();
+        let titles = tokio::task::spawn_blocking(move || {
+            let mut titles = HashMap::new();
+            if let Ok(read_txn) = db_clone.begin_read() {
+                if let Ok(table) = read_txn.open_table(DETAILS_TABLE) {
+                    if let Ok(iter) = table.iter() {
+                        for (key, value) in iter.flatten() {
+                            if let Ok(details) = serde_json::from_slice(value.value()) {
+                                titles.insert(key.value().to_string(), details);
+                            }
+                        }
+                    }
+                }
+            }
+            titles
+        })
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to load title details: {:?}", e);
+            HashMap::new()
+        });
+        Self {
+            titles: RwLock::new(titles),
+            attempted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached details of a title, if it is identified and fetched.
+    pub fn get(&self, metadata: &MediaMetadata) -> Option<TitleDetails> {
+        let titles = self.titles.read().unwrap_or_else(|e| e.into_inner());
+        titles.get(&details_key(metadata)?).cloned()
+    }
+
+    /// Fetch details for library titles that aren't cached. Returns whether any were
+    /// added, i.e. whether the VFS should be rebuilt.
+    pub async fn refresh(
+        &self,
+        db: &Arc<redb::Database>,
+        sources: &MetadataSources,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> bool {
+        let missing: BTreeMap<String, (MediaType, String)> = {
+            let titles = self.titles.read().unwrap_or_else(|e| e.into_inner());
+            library
+                .values()
+                .filter_map(|(_, metadata)| {
+                    let key = details_key(metadata)?;
+                    let id = metadata.external_id.clone()?;
+                    (!titles.contains_key(&key)).then(|| (key, (metadata.media_type.clone(), id)))
+                })
+                .collect()
+        };
+        let due: Vec<_> = {
+            let mut attempted = self.attempted.lock().await;
+            let due: Vec<_> = missing
+                .into_iter()
+                .filter(|(key, _)| {
+                    attempted
+                        .get(key)
+                        .is_none_or(|at| at.elapsed() >= RETRY_AFTER)
+                })
+                .take(MAX_TITLES_PER_REFRESH)
+                .collect();
+            for (key, _) in &due {
+                attempted.insert(key.clone(), Instant::now());
+            }
+            due
+        };
+
+        let mut changed = false;
+        for (key, (media_type, id)) in due {
+            let Some(details) = sources.details(&media_type, &id).await else {
+                continue;
+            };
+            if let Err(e) = store(db, &key, &details).await {
+                error!("Failed to cache details for {}: {}", key, e);
+            }
+            self.titles
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(key, details);
+            changed = true;
+        }
+        if changed {
+            info!("Title details: cache updated, rebuilding VFS");
+        }
+        changed
+    }
+}
+
+async fn store(
+    db: &Arc<redb::Database>,
+    key: &str,
+    details: &TitleDetails,
+) -> Result<(), redb::Error> {
+    let db = db.clone();
+    let key = key.to_string();
+    let bytes =
+        serde_json::to_vec(details).map_err(|e| redb::Error::Io(std::io::Error::other(e)))?;
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(DETAILS_TABLE)?;
+            table.insert(key.as_str(), bytes.as_slice())?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_json;
+    use crate::tmdb_client::TmdbClient;
+
+    #[tokio::test]
+    async fn refresh_fetches_uncached_titles_once() {
+        let (base, _server) =
+            spawn_json(r#"{"overview": "Dreams within dreams.", "genres": []}"#).await;
+        let tmdb = TmdbClient::with_base_url("key".to_string(), base).unwrap();
+        let sources = MetadataSources::new(vec![Arc::new(tmdb)], false);
+        let db = Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        );
+        let movie = MediaMetadata {
+            title: "Inception".to_string(),
+            year: Some("2010".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:27205".to_string()),
+        };
+        let unidentified = MediaMetadata {
+            external_id: None,
+            ..movie.clone()
+        };
+        let library = HashMap::from([
+            ("t1".to_string(), (TorrentInfo::default(), movie.clone())),
+            ("t2".to_string(), (TorrentInfo::default(), unidentified)),
+        ]);
+
+        let cache = DetailsCache::load(&db).await;
+        assert!(cache.refresh(&db, &sources, &library).await);
+        assert_eq!(
+            cache.get(&movie).unwrap().plot.as_deref(),
+            Some("Dreams within dreams.")
+        );
+        // Cached, and the unidentified title is never fetched: no second request.
+        assert!(!cache.refresh(&db, &sources, &library).await);
+        assert!(DetailsCache::load(&db).await.get(&movie).is_some());
+    }
+
+    #[test]
+    fn details_key_keeps_the_source_of_other_ids() {
+        let show = |external_id: &str| MediaMetadata {
+            title: "Lost".to_string(),
+            year: Some("2004".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some(external_id.to_string()),
+        };
+        assert_eq!(details_key(&show("tmdb:4607")).as_deref(), Some("tv:4607"));
+        assert_eq!(
+            details_key(&show("tvdb:73739")).as_deref(),
+            Some("tv:tvdb:73739")
+        );
+    }
+
+    #[test]
+    fn build_exposes_artwork_for_cached_details() {
+        use crate::rd_client::TorrentFile;
+        use crate::vfs::{DebridVfs, VfsNode, VfsOptions};
+
+        let show = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:95396".to_string()),
+        };
+        let cache = DetailsCache::default();
+        cache.titles.write().unwrap().insert(
+            details_key(&show).unwrap(),
+            TitleDetails {
+                poster_url: Some("https://image.tmdb.org/t/p/original/p.jpg".to_string()),
+                season_posters: BTreeMap::from([
+                    (1, "https://image.tmdb.org/t/p/original/s1.jpg".to_string()),
+                    (2, "https://image.tmdb.org/t/p/original/s2.jpg".to_string()),
+                ]),
+                ..Default::default()
+            },
+        );
+        let info = TorrentInfo {
+            id: "t1".to_string(),
+            files: vec![TorrentFile {
+                id: 1,
+                path: "/Severance.S01E01.mkv".to_string(),
+                bytes: 1,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let options = VfsOptions {
+            details: Some(Arc::new(cache)),
+            artwork: Some(Arc::new(crate::artwork::ArtworkCache::new(
+                "artwork",
+                reqwest::Client::new(),
+            ))),
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(vec![(info, show)], Arc::new(options));
+        let VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let VfsNode::Directory { children: shows } = &children["Shows"] else {
+            panic!("Shows is a directory");
+        };
+        let VfsNode::Directory { children: folder } = &shows["Severance [tmdbid-95396]"] else {
+            panic!("show folder is a directory");
+        };
+        assert_eq!(
+            folder.get("poster.jpg"),
+            Some(&VfsNode::Artwork {
+                url: "https://image.tmdb.org/t/p/original/p.jpg".to_string()
+            })
+        );
+        assert!(folder.contains_key("season01-poster.jpg"));
+        // No fanart, and no poster for a season the library doesn't have.
+        assert!(!folder.contains_key("fanart.jpg"));
+        assert!(!folder.contains_key("season02-poster.jpg"));
+        // Full NFO mode is off: the NFO stays minimal.
+        let VfsNode::VirtualFile { content } = &folder["tvshow.nfo"] else {
+            panic!("tvshow.nfo is a virtual file");
+        };
+        assert!(!String::from_utf8_lossy(content).contains("<thumb"));
+    }
+
+    #[test]
+    fn build_groups_collection_movies() {
+        use crate::rd_client::TorrentFile;
+        use crate::tmdb_client::TitleCollection;
+        use crate::vfs::{DebridVfs, VfsNode, VfsOptions};
+
+        let movie = |title: &str, id: &str| MediaMetadata {
+            title: title.to_string(),
+            year: None,
+            media_type: MediaType::Movie,
+            external_id: Some(format!("tmdb:{}", id)),
+        };
+        let torrent = |id: &str| TorrentInfo {
+            id: id.to_string(),
+            files: vec![TorrentFile {
+                id: 1,
+                path: format!("/{}.mkv", id),
+                bytes: 1,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let (alien, aliens) = (movie("Alien", "348"), movie("Aliens", "679"));
+        let cache = DetailsCache::default();
+        for metadata in [&alien, &aliens] {
+            cache.titles.write().unwrap().insert(
+                details_key(metadata).unwrap(),
+                TitleDetails {
+                    plot: Some("In space.".to_string()),
+                    collection: Some(TitleCollection {
+                        id: 8091,
+                        name: "Alien Collection".to_string(),
+                    }),
+                    ..Default::default()
+                },
+            );
+        }
+        let options = VfsOptions {
+            details: Some(Arc::new(cache)),
+            collections: true,
+            collection_folders: true,
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(
+            vec![(torrent("a1"), alien), (torrent("a2"), aliens)],
+            Arc::new(options),
+        );
+        let VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let VfsNode::Directory { children: sets } = &children["Collections"] else {
+            panic!("Collections is a directory");
+        };
+        let VfsNode::Directory { children: set } = &sets["Alien Collection"] else {
+            panic!("collection folder is a directory");
+        };
+        assert_eq!(
+            set.keys().collect::<Vec<_>>(),
+            vec!["Alien [tmdbid-348]", "Aliens [tmdbid-679]"]
+        );
+        assert!(vfs
+            .timestamps
+            .contains_key("Collections/Alien Collection/Alien [tmdbid-348]"));
+        // Members link to their movie folder instead of repeating it.
+        assert_eq!(
+            set["Alien [tmdbid-348]"],
+            VfsNode::Link {
+                target: "Movies/Alien [tmdbid-348]".to_string()
+            }
+        );
+        assert_eq!(
+            vfs.torrent_paths()["a1"],
+            vec!["Movies/Alien [tmdbid-348]/a1.mkv"]
+        );
+        let (node, path) = vfs
+            .lookup("Collections/Alien Collection/Alien [tmdbid-348]")
+            .unwrap();
+        assert_eq!(path, "Movies/Alien [tmdbid-348]");
+        // The set is written without full NFO mode, and nothing else from the details is.
+        let VfsNode::Directory { children: movie } = node else {
+            panic!("movie folder is a directory");
+        };
+        let VfsNode::VirtualFile { content } = &movie["movie.nfo"] else {
+            panic!("movie.nfo is a virtual file");
+        };
+        let nfo = String::from_utf8_lossy(content);
+        assert!(nfo.contains("<set tmdbcolid=\"8091\">\n    <name>Alien Collection</name>"));
+        assert!(!nfo.contains("<plot>"));
+    }
+}
@@ -0,0 +1,386 @@
+//! TMDB episode details (title, air date, id) for canonical episode file names
+//! (`Show - S01E02 - Title.mkv`) and episode NFOs. They come from TMDB season details and
+//! are cached in redb, so each season is fetched once and the VFS can be built
+//! synchronously from the in-memory copy.
+
+use crate::episodes::tracked_shows;
+use crate::rd_client::TorrentInfo;
+use crate::tmdb_client::TmdbClient;
+use crate::vfs::{env_flag, MediaMetadata};
+use redb::{ReadableDatabase, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+/// Episodes per show season, keyed `<tmdb id>:<season>`, value a JSON map from episode
+/// number to `CatalogEpisode`.
+pub const EPISODE_CATALOG_TABLE: TableDefinition<&str, &[u8]> =
+    TableDefinition::new("episode_catalog");
+
+/// The catalog's predecessor, which cached episode titles only (same keys, values a JSON
+/// map from episode number to title). Dropped rather than migrated: its seasons lack air
+/// dates and episode ids, so they are refetched into `EPISODE_CATALOG_TABLE` instead.
+const LEGACY_EPISODE_TITLES_TABLE: TableDefinition<&str, &[u8]> =
+    TableDefinition::new("episode_titles");
+
+/// Seasons fetched per refresh, so a large library fills the cache over a few scans
+/// instead of bursting TMDB.
+const MAX_SEASONS_PER_REFRESH: usize = 50;
+/// Before retrying a season TMDB couldn't provide (or that has since gained episodes).
+const RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+type SeasonKey = (u32, u32);
+
+/// What the VFS needs to know about one episode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEpisode {
+    pub name: String,
+    /// `YYYY-MM-DD`.
+    pub air_date: Option<String>,
+    /// TMDB episode id.
+    pub tmdb_id: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct EpisodeCatalog {
+    seasons: RwLock<HashMap<SeasonKey, BTreeMap<u32, CatalogEpisode>>>,
+    attempted: Mutex<HashMap<SeasonKey, Instant>>,
+}
+
+impl EpisodeCatalog {
+    /// Needed by `RENAME_EPISODES=true` and `EPISODE_NFO=true`; loads the episodes cached
+    /// in `db`.
+    pub async fn from_env(db: &Arc<redb::Database>) -> Option<Self> {
+        if !["RENAME_EPISODES", "EPISODE_NFO"].into_iter().any(env_flag) {
+            return None;
+        }
+        Some(Self::load(db).await)
+    }
+
+    pub async fn load(db: &Arc<redb::Database>) -> Self {
+        let db_clone = db.clone();
+        let seasons = tokio::task::spawn_blocking(move || {
+            let mut seasons = HashMap::new();
+            if let Ok(read_txn) = db_clone.begin_read() {
+                if let Ok(table) = read_txn.open_table(EPISODE_CATALOG_TABLE) {
+                    if let Ok(iter) = table.iter() {
+                        for (key, value) in iter.flatten() {
+                            let Some(season_key) = parse_key(key.value()) else {
+                                continue;
+                            };
+                            if let Ok(episodes) = serde_json::from_slice(value.value()) {
+                                seasons.insert(season_key, episodes);
+                            }
+                        }
+                    }
+                }
+            }
+            seasons
+        })
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to load episode catalog: {:?}", e);
+            HashMap::new()
+        });
+        Self {
+            seasons: RwLock::new(seasons),
+            attempted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached details of an episode, if TMDB has it.
+    pub fn episode(&self, tmdb_id: u32, season: u32, episode: u32) -> Option<CatalogEpisode> {
+        let seasons = self.seasons.read().unwrap_or_else(|e| e.into_inner());
+        seasons.get(&(tmdb_id, season))?.get(&episode).cloned()
+    }
+
+    /// Cached title of an episode, if TMDB has one.
+    pub fn title(&self, tmdb_id: u32, season: u32, episode: u32) -> Option<String> {
+        self.episode(tmdb_id, season, episode)
+            .map(|e| e.name)
+            .filter(|name| !name.trim().is_empty())
+    }
+
+    /// Fetch library seasons that aren't cached, or whose cached copy lacks an episode the
+    /// library has. Returns whether any episodes were added, i.e. whether the VFS should
+    /// be rebuilt.
+    pub async fn refresh(
+        &self,
+        db: &Arc<redb::Database>,
+        tmdb: &TmdbClient,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> bool {
+        let wanted = self.stale_seasons(library);
+        let due: Vec<SeasonKey> = {
+            let mut attempted = self.attempted.lock().await;
+            let due: Vec<_> = wanted
+                .into_iter()
+                .filter(|key| {
+                    attempted
+                        .get(key)
+                        .is_none_or(|at| at.elapsed() >= RETRY_AFTER)
+                })
+                .take(MAX_SEASONS_PER_REFRESH)
+                .collect();
+            for key in &due {
+                attempted.insert(*key, Instant::now());
+            }
+            due
+        };
+
+        let mut changed = false;
+        for (tmdb_id, season_number) in due {
+            let Some(season) = tmdb.tv_season(tmdb_id, season_number).await else {
+                continue;
+            };
+            let episodes: BTreeMap<u32, CatalogEpisode> = season
+                .episodes
+                .into_iter()
+                .map(|e| {
+                    let episode = CatalogEpisode {
+                        name: e.name,
+                        air_date: e.air_date,
+                        tmdb_id: e.id,
+                    };
+                    (e.episode_number, episode)
+                })
+                .collect();
+            if let Err(e) = store(db, tmdb_id, season_number, &episodes).await {
+                error!(
+                    "Failed to cache episodes for tmdb:{} season {}: {}",
+                    tmdb_id, season_number, e
+                );
+            }
+            let mut seasons = self.seasons.write().unwrap_or_else(|e| e.into_inner());
+            if seasons.get(&(tmdb_id, season_number)) != Some(&episodes) {
+                seasons.insert((tmdb_id, season_number), episodes);
+                changed = true;
+            }
+        }
+        if changed {
+            info!("Episode catalog: cache updated, rebuilding VFS");
+        }
+        changed
+    }
+
+    /// Library seasons with an episode the cache doesn't have.
+    fn stale_seasons(
+        &self,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> Vec<SeasonKey> {
+        let seasons = self.seasons.read().unwrap_or_else(|e| e.into_inner());
+        let mut stale = Vec::new();
+        for (tmdb_id, show) in tracked_shows(library) {
+            for &(season, episode) in &show.episodes {
+                let key = (tmdb_id, season);
+                let cached = seasons.get(&key).is_some_and(|e| e.contains_key(&episode));
+                if !cached && stale.last() != Some(&key) {
+                    stale.push(key);
+                }
+            }
+        }
+        stale
+    }
+}
+
+/// Drop the legacy `episode_titles` table from a database written by older versions.
+/// Returns whether it existed.
+pub fn drop_legacy_table(write_txn: &redb::WriteTransaction) -> Result<bool, redb::TableError> {
+    write_txn.delete_table(LEGACY_EPISODE_TITLES_TABLE)
+}
+
+fn season_key(tmdb_id: u32, season: u32) -> String {
+    format!("{}:{}", tmdb_id, season)
+}
+
+fn parse_key(key: &str) -> Option<SeasonKey> {
+    let (tmdb_id, season) = key.split_once(':')?;
+    Some((tmdb_id.parse().ok()?, season.parse().ok()?))
+}
+
+async fn store(
+    db: &Arc<redb::Database>,
+    tmdb_id: u32,
+    season: u32,
+    episodes: &BTreeMap<u32, CatalogEpisode>,
+) -> Result<(), redb::Error> {
+    let db = db.clone();
+    let key = season_key(tmdb_id, season);
+    let bytes =
+        serde_json::to_vec(episodes).map_err(|e| redb::Error::Io(std::io::Error::other(e)))?;
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(EPISODE_CATALOG_TABLE)?;
+            table.insert(key.as_str(), bytes.as_slice())?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rd_client::TorrentFile;
+    use crate::test_support::spawn_json;
+    use crate::vfs::MediaType;
+
+    fn in_memory_db() -> Arc<redb::Database> {
+        Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn drop_legacy_table_removes_episode_titles_once() {
+        let db = in_memory_db();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(LEGACY_EPISODE_TITLES_TABLE).unwrap();
+            table
+                .insert("1396:1", br#"{"1": "Pilot"}"#.as_slice())
+                .unwrap();
+        }
+        write_txn.commit().unwrap();
+
+        let write_txn = db.begin_write().unwrap();
+        assert!(drop_legacy_table(&write_txn).unwrap());
+        assert!(!drop_legacy_table(&write_txn).unwrap());
+        write_txn.commit().unwrap();
+        let read_txn = db.begin_read().unwrap();
+        assert!(read_txn.open_table(LEGACY_EPISODE_TITLES_TABLE).is_err());
+    }
+
+    fn library(paths: &[&str]) -> HashMap<String, (TorrentInfo, MediaMetadata)> {
+        let info = TorrentInfo {
+            files: paths
+                .iter()
+                .enumerate()
+                .map(|(i, p)| TorrentFile {
+                    id: i as u32,
+                    path: p.to_string(),
+                    bytes: 1,
+                    selected: 1,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let metadata = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:95396".to_string()),
+        };
+        HashMap::from([("t1".to_string(), (info, metadata))])
+    }
+
+    #[tokio::test]
+    async fn refresh_fetches_missing_seasons_and_persists_them() {
+        let body = r#"{"season_number":1,"episodes":[
+            {"season_number":1,"episode_number":1,"name":"Good News About Hell","air_date":"2022-02-18"},
+            {"id":3227001,"season_number":1,"episode_number":2,"name":"Half Loop","air_date":"2022-02-18"}]}"#;
+        let (base, _server) = spawn_json(body).await;
+        let tmdb = TmdbClient::with_base_url("key".to_string(), base).unwrap();
+        let db = in_memory_db();
+        let library = library(&["/Sev/Severance.S01E02.mkv"]);
+
+        let catalog = EpisodeCatalog::load(&db).await;
+        assert!(catalog.refresh(&db, &tmdb, &library).await);
+        assert_eq!(
+            catalog.episode(95396, 1, 2),
+            Some(CatalogEpisode {
+                name: "Half Loop".to_string(),
+                air_date: Some("2022-02-18".to_string()),
+                tmdb_id: Some(3227001),
+            })
+        );
+        // Cached: nothing left to fetch, and a reload sees the same episodes.
+        assert!(!catalog.refresh(&db, &tmdb, &library).await);
+        let reloaded = EpisodeCatalog::load(&db).await;
+        assert_eq!(
+            reloaded.title(95396, 1, 1).as_deref(),
+            Some("Good News About Hell")
+        );
+    }
+
+    #[test]
+    fn stale_seasons_lists_each_uncovered_season_once() {
+        let catalog = EpisodeCatalog::default();
+        let pilot = CatalogEpisode {
+            name: "Pilot".to_string(),
+            air_date: None,
+            tmdb_id: None,
+        };
+        catalog
+            .seasons
+            .write()
+            .unwrap()
+            .insert((95396, 1), BTreeMap::from([(1, pilot)]));
+        let library = library(&[
+            "/Sev/Severance.S01E01.mkv",
+            "/Sev/Severance.S01E02.mkv",
+            "/Sev/Severance.S01E03.mkv",
+            "/Sev/Severance.S02E01.mkv",
+        ]);
+        assert_eq!(
+            catalog.stale_seasons(&library),
+            vec![(95396, 1), (95396, 2)]
+        );
+    }
+
+    #[test]
+    fn build_writes_episode_nfos_from_the_catalog() {
+        let catalog = EpisodeCatalog::default();
+        let half_loop = CatalogEpisode {
+            name: "Half Loop".to_string(),
+            air_date: Some("2022-02-18".to_string()),
+            tmdb_id: Some(3227001),
+        };
+        catalog
+            .seasons
+            .write()
+            .unwrap()
+            .insert((95396, 1), BTreeMap::from([(2, half_loop)]));
+        let options = crate::vfs::VfsOptions {
+            episodes: Some(Arc::new(catalog)),
+            episode_nfo: true,
+            ..Default::default()
+        };
+        let (info, metadata) = library(&["/Sev/Severance.S01E02.mkv"])
+            .remove("t1")
+            .unwrap();
+        let vfs = crate::vfs::DebridVfs::build_with(vec![(info, metadata)], Arc::new(options));
+
+        let crate::vfs::VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let mut node = children.get("Shows").unwrap();
+        for part in [
+            "Severance [tmdbid-95396]",
+            "Season 01",
+            "Severance.S01E02.nfo",
+        ] {
+            let crate::vfs::VfsNode::Directory { children } = node else {
+                panic!("{} is not in a directory", part);
+            };
+            node = children.get(part).unwrap();
+        }
+        let crate::vfs::VfsNode::VirtualFile { content } = node else {
+            panic!("episode NFO is a virtual file");
+        };
+        let nfo = String::from_utf8(content.clone()).unwrap();
+        assert!(nfo.contains("<title>Half Loop</title>"));
+        assert!(nfo.contains("<season>1</season>") && nfo.contains("<episode>2</episode>"));
+        assert!(nfo.contains("<aired>2022-02-18</aired>"));
+        assert!(nfo.contains("<uniqueid type=\"tmdb\" default=\"true\">3227001</uniqueid>"));
+    }
+}
@@ -0,0 +1,377 @@
+//! Auto-grab of newly aired episodes: for each show in the library with a TMDB id, aired
+//! episodes of its latest season (and any later ones) that no torrent provides are searched
+//! on the Torznab indexer and the first cached release is added to the debrid account.
+//! Started by the scan loop at most once per sync interval, in a task of its own so grabs
+//! never delay a scan.
+
+use crate::acquire::{add_if_cached, load_grabs, record_grab, Grab};
+use crate::indexer::{rank_releases, SearchQuery, TorrentIndexer};
+use crate::provider::DebridProvider;
+use crate::quality::QualityProfile;
+use crate::rd_client::TorrentInfo;
+use crate::tmdb_client::{TmdbClient, TmdbEpisode};
+use crate::vfs::{env_flag, is_video_file, parse_episode_range, MediaMetadata, MediaType};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 3600;
+/// Releases tried per episode before giving up until the next sync.
+const MAX_CANDIDATES: usize = 3;
+/// Episodes searched per sync, so a show with a long backlog can't monopolise the indexer.
+const MAX_EPISODES_PER_SYNC: usize = 20;
+
+/// Key under which an episode grab is recorded, e.g. `episode:1396:s02e05`.
+pub fn episode_grab_key(tmdb_id: u32, season: u32, episode: u32) -> String {
+    format!("episode:{}:s{:02}e{:02}", tmdb_id, season, episode)
+}
+
+/// A show in the library and the episodes its torrents already provide.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TrackedShow {
+    pub(crate) title: String,
+    pub(crate) episodes: BTreeSet<(u32, u32)>,
+}
+
+/// An aired episode missing from the library.
+#[derive(Debug, Clone, PartialEq)]
+struct MissingEpisode {
+    key: String,
+    tmdb_id: u32,
+    title: String,
+    season: u32,
+    episode: u32,
+}
+
+impl MissingEpisode {
+    fn query(&self) -> SearchQuery {
+        SearchQuery {
+            tmdb_id: Some(u64::from(self.tmdb_id)),
+            season: Some(self.season),
+            episode: Some(self.episode),
+            ..SearchQuery::show(&self.title)
+        }
+    }
+}
+
+pub struct EpisodeSync {
+    indexer: Arc<dyn TorrentIndexer>,
+    interval: Duration,
+    profile: QualityProfile,
+    last_run: Mutex<Option<Instant>>,
+}
+
+impl EpisodeSync {
+    pub fn new(
+        indexer: Arc<dyn TorrentIndexer>,
+        profile: QualityProfile,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            indexer,
+            profile,
+            interval,
+            last_run: Mutex::new(None),
+        }
+    }
+
+    /// Enabled by `AUTO_GRAB_EPISODES=true` when the indexer is configured. The interval
+    /// comes from `EPISODE_SYNC_INTERVAL_SECS` (default one hour).
+    pub fn from_env(
+        indexer: Option<Arc<dyn TorrentIndexer>>,
+        profile: QualityProfile,
+    ) -> Option<Self> {
+        if !env_flag("AUTO_GRAB_EPISODES") {
+            return None;
+        }
+        let Some(indexer) = indexer else {
+            warn!("AUTO_GRAB_EPISODES is set but TORZNAB_URL is not; episode auto-grab disabled");
+            return None;
+        };
+        let secs = match std::env::var("EPISODE_SYNC_INTERVAL_SECS") {
+            Ok(s) => s.parse().unwrap_or_else(|_| {
+                warn!(
+                    "Invalid EPISODE_SYNC_INTERVAL_SECS value '{}', falling back to {}",
+                    s, DEFAULT_SYNC_INTERVAL_SECS
+                );
+                DEFAULT_SYNC_INTERVAL_SECS
+            }),
+            Err(_) => DEFAULT_SYNC_INTERVAL_SECS,
+        };
+        Some(Self::new(indexer, profile, Duration::from_secs(secs)))
+    }
+
+    /// Sync if the interval has elapsed since the last run. Returns the number of
+    /// releases added.
+    pub async fn run_if_due(
+        &self,
+        provider: &Arc<dyn DebridProvider>,
+        db: &Arc<redb::Database>,
+        tmdb: &TmdbClient,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> usize {
+        {
+            let mut last_run = self.last_run.lock().await;
+            if last_run.is_some_and(|t| t.elapsed() < self.interval) {
+                return 0;
+            }
+            *last_run = Some(Instant::now());
+        }
+        let grabs = load_grabs(db).await;
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+
+        let mut missing = Vec::new();
+        for (tmdb_id, show) in tracked_shows(library) {
+            missing.extend(
+                self.missing_for_show(tmdb, tmdb_id, &show, &grabs, &today)
+                    .await,
+            );
+        }
+        if missing.is_empty() {
+            return 0;
+        }
+        info!(
+            "Episodes: {} aired episode(s) missing from library",
+            missing.len()
+        );
+
+        let mut added = 0;
+        for episode in missing.iter().take(MAX_EPISODES_PER_SYNC) {
+            if self.acquire(provider, db, episode).await {
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Aired, ungrabbed episodes of `show` from its latest season in the library onwards.
+    async fn missing_for_show(
+        &self,
+        tmdb: &TmdbClient,
+        tmdb_id: u32,
+        show: &TrackedShow,
+        grabs: &HashMap<String, Grab>,
+        today: &str,
+    ) -> Vec<MissingEpisode> {
+        let Some(&(latest_season, _)) = show.episodes.last() else {
+            return Vec::new();
+        };
+        let Some(details) = tmdb.tv_details(tmdb_id).await else {
+            return Vec::new();
+        };
+        let mut missing = Vec::new();
+        for summary in details.seasons.iter().filter(|s| {
+            s.season_number >= latest_season.max(1)
+                && s.air_date.as_deref().is_some_and(|d| d <= today)
+        }) {
+            let Some(season) = tmdb.tv_season(tmdb_id, summary.season_number).await else {
+                continue;
+            };
+            missing.extend(missing_episodes(
+                tmdb_id,
+                show,
+                &season.episodes,
+                grabs,
+                today,
+            ));
+        }
+        missing
+    }
+
+    async fn acquire(
+        &self,
+        provider: &Arc<dyn DebridProvider>,
+        db: &Arc<redb::Database>,
+        episode: &MissingEpisode,
+    ) -> bool {
+        let query = episode.query();
+        let releases = match self.indexer.search(&query).await {
+            Ok(releases) => releases,
+            Err(e) => {
+                error!("Indexer search for '{}' failed: {}", query.text(), e);
+                return false;
+            }
+        };
+        for release in rank_releases(&query, &releases, &self.profile)
+            .into_iter()
+            .take(MAX_CANDIDATES)
+        {
+            match add_if_cached(provider, release).await {
+                Ok(Some(info)) => {
+                    info!("Episodes: added {} for {}", release.title, query.text());
+                    let grab = Grab {
+                        release: release.title.clone(),
+                        hash: info.hash.to_lowercase(),
+                        torrent_id: info.id,
+                        grabbed_at: chrono::Utc::now().timestamp(),
+                    };
+                    if let Err(e) = record_grab(db, &episode.key, &grab).await {
+                        error!("Failed to record grab for {}: {}", episode.key, e);
+                    }
+                    return true;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to add release {}: {}", release.title, e),
+            }
+        }
+        info!("Episodes: no cached release found for {}", query.text());
+        false
+    }
+}
+
+/// Shows in the library keyed by TMDB id, with the episodes their selected video files
+/// provide. Files without an `SxxEyy` marker contribute nothing, so a show whose files
+/// can't be numbered is never searched.
+pub(crate) fn tracked_shows(
+    library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+) -> BTreeMap<u32, TrackedShow> {
+    let mut shows: BTreeMap<u32, TrackedShow> = BTreeMap::new();
+    for (info, metadata) in library.values() {
+        if metadata.media_type != MediaType::Show {
+            continue;
+        }
+        let Some(tmdb_id) = metadata.tmdb_id() else {
+            continue;
+        };
+        let show = shows.entry(tmdb_id).or_insert_with(|| TrackedShow {
+            title: metadata.title.clone(),
+            episodes: BTreeSet::new(),
+        });
+        for file in info
+            .files
+            .iter()
+            .filter(|f| f.selected == 1 && is_video_file(&f.path))
+        {
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            if let Some((season, first, last)) = parse_episode_range(filename) {
+                show.episodes.extend((first..=last).map(|e| (season, e)));
+            }
+        }
+    }
+    shows.retain(|_, show| !show.episodes.is_empty());
+    shows
+}
+
+/// Episodes that aired on or before `today` and are neither in the library nor already
+/// grabbed.
+fn missing_episodes(
+    tmdb_id: u32,
+    show: &TrackedShow,
+    episodes: &[TmdbEpisode],
+    grabs: &HashMap<String, Grab>,
+    today: &str,
+) -> Vec<MissingEpisode> {
+    episodes
+        .iter()
+        .filter(|e| e.air_date.as_deref().is_some_and(|d| d <= today))
+        .filter(|e| !show.episodes.contains(&(e.season_number, e.episode_number)))
+        .map(|e| MissingEpisode {
+            key: episode_grab_key(tmdb_id, e.season_number, e.episode_number),
+            tmdb_id,
+            title: show.title.clone(),
+            season: e.season_number,
+            episode: e.episode_number,
+        })
+        .filter(|m| !grabs.contains_key(&m.key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rd_client::TorrentFile;
+
+    fn show_entry(external_id: &str, paths: &[&str]) -> (TorrentInfo, MediaMetadata) {
+        (
+            TorrentInfo {
+                files: paths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| TorrentFile {
+                        id: i as u32,
+                        path: p.to_string(),
+                        bytes: 1,
+                        selected: 1,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            MediaMetadata {
+                title: "Breaking Bad".to_string(),
+                year: Some("2008".to_string()),
+                media_type: MediaType::Show,
+                external_id: Some(external_id.to_string()),
+            },
+        )
+    }
+
+    fn episode(season: u32, number: u32, air_date: Option<&str>) -> TmdbEpisode {
+        TmdbEpisode {
+            id: None,
+            season_number: season,
+            episode_number: number,
+            name: String::new(),
+            air_date: air_date.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn tracked_shows_merges_torrents_and_skips_unnumbered() {
+        let library = HashMap::from([
+            (
+                "a".to_string(),
+                show_entry("tmdb:1396", &["/BB.S01E01.mkv", "/BB.S01E02.mkv"]),
+            ),
+            (
+                "b".to_string(),
+                show_entry("tmdb:1396", &["/BB.S02E01.mkv", "/BB.S02E01.sample.mkv"]),
+            ),
+            ("c".to_string(), show_entry("tmdb:7", &["/Show.Pilot.mkv"])),
+            ("d".to_string(), show_entry("imdb:tt1", &["/X.S01E01.mkv"])),
+        ]);
+        let shows = tracked_shows(&library);
+        assert_eq!(shows.keys().collect::<Vec<_>>(), vec![&1396]);
+        assert_eq!(
+            shows[&1396].episodes.iter().copied().collect::<Vec<_>>(),
+            vec![(1, 1), (1, 2), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn missing_episodes_skips_present_grabbed_and_unaired() {
+        let show = TrackedShow {
+            title: "Breaking Bad".to_string(),
+            episodes: BTreeSet::from([(2, 1)]),
+        };
+        let episodes = vec![
+            episode(2, 1, Some("2009-03-08")),
+            episode(2, 2, Some("2009-03-15")),
+            episode(2, 3, Some("2009-03-22")),
+            episode(2, 4, Some("2099-01-01")),
+            episode(2, 5, None),
+        ];
+        let grabs = HashMap::from([(
+            episode_grab_key(1396, 2, 3),
+            Grab {
+                release: "BB.S02E03".to_string(),
+                hash: "h".to_string(),
+                torrent_id: "t".to_string(),
+                grabbed_at: 0,
+            },
+        )]);
+        let missing = missing_episodes(1396, &show, &episodes, &grabs, "2026-10-16");
+        assert_eq!(
+            missing,
+            vec![MissingEpisode {
+                key: "episode:1396:s02e02".to_string(),
+                tmdb_id: 1396,
+                title: "Breaking Bad".to_string(),
+                season: 2,
+                episode: 2,
+            }]
+        );
+    }
+}
@@ -14,6 +14,12 @@ pub enum AppError {
     #[error("Invalid configuration: {0}")]
     Config(String),
 
+    #[error("Indexer error: {0}")]
+    Indexer(String),
+
+    #[error("Metadata source error: {0}")]
+    Metadata(String),
+
     #[error("Debrid resource temporarily unavailable")]
     Unavailable,
 }
@@ -1,8 +1,13 @@
+use crate::dav_fs::DebridFileSystem;
+use crate::error::AppError;
+use crate::metadata_source::{MetadataSource, MetadataSources};
+use crate::provider::FileLocator;
 use crate::rd_client;
 use crate::tmdb_client::{TmdbClient, TmdbSearchResult};
-use crate::vfs::{is_video_file, MediaMetadata, MediaType, VIDEO_EXTENSIONS};
+use crate::vfs::{is_video_file, selected_files, MediaMetadata, MediaType, VIDEO_EXTENSIONS};
 use chrono::Datelike;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 use tracing::{debug, info, warn};
 
@@ -33,19 +38,181 @@ static GENERIC_RE: LazyLock<Regex> =
 static EPISODE_NUM_DASH_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\s+-\s+\d{1,3}\s+-\s+").unwrap());
 
+/// Top-scoring search results (per media type) whose alternative and translated titles are
+/// looked up when no result's own title matches the query.
+const ALTERNATIVE_TITLE_CANDIDATES: usize = 3;
+
+/// An IMDb id (`tt0133093`), bare or in an IMDb URL.
+static IMDB_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(tt\d{7,8})\b").unwrap());
+
+/// A TVDB id: in a TVDB URL (`thetvdb.com/?tab=series&id=81189`, `.../series/81189`) or a
+/// naming hint (`[tvdbid-81189]`, `tvdb=81189`).
+static TVDB_ID_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:thetvdb\.com/\S*?(?:[?&](?:series)?id=|/series/)|\btvdb(?:id)?[-=_ ]?)(\d+)\b",
+    )
+    .unwrap()
+});
+
+/// Largest bundled `.nfo` read for ids; release NFOs are a few KB.
+const MAX_NFO_SIZE: u64 = 64 * 1024;
+
+/// Scores below this lack an exact title match (which alone is worth 1000).
+const REVIEW_MIN_SCORE: f64 = 1000.0;
+
+/// A runner-up scoring within this much of the winner makes the match ambiguous.
+const REVIEW_MIN_MARGIN: f64 = 50.0;
+
+/// How an identification was reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStrategy {
+    /// An IMDb/TVDB id in the name or bundled NFO, resolved through `/find`.
+    ExternalId,
+    /// A search for the cleaned name.
+    Direct,
+    /// A search for the CamelCase-split name ("TheOffice" → "The Office").
+    CamelCaseSplit,
+    /// A search with leading words stripped ("Bond 50 Goldfinger" → "Goldfinger").
+    PrefixStripped,
+    /// A search for the part after the first dash ("d3us-Title" → "Title").
+    AfterDash,
+    /// A search without the year from the name.
+    NoYear,
+    /// Nothing matched; the cleaned name is used as is.
+    Unidentified,
+}
+
+/// The outcome of identifying a torrent, with how sure it was: the winning and runner-up
+/// `score_result` values (none for id lookups and unidentified torrents) and the strategy
+/// that produced the match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identification {
+    pub metadata: MediaMetadata,
+    /// The name (or external id) that was looked up.
+    pub query: String,
+    pub strategy: MatchStrategy,
+    pub score: Option<f64>,
+    pub runner_up: Option<f64>,
+}
+
+impl Identification {
+    fn unscored(metadata: MediaMetadata, query: &str, strategy: MatchStrategy) -> Self {
+        Self {
+            metadata,
+            query: query.to_string(),
+            strategy,
+            score: None,
+            runner_up: None,
+        }
+    }
+
+    /// Whether the match is doubtful enough to be checked by hand: nothing matched, the
+    /// winner lacks an exact title match, or a runner-up scored about as well.
+    pub fn needs_review(&self) -> bool {
+        match (self.strategy, self.score) {
+            (MatchStrategy::ExternalId, _) => false,
+            (MatchStrategy::Unidentified, _) | (_, None) => true,
+            (_, Some(score)) => {
+                score < REVIEW_MIN_SCORE
+                    || self
+                        .runner_up
+                        .is_some_and(|runner_up| score - runner_up < REVIEW_MIN_MARGIN)
+            }
+        }
+    }
+}
+
+/// Identify a torrent: first by an IMDb/TVDB id in its name or its main file's name,
+/// then by TMDB search.
 pub async fn identify_torrent(info: &rd_client::TorrentInfo, tmdb: &TmdbClient) -> MediaMetadata {
-    let representative_name = info
-        .files
+    identify_torrent_with_nfo(info, None, tmdb).await
+}
+
+/// `identify_torrent`, also looking for an id in `nfo` (the text of an NFO the torrent
+/// ships, see `read_bundled_nfo`).
+pub async fn identify_torrent_with_nfo(
+    info: &rd_client::TorrentInfo,
+    nfo: Option<&str>,
+    tmdb: &TmdbClient,
+) -> MediaMetadata {
+    identify_torrent_scored(info, nfo, tmdb).await.metadata
+}
+
+/// `identify_torrent_with_nfo` against any metadata source, keeping the scores and strategy
+/// behind the match. A source that cannot be searched leaves the torrent unidentified.
+pub async fn identify_torrent_scored(
+    info: &rd_client::TorrentInfo,
+    nfo: Option<&str>,
+    source: &dyn MetadataSource,
+) -> Identification {
+    match identify_torrent_matched(info, nfo, source).await {
+        Ok(Some(identification)) => identification,
+        Ok(None) => unidentified(info),
+        Err(e) => {
+            warn!("Could not search for {}: {}", info.filename, e);
+            unidentified(info)
+        }
+    }
+}
+
+/// Identify a torrent against each source in `sources.chain_for` its files, settling
+/// for the first that finds it. Only a source that answered without a match hands over
+/// to the next: if one errors, so does the chain, rather than keep a fallback's id for a
+/// title the source would have known.
+pub async fn identify_with_sources(
+    info: &rd_client::TorrentInfo,
+    nfo: Option<&str>,
+    sources: &MetadataSources,
+) -> Result<Identification, AppError> {
+    for source in sources.chain_for(&info.files) {
+        if let Some(identification) = identify_torrent_matched(info, nfo, source).await? {
+            return Ok(identification);
+        }
+        debug!(
+            "{} found nothing for {}; trying the next source",
+            source.id_source(),
+            info.filename
+        );
+    }
+    Ok(unidentified(info))
+}
+
+/// The torrent's largest video file's name, which usually names the title more cleanly
+/// than the torrent.
+fn representative_name(info: &rd_client::TorrentInfo) -> &str {
+    info.files
         .iter()
         .filter(|f| is_video_file(&f.path))
         .max_by_key(|f| f.bytes)
         .map(|f| f.path.split('/').next_back().unwrap_or(&f.path))
-        .unwrap_or(&info.filename);
+        .unwrap_or(&info.filename)
+}
+
+async fn identify_torrent_matched(
+    info: &rd_client::TorrentInfo,
+    nfo: Option<&str>,
+    source: &dyn MetadataSource,
+) -> Result<Option<Identification>, AppError> {
+    let representative_name = representative_name(info);
 
-    if let Some(metadata) = identify_name(representative_name, &info.files, tmdb).await {
-        // If it was identified as something very suspicious like "00000", maybe reject it?
-        // But identify_name already does identification.
-        return metadata;
+    for text in [Some(info.filename.as_str()), Some(representative_name), nfo]
+        .into_iter()
+        .flatten()
+    {
+        if let Some((metadata, id)) = identify_external_id(text, &info.files, source).await {
+            return Ok(Some(Identification::unscored(
+                metadata,
+                &id,
+                MatchStrategy::ExternalId,
+            )));
+        }
+    }
+
+    if let Some(identification) =
+        identify_name_scored(representative_name, &info.files, source).await?
+    {
+        return Ok(Some(identification));
     }
 
     if representative_name != info.filename {
@@ -53,18 +220,21 @@ pub async fn identify_torrent(info: &rd_client::TorrentInfo, tmdb: &TmdbClient)
             "Filename '{}' was generic or no match found. Trying torrent name: '{}'",
             representative_name, info.filename
         );
-        if let Some(metadata) = identify_name(&info.filename, &info.files, tmdb).await {
-            return metadata;
-        }
+        return identify_name_scored(&info.filename, &info.files, source).await;
     }
+    Ok(None)
+}
 
+/// Best guess at the title of a torrent no source identified, from its names alone.
+fn unidentified(info: &rd_client::TorrentInfo) -> Identification {
+    let representative_name = representative_name(info);
     warn!(
         "Could not identify torrent: {}. Filename: {}.",
         info.filename, representative_name
     );
     let (cleaned_torrent, year_t) = clean_name(&info.filename);
     if !cleaned_torrent.is_empty() {
-        return MediaMetadata {
+        let metadata = MediaMetadata {
             title: cleaned_torrent,
             year: year_t,
             media_type: if is_show_guess(&info.files) {
@@ -74,6 +244,7 @@ pub async fn identify_torrent(info: &rd_client::TorrentInfo, tmdb: &TmdbClient)
             },
             external_id: None,
         };
+        return Identification::unscored(metadata, &info.filename, MatchStrategy::Unidentified);
     }
 
     let (cleaned_file, year_f) = clean_name(representative_name);
@@ -83,7 +254,7 @@ pub async fn identify_torrent(info: &rd_client::TorrentInfo, tmdb: &TmdbClient)
         representative_name.to_string()
     };
 
-    MediaMetadata {
+    let metadata = MediaMetadata {
         title: final_title,
         year: year_f,
         media_type: if is_show_guess(&info.files) {
@@ -92,6 +263,90 @@ pub async fn identify_torrent(info: &rd_client::TorrentInfo, tmdb: &TmdbClient)
             MediaType::Movie
         },
         external_id: None,
+    };
+    Identification::unscored(metadata, representative_name, MatchStrategy::Unidentified)
+}
+
+/// The first IMDb or TVDB id in `text`, with the `/find` source it belongs to.
+fn find_external_id(text: &str) -> Option<(String, &'static str)> {
+    if let Some(cap) = IMDB_ID_RE.captures(text) {
+        return Some((cap[1].to_string(), "imdb_id"));
+    }
+    TVDB_ID_RE
+        .captures(text)
+        .map(|cap| (cap[1].to_string(), "tvdb_id"))
+}
+
+/// Resolve an IMDb/TVDB id found in `text` through the source's `find` (TMDB's `/find`).
+/// An id that belongs to both a movie and a show is settled by whether the files look
+/// like a show. Returns the id alongside the match.
+async fn identify_external_id(
+    text: &str,
+    files: &[rd_client::TorrentFile],
+    metadata_source: &dyn MetadataSource,
+) -> Option<(MediaMetadata, String)> {
+    let (id, source) = find_external_id(text)?;
+    let found = metadata_source.find(&id, source).await?;
+    let movie = found.movie_results.into_iter().next();
+    let tv = found.tv_results.into_iter().next();
+    let (result, media_type) = match (movie, tv) {
+        (Some(_), Some(tv)) if is_show_guess(files) => (tv, MediaType::Show),
+        (Some(movie), _) => (movie, MediaType::Movie),
+        (None, Some(tv)) => (tv, MediaType::Show),
+        (None, None) => {
+            debug!(
+                "{} has no title for {} {}",
+                metadata_source.id_source(),
+                source,
+                id
+            );
+            return None;
+        }
+    };
+    let year: Option<String> = result
+        .release_date
+        .map(|d| d.chars().filter(|c| c.is_ascii_digit()).take(4).collect())
+        .filter(|y: &String| y.len() == 4);
+    let external_id = metadata_source.external_id(&result.id.to_string());
+    info!(
+        "Identified {} ({:?}) as {:?} via {} {} ({})",
+        result.title, year, media_type, source, id, external_id
+    );
+    let metadata = MediaMetadata {
+        title: result.title,
+        year,
+        media_type,
+        external_id: Some(external_id),
+    };
+    Some((metadata, id))
+}
+
+/// Text of the smallest selected `.nfo` shipped in the torrent, read through `media` like a
+/// WebDAV read. `None` when there is none, it is too large or reading fails.
+pub async fn read_bundled_nfo(
+    info: &rd_client::TorrentInfo,
+    media: &DebridFileSystem,
+) -> Option<String> {
+    let (file, link) = selected_files(info, |path| path.to_lowercase().ends_with(".nfo"))
+        .into_iter()
+        .filter(|(f, _)| f.bytes > 0 && f.bytes <= MAX_NFO_SIZE)
+        .min_by_key(|(f, _)| f.bytes)?;
+    let locator = FileLocator {
+        hash: info.hash.clone(),
+        torrent_id: info.id.clone(),
+        file_id: file.id,
+        file_path: file.path.clone(),
+        link,
+    };
+    match media
+        .read_range(locator, file.bytes, 0, file.bytes as usize)
+        .await
+    {
+        Ok(content) => Some(String::from_utf8_lossy(&content).into_owned()),
+        Err(e) => {
+            debug!("Could not read {} for identification: {:?}", file.path, e);
+            None
+        }
     }
 }
 
@@ -145,13 +400,9 @@ fn select_best_match(
     normalized_query: &str,
     year: &Option<String>,
     is_show_guess: bool,
-) -> Option<(String, Option<String>, String, &'static str, MediaType)> {
+) -> Option<(String, Option<String>, String, MediaType)> {
     let is_exact = |r: &TmdbSearchResult| -> bool {
-        normalize_title(&r.title) == normalized_query
-            || r.original_title
-                .as_ref()
-                .map(|t| normalize_title(t) == normalized_query)
-                .unwrap_or(false)
+        known_titles(r).any(|t| normalize_title(t) == normalized_query)
     };
     let has_year_match = |r: &TmdbSearchResult| -> bool {
         year.as_ref()
@@ -198,7 +449,6 @@ fn select_best_match(
                 selected.title.clone(),
                 selected.release_date.clone(),
                 selected.id.to_string(),
-                "tmdb",
                 media_type,
             ))
         }
@@ -206,41 +456,39 @@ fn select_best_match(
             tv.title.clone(),
             tv.release_date.clone(),
             tv.id.to_string(),
-            "tmdb",
             MediaType::Show,
         )),
         (None, Some(movie)) => Some((
             movie.title.clone(),
             movie.release_date.clone(),
             movie.id.to_string(),
-            "tmdb",
             MediaType::Movie,
         )),
         (None, None) => None,
     }
 }
 
+/// Every title a search result is known by: its title, original title and any
+/// alternative or translated titles looked up for it.
+fn known_titles(result: &TmdbSearchResult) -> impl Iterator<Item = &str> {
+    std::iter::once(result.title.as_str())
+        .chain(result.original_title.as_deref())
+        .chain(result.alternative_titles.iter().map(String::as_str))
+}
+
 /// Score a search result based on how well it matches the query
 /// Higher score = better match
 fn score_result(result: &TmdbSearchResult, normalized_query: &str, year: &Option<String>) -> f64 {
     let mut score = 0.0;
 
     // Title match (most important)
-    let normalized_title = normalize_title(&result.title);
-    let normalized_original = result.original_title.as_ref().map(|t| normalize_title(t));
+    let normalized_titles: Vec<String> = known_titles(result).map(normalize_title).collect();
 
-    if normalized_title == normalized_query
-        || normalized_original
-            .as_ref()
-            .map(|t| t == normalized_query)
-            .unwrap_or(false)
-    {
+    if normalized_titles.iter().any(|t| t == normalized_query) {
         score += 1000.0; // Exact title match
-    } else if normalized_title.contains(normalized_query)
-        || normalized_original
-            .as_ref()
-            .map(|t| t.contains(normalized_query))
-            .unwrap_or(false)
+    } else if normalized_titles
+        .iter()
+        .any(|t| t.contains(normalized_query))
     {
         score += 100.0; // Partial title match
     }
@@ -297,21 +545,36 @@ fn score_result(result: &TmdbSearchResult, normalized_query: &str, year: &Option
 pub async fn identify_name(
     name: &str,
     files: &[rd_client::TorrentFile],
-    tmdb: &TmdbClient,
+    source: &dyn MetadataSource,
 ) -> Option<MediaMetadata> {
+    identify_name_scored(name, files, source)
+        .await
+        .ok()
+        .flatten()
+        .map(|identification| identification.metadata)
+}
+
+/// `identify_name`, keeping the scores and strategy behind the match.
+pub async fn identify_name_scored(
+    name: &str,
+    files: &[rd_client::TorrentFile],
+    source: &dyn MetadataSource,
+) -> Result<Option<Identification>, AppError> {
     let (cleaned_name, year) = clean_name(name);
     if cleaned_name.is_empty() || is_generic_title(&cleaned_name) {
-        return None;
+        return Ok(None);
     }
     let is_show_guess = is_show_guess(files);
 
     let normalized_cleaned = normalize_title(&cleaned_name);
 
-    // Try TMDB first
-    let (mut tv_results, mut movie_results) = tokio::join!(
-        tmdb.search_tv(&cleaned_name, year.as_deref()),
-        tmdb.search_movie(&cleaned_name, year.as_deref())
-    );
+    let (mut tv_results, mut movie_results) = tokio::try_join!(
+        source.search_tv(&cleaned_name, year.as_deref()),
+        source.search_movie(&cleaned_name, year.as_deref())
+    )?;
+    // The search behind each run of results, by where the run ends in `tv_results` and
+    // `movie_results`, so the match records the search that actually found it.
+    let mut origins = vec![(MatchStrategy::Direct, tv_results.len(), movie_results.len())];
 
     // If no results found, try CamelCase splitting as a fallback
     if tv_results.is_empty() && movie_results.is_empty() {
@@ -321,12 +584,17 @@ pub async fn identify_name(
                 "No results for '{}', trying CamelCase split: '{}'",
                 cleaned_name, split_name
             );
-            let (tv_extra, movie_extra) = tokio::join!(
-                tmdb.search_tv(&split_name, year.as_deref()),
-                tmdb.search_movie(&split_name, year.as_deref())
-            );
+            let (tv_extra, movie_extra) = tokio::try_join!(
+                source.search_tv(&split_name, year.as_deref()),
+                source.search_movie(&split_name, year.as_deref())
+            )?;
             tv_results.extend(tv_extra);
             movie_results.extend(movie_extra);
+            origins.push((
+                MatchStrategy::CamelCaseSplit,
+                tv_results.len(),
+                movie_results.len(),
+            ));
         }
     }
 
@@ -342,13 +610,18 @@ pub async fn identify_name(
                 "No results for '{}', trying stripped: '{}'",
                 cleaned_name, stripped
             );
-            let (tv_extra, movie_extra) = tokio::join!(
-                tmdb.search_tv(&stripped, year.as_deref()),
-                tmdb.search_movie(&stripped, year.as_deref())
-            );
+            let (tv_extra, movie_extra) = tokio::try_join!(
+                source.search_tv(&stripped, year.as_deref()),
+                source.search_movie(&stripped, year.as_deref())
+            )?;
             if !tv_extra.is_empty() || !movie_extra.is_empty() {
                 tv_results.extend(tv_extra);
                 movie_results.extend(movie_extra);
+                origins.push((
+                    MatchStrategy::PrefixStripped,
+                    tv_results.len(),
+                    movie_results.len(),
+                ));
                 break;
             }
         }
@@ -364,25 +637,26 @@ pub async fn identify_name(
                     "No results for '{}', trying after dash: '{}'",
                     cleaned_name, after_dash
                 );
-                let (tv_extra, movie_extra) = tokio::join!(
-                    tmdb.search_tv(after_dash, year.as_deref()),
-                    tmdb.search_movie(after_dash, year.as_deref())
-                );
+                let (tv_extra, movie_extra) = tokio::try_join!(
+                    source.search_tv(after_dash, year.as_deref()),
+                    source.search_movie(after_dash, year.as_deref())
+                )?;
                 tv_results.extend(tv_extra);
                 movie_results.extend(movie_extra);
+                origins.push((
+                    MatchStrategy::AfterDash,
+                    tv_results.len(),
+                    movie_results.len(),
+                ));
             }
         }
     }
 
     // Helper to check if results contain exact title match
     let has_exact_match = |results: &[TmdbSearchResult]| -> bool {
-        results.iter().any(|r| {
-            normalize_title(&r.title) == normalized_cleaned
-                || r.original_title
-                    .as_ref()
-                    .map(|t| normalize_title(t) == normalized_cleaned)
-                    .unwrap_or(false)
-        })
+        results
+            .iter()
+            .any(|r| known_titles(r).any(|t| normalize_title(t) == normalized_cleaned))
     };
 
     let tv_has_exact = has_exact_match(&tv_results);
@@ -390,12 +664,34 @@ pub async fn identify_name(
 
     // If we have a year but no exact title match yet, try searching without the year
     if !tv_has_exact && !movie_has_exact && year.is_some() {
-        let (tv_no_year, movie_no_year) = tokio::join!(
-            tmdb.search_tv(&cleaned_name, None),
-            tmdb.search_movie(&cleaned_name, None)
-        );
+        let (tv_no_year, movie_no_year) = tokio::try_join!(
+            source.search_tv(&cleaned_name, None),
+            source.search_movie(&cleaned_name, None)
+        )?;
         tv_results.extend(tv_no_year);
         movie_results.extend(movie_no_year);
+        origins.push((MatchStrategy::NoYear, tv_results.len(), movie_results.len()));
+    }
+
+    // Still no exact match: the release may use a romaji or localized title TMDB only
+    // knows as an alternative title or translation (e.g. "Shingeki no Kyojin").
+    if !has_exact_match(&tv_results) && !has_exact_match(&movie_results) {
+        tokio::join!(
+            add_alternative_titles(
+                &mut tv_results,
+                MediaType::Show,
+                source,
+                &normalized_cleaned,
+                &year
+            ),
+            add_alternative_titles(
+                &mut movie_results,
+                MediaType::Movie,
+                source,
+                &normalized_cleaned,
+                &year
+            )
+        );
     }
 
     // Score all results and pick the best TV and movie matches
@@ -413,22 +709,97 @@ pub async fn identify_name(
         is_show_guess,
     );
 
-    if let Some((title, release_date, id, source, mtype)) = selected {
+    if let Some((title, release_date, id, mtype)) = selected {
         let year_val =
             release_date.map(|d| d.chars().filter(|c| c.is_ascii_digit()).take(4).collect());
+        let external_id = source.external_id(&id);
         info!(
-            "Identified {} ({:?}) as {:?} via TMDB (ID: {})",
-            title, year_val, mtype, id
+            "Identified {} ({:?}) as {:?} ({})",
+            title, year_val, mtype, external_id
         );
-        return Some(MediaMetadata {
-            title,
-            year: year_val,
-            media_type: mtype,
-            external_id: Some(format!("{}:{}", source, id)),
-        });
+        let winner_results = match mtype {
+            MediaType::Show => &tv_results,
+            MediaType::Movie => &movie_results,
+        };
+        let is_winner = |r: &TmdbSearchResult| r.id.to_string() == id;
+        let found_at = winner_results.iter().position(is_winner).unwrap_or(0);
+        let strategy = origins
+            .iter()
+            .find(|(_, tv_end, movie_end)| match mtype {
+                MediaType::Show => found_at < *tv_end,
+                MediaType::Movie => found_at < *movie_end,
+            })
+            .map_or(MatchStrategy::Direct, |(strategy, _, _)| *strategy);
+        let score = winner_results
+            .iter()
+            .find(|r| is_winner(r))
+            .map(|r| score_result(r, &normalized_cleaned, &year));
+        let runner_up = tv_results
+            .iter()
+            .filter(|r| mtype == MediaType::Movie || !is_winner(r))
+            .chain(
+                movie_results
+                    .iter()
+                    .filter(|r| mtype == MediaType::Show || !is_winner(r)),
+            )
+            .map(|r| score_result(r, &normalized_cleaned, &year))
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        return Ok(Some(Identification {
+            metadata: MediaMetadata {
+                title,
+                year: year_val,
+                media_type: mtype,
+                external_id: Some(external_id),
+            },
+            query: name.to_string(),
+            strategy,
+            score,
+            runner_up,
+        }));
     }
 
-    None
+    Ok(None)
+}
+
+/// Look up alternative and translated titles for the `ALTERNATIVE_TITLE_CANDIDATES`
+/// best-scoring distinct results, so scoring can match them.
+async fn add_alternative_titles(
+    results: &mut [TmdbSearchResult],
+    media_type: MediaType,
+    source: &dyn MetadataSource,
+    normalized_query: &str,
+    year: &Option<String>,
+) {
+    let mut ranked: Vec<(f64, u32)> = results
+        .iter()
+        .map(|r| (score_result(r, normalized_query, year), r.id))
+        .collect();
+    ranked.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let mut candidates: Vec<u32> = Vec::new();
+    for (_, id) in ranked {
+        if candidates.len() == ALTERNATIVE_TITLE_CANDIDATES {
+            break;
+        }
+        if !candidates.contains(&id) {
+            candidates.push(id);
+        }
+    }
+    for id in candidates {
+        let titles = source.alternative_titles(&media_type, id).await;
+        if titles.is_empty() {
+            continue;
+        }
+        debug!(
+            "{} {:?} {} is also known as {:?}",
+            source.id_source(),
+            media_type,
+            id,
+            titles
+        );
+        for result in results.iter_mut().filter(|r| r.id == id) {
+            result.alternative_titles = titles.clone();
+        }
+    }
 }
 
 /// A title is "short" (≤3 characters) if it has at most 3 Unicode scalar values.
@@ -614,6 +985,8 @@ fn is_generic_title(s: &str) -> bool {
 mod tests {
     use super::*;
     use crate::rd_client::{TorrentFile, TorrentInfo};
+    use crate::test_support::{serve_once, spawn_json};
+    use std::sync::Arc;
 
     #[tokio::test]
     #[ignore]
@@ -1184,11 +1557,163 @@ mod tests {
             popularity,
             vote_average: vote_avg,
             vote_count,
+            alternative_titles: Vec::new(),
         }
     }
 
+    // --- Tests for IMDb/TVDB id identification ---
+
+    #[test]
+    fn needs_review_flags_weak_and_ambiguous_matches() {
+        let record = |strategy, score, runner_up| Identification {
+            metadata: MediaMetadata {
+                title: "Dune".to_string(),
+                year: Some("2021".to_string()),
+                media_type: MediaType::Movie,
+                external_id: Some("tmdb:438631".to_string()),
+            },
+            query: "Dune.2021.mkv".to_string(),
+            strategy,
+            score,
+            runner_up,
+        };
+        assert!(!record(MatchStrategy::Direct, Some(1450.0), Some(1200.0)).needs_review());
+        assert!(!record(MatchStrategy::Direct, Some(1450.0), None).needs_review());
+        assert!(!record(MatchStrategy::ExternalId, None, None).needs_review());
+        // No exact title match
+        assert!(record(MatchStrategy::AfterDash, Some(380.0), None).needs_review());
+        // Runner-up about as good as the winner
+        assert!(record(MatchStrategy::NoYear, Some(1200.0), Some(1180.0)).needs_review());
+        assert!(record(MatchStrategy::Unidentified, None, None).needs_review());
+    }
+
+    #[test]
+    fn find_external_id_reads_imdb_and_tvdb_ids() {
+        assert_eq!(
+            find_external_id("The.Matrix.1999.tt0133093.1080p"),
+            Some(("tt0133093".to_string(), "imdb_id"))
+        );
+        assert_eq!(
+            find_external_id("Link: https://www.imdb.com/title/tt10872600/"),
+            Some(("tt10872600".to_string(), "imdb_id"))
+        );
+        assert_eq!(
+            find_external_id("https://thetvdb.com/?tab=series&id=81189"),
+            Some(("81189".to_string(), "tvdb_id"))
+        );
+        assert_eq!(
+            find_external_id("Breaking Bad [tvdbid-81189]"),
+            Some(("81189".to_string(), "tvdb_id"))
+        );
+        assert_eq!(find_external_id("Matt.1999.1080p"), None);
+        assert_eq!(
+            find_external_id("https://thetvdb.com/series/breaking-bad"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn identify_torrent_resolves_nfo_imdb_id_via_find() {
+        let (base, server) = spawn_json(
+            r#"{"movie_results": [{"id": 603, "title": "The Matrix", "release_date": "1999-03-30"}],
+                "tv_results": []}"#,
+        )
+        .await;
+        let tmdb = TmdbClient::with_base_url("key".to_string(), base).unwrap();
+        let info = TorrentInfo {
+            filename: "TM.1999.1080p".to_string(),
+            files: vec![TorrentFile {
+                id: 1,
+                path: "/TM.1999.1080p/tm.mkv".to_string(),
+                bytes: 1000,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let nfo = "Release info\nIMDb: https://www.imdb.com/title/tt0133093/\n";
+        let metadata = identify_torrent_with_nfo(&info, Some(nfo), &tmdb).await;
+        assert_eq!(metadata.title, "The Matrix");
+        assert_eq!(metadata.year.as_deref(), Some("1999"));
+        assert_eq!(metadata.media_type, MediaType::Movie);
+        assert_eq!(metadata.external_id.as_deref(), Some("tmdb:603"));
+        // One deterministic lookup, no search.
+        assert_eq!(
+            server.await.unwrap().lines().next(),
+            Some("GET /find/tt0133093?api_key=key&external_source=imdb_id HTTP/1.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn read_bundled_nfo_fetches_the_release_nfo() {
+        use crate::provider::{DebridProvider, MockProvider};
+        use crate::repair::RepairManager;
+        use crate::vfs::DebridVfs;
+        use std::sync::Arc;
+
+        let body = "IMDb: tt0133093";
+        let (base, server) = serve_once("text/plain", body).await;
+        let provider: Arc<dyn DebridProvider> = Arc::new(MockProvider {
+            resolved_url: Some(format!("{}/movie.nfo", base)),
+            ..Default::default()
+        });
+        let media = DebridFileSystem::new(
+            provider.clone(),
+            Arc::new(tokio::sync::RwLock::new(DebridVfs::new())),
+            Arc::new(RepairManager::new(provider)),
+            reqwest::Client::new(),
+        );
+        let file = |id: u32, path: &str, bytes: u64, selected: u32| TorrentFile {
+            id,
+            path: path.to_string(),
+            bytes,
+            selected,
+        };
+        let info = TorrentInfo {
+            files: vec![
+                file(1, "/TM/tm.mkv", 1000, 1),
+                file(2, "/TM/huge.nfo", MAX_NFO_SIZE + 1, 1),
+                file(3, "/TM/TM.NFO", body.len() as u64, 1),
+                file(4, "/TM/other.nfo", 1, 0),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(read_bundled_nfo(&info, &media).await.as_deref(), Some(body));
+        assert_eq!(
+            server.await.unwrap().lines().next(),
+            Some("GET /movie.nfo HTTP/1.1")
+        );
+
+        // No selected NFO: nothing is fetched.
+        let bare = TorrentInfo {
+            files: vec![file(1, "/TM/tm.mkv", 1000, 1)],
+            ..Default::default()
+        };
+        assert_eq!(read_bundled_nfo(&bare, &media).await, None);
+    }
+
     // --- Tests for best_scored_result ---
 
+    #[test]
+    fn best_scored_result_matches_alternative_titles() {
+        let mut titan = make_result(
+            1429,
+            "Attack on Titan",
+            Some("2013-04-07"),
+            90.0,
+            None,
+            None,
+        );
+        let other = make_result(2, "Kyojin", Some("2013-01-01"), 5.0, None, None);
+        let query = normalize_title("Shingeki no Kyojin");
+        // Without its alternative titles, no title of the right show matches the romaji...
+        assert!(score_result(&titan, &query, &None) < 1000.0);
+        // ...once they are looked up, it is an exact match.
+        titan.alternative_titles = vec!["Shingeki no Kyojin".to_string()];
+        assert!(score_result(&titan, &query, &None) >= 1000.0);
+        let selected = select_best_match(Some(&titan), Some(&other), &query, &None, false);
+        assert_eq!(selected.unwrap().2, "1429");
+    }
+
     #[test]
     fn best_scored_result_empty_returns_none() {
         let results: Vec<TmdbSearchResult> = vec![];
@@ -1363,7 +1888,7 @@ mod tests {
             &None,
             true,
         );
-        let (title, _date, id, _source, media_type) = got.unwrap();
+        let (title, _date, id, media_type) = got.unwrap();
         assert_eq!(title, "Breaking Bad");
         assert_eq!(id, "100");
         assert_eq!(media_type, MediaType::Show);
@@ -1386,7 +1911,7 @@ mod tests {
             &None,
             false,
         );
-        let (title, _date, id, _source, media_type) = got.unwrap();
+        let (title, _date, id, media_type) = got.unwrap();
         assert_eq!(title, "Inception");
         assert_eq!(id, "200");
         assert_eq!(media_type, MediaType::Movie);
@@ -1400,7 +1925,7 @@ mod tests {
         let nq = normalize_title("Dune");
         let year = Some("2000".to_string());
         let got = select_best_match(Some(&tv), Some(&movie), &nq, &year, false);
-        let (_title, _date, id, _source, media_type) = got.unwrap();
+        let (_title, _date, id, media_type) = got.unwrap();
         assert_eq!(id, "10");
         assert_eq!(media_type, MediaType::Show);
     }
@@ -1413,7 +1938,7 @@ mod tests {
         let nq = normalize_title("Don");
         let year = Some("2022".to_string());
         let got = select_best_match(Some(&tv), Some(&movie), &nq, &year, true);
-        let (_title, _date, id, _source, media_type) = got.unwrap();
+        let (_title, _date, id, media_type) = got.unwrap();
         assert_eq!(id, "20");
         assert_eq!(media_type, MediaType::Movie);
     }
@@ -1439,7 +1964,7 @@ mod tests {
         );
         let nq = normalize_title("Sherwood");
         let got = select_best_match(Some(&tv), Some(&movie), &nq, &None, true);
-        let (_title, _date, id, _source, media_type) = got.unwrap();
+        let (_title, _date, id, media_type) = got.unwrap();
         assert_eq!(id, "10");
         assert_eq!(media_type, MediaType::Show);
     }
@@ -1465,7 +1990,7 @@ mod tests {
         );
         let nq = normalize_title("Sherwood");
         let got = select_best_match(Some(&tv), Some(&movie), &nq, &None, false);
-        let (_title, _date, id, _source, media_type) = got.unwrap();
+        let (_title, _date, id, media_type) = got.unwrap();
         assert_eq!(id, "20");
         assert_eq!(media_type, MediaType::Movie);
     }
@@ -1536,4 +2061,176 @@ mod tests {
             "Episode pattern in filename should be guessed as show"
         );
     }
+
+    /// A source that knows at most one show.
+    struct OneShow(&'static str, Option<TmdbSearchResult>);
+
+    #[async_trait::async_trait]
+    impl MetadataSource for OneShow {
+        fn id_source(&self) -> &'static str {
+            self.0
+        }
+        async fn search_movie(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(Vec::new())
+        }
+        async fn search_tv(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(self.1.iter().cloned().collect())
+        }
+        async fn details(
+            &self,
+            _: &MediaType,
+            _: &str,
+        ) -> Option<crate::tmdb_client::TitleDetails> {
+            None
+        }
+    }
+
+    /// A source that is down.
+    struct Down;
+
+    #[async_trait::async_trait]
+    impl MetadataSource for Down {
+        fn id_source(&self) -> &'static str {
+            "tmdb"
+        }
+        async fn search_movie(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Err(AppError::Metadata("TMDB search failed: 503".to_string()))
+        }
+        async fn search_tv(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Err(AppError::Metadata("TMDB search failed: 503".to_string()))
+        }
+        async fn details(
+            &self,
+            _: &MediaType,
+            _: &str,
+        ) -> Option<crate::tmdb_client::TitleDetails> {
+            None
+        }
+    }
+
+    /// A source that finds movies only for exact (query, year) searches.
+    struct Answers(Vec<(&'static str, Option<&'static str>, TmdbSearchResult)>);
+
+    #[async_trait::async_trait]
+    impl MetadataSource for Answers {
+        fn id_source(&self) -> &'static str {
+            "tmdb"
+        }
+        async fn search_movie(
+            &self,
+            query: &str,
+            year: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|(q, y, _)| *q == query && *y == year)
+                .map(|(_, _, result)| result.clone())
+                .collect())
+        }
+        async fn search_tv(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(Vec::new())
+        }
+        async fn details(
+            &self,
+            _: &MediaType,
+            _: &str,
+        ) -> Option<crate::tmdb_client::TitleDetails> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn strategy_names_the_search_that_found_the_match() {
+        let matrix = make_result(603, "The Matrix", Some("1999-03-30"), 80.0, None, None);
+        let name = "TheMatrix.1999.1080p.mkv";
+
+        let split = Answers(vec![("The Matrix", Some("1999"), matrix.clone())]);
+        let found = identify_name_scored(name, &[], &split)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.strategy, MatchStrategy::CamelCaseSplit);
+
+        // The split search contributed nothing: the match came from the original name
+        // without its year.
+        let no_year = Answers(vec![("TheMatrix", None, matrix)]);
+        let found = identify_name_scored(name, &[], &no_year)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.metadata.external_id.as_deref(), Some("tmdb:603"));
+        assert_eq!(found.strategy, MatchStrategy::NoYear);
+    }
+
+    fn lost_episode() -> TorrentInfo {
+        TorrentInfo {
+            id: "t1".to_string(),
+            filename: "Lost.S01E01.2004.1080p.mkv".to_string(),
+            files: vec![TorrentFile {
+                id: 1,
+                path: "/Lost.S01E01.2004.1080p.mkv".to_string(),
+                bytes: 1_000_000_000,
+                selected: 1,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn identify_with_sources_falls_back_until_a_source_matches() {
+        let lost = make_result(73739, "Lost", Some("2004-09-22"), 0.0, None, None);
+        let info = lost_episode();
+        let sources = MetadataSources::new(
+            vec![
+                Arc::new(OneShow("tmdb", None)),
+                Arc::new(OneShow("tvdb", Some(lost))),
+            ],
+            false,
+        );
+
+        let found = identify_with_sources(&info, None, &sources).await.unwrap();
+        assert_eq!(found.metadata.external_id.as_deref(), Some("tvdb:73739"));
+        assert_eq!(found.metadata.media_type, MediaType::Show);
+
+        let nothing = MetadataSources::new(vec![Arc::new(OneShow("tmdb", None))], false);
+        let found = identify_with_sources(&info, None, &nothing).await.unwrap();
+        assert_eq!(found.strategy, MatchStrategy::Unidentified);
+        assert_eq!(found.metadata.title, "Lost");
+    }
+
+    #[tokio::test]
+    async fn identify_with_sources_does_not_fall_back_past_a_failed_source() {
+        let lost = make_result(73739, "Lost", Some("2004-09-22"), 0.0, None, None);
+        let info = lost_episode();
+        let sources = MetadataSources::new(
+            vec![Arc::new(Down), Arc::new(OneShow("tvdb", Some(lost)))],
+            false,
+        );
+        assert!(identify_with_sources(&info, None, &sources).await.is_err());
+
+        // Callers without a fallback still get the unidentified guess.
+        let found = identify_torrent_scored(&info, None, &Down).await;
+        assert_eq!(found.strategy, MatchStrategy::Unidentified);
+    }
 }
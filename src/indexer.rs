@@ -0,0 +1,556 @@
+//! Torrent indexer abstraction and the Torznab client used to find releases for automated
+//! acquisition.
+//!
+//! Torznab is the torrent flavour of the Newznab API spoken by Jackett and Prowlarr:
+//! `GET <api>?t=search&q=...&apikey=...` returns an RSS feed whose `<item>`s carry
+//! `torznab:attr` elements for the info hash, magnet URL and seeders. `t=movie` and
+//! `t=tvsearch` additionally accept `tmdbid`/`imdbid` and `season`/`ep` parameters.
+
+use crate::error::AppError;
+use crate::identification::{clean_name, normalize_title};
+use crate::quality::{QualityProfile, Resolution};
+use crate::vfs::{parse_episode, MediaType};
+use reqwest::Client;
+use std::time::Duration;
+use tracing::warn;
+use xmltree::{Element, XMLNode};
+
+/// Newznab category for movies.
+pub const CATEGORY_MOVIES: u32 = 2000;
+/// Newznab category for TV.
+pub const CATEGORY_TV: u32 = 5000;
+
+/// One item from an indexer feed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Release {
+    pub title: String,
+    pub size: u64,
+    pub seeders: Option<u32>,
+    /// Lowercase hex info hash, when the indexer reports one.
+    pub info_hash: Option<String>,
+    pub magnet: Option<String>,
+}
+
+impl Release {
+    /// Magnet URI to hand to the debrid provider. Releases that only offer a `.torrent`
+    /// download can't be added (the provider API here is magnet-only) and return `None`.
+    pub fn magnet_uri(&self) -> Option<String> {
+        self.magnet.clone().or_else(|| {
+            self.info_hash
+                .as_ref()
+                .map(|h| format!("magnet:?xt=urn:btih:{}", h))
+        })
+    }
+}
+
+/// What to look for: a movie, a whole show, a season or a single episode. The title is
+/// sent as the free-text query and, with the year, used to check results; the ids and
+/// season/episode numbers narrow the search on indexers that support them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub media_type: MediaType,
+    pub title: String,
+    pub year: Option<u32>,
+    pub tmdb_id: Option<u64>,
+    pub imdb_id: Option<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+impl SearchQuery {
+    pub fn movie(title: &str, year: Option<u32>) -> Self {
+        Self {
+            media_type: MediaType::Movie,
+            title: title.to_string(),
+            year,
+            tmdb_id: None,
+            imdb_id: None,
+            season: None,
+            episode: None,
+        }
+    }
+
+    pub fn show(title: &str) -> Self {
+        Self {
+            media_type: MediaType::Show,
+            ..Self::movie(title, None)
+        }
+    }
+
+    /// Free-text form of the query, e.g. `Inception 2010` or `Breaking Bad S02E05`: sent
+    /// to the indexer when there are no ids or season/episode numbers to send instead.
+    pub fn text(&self) -> String {
+        match (&self.media_type, self.season, self.episode) {
+            (MediaType::Movie, ..) => match self.year {
+                Some(year) => format!("{} {}", self.title, year),
+                None => self.title.clone(),
+            },
+            (MediaType::Show, Some(season), Some(episode)) => {
+                format!("{} S{:02}E{:02}", self.title, season, episode)
+            }
+            (MediaType::Show, Some(season), None) => format!("{} S{:02}", self.title, season),
+            (MediaType::Show, None, _) => self.title.clone(),
+        }
+    }
+}
+
+/// A source of releases. Implemented by `TorznabClient`; acquisition code only depends on
+/// this trait so it can be driven by other indexers or a stand-in.
+#[async_trait::async_trait]
+pub trait TorrentIndexer: Send + Sync {
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<Release>, AppError>;
+}
+
+/// Check `release` as a candidate for `query`, or `None` if it is for something else:
+/// the cleaned title must match, a movie's year must match when both are known, an
+/// episode query only accepts that exact episode, and a show or season query only accepts
+/// packs (never a single episode). Returns the parsed resolution, used as a
+/// tie-breaker after the quality profile.
+fn match_release(query: &SearchQuery, release: &Release) -> Option<Option<Resolution>> {
+    release.magnet_uri()?;
+    let (title, year) = clean_name(&release.title);
+    if normalize_title(&title) != normalize_title(&query.title) {
+        return None;
+    }
+    if let (MediaType::Movie, Some(wanted), Some(found)) = (&query.media_type, query.year, year) {
+        if found != wanted.to_string() {
+            return None;
+        }
+    }
+    let episode = parse_episode(&release.title);
+    match (query.season, query.episode) {
+        (Some(season), Some(wanted)) if episode != Some((season, wanted)) => return None,
+        (_, None) if query.media_type == MediaType::Show && episode.is_some() => return None,
+        _ => {}
+    }
+    Some(Resolution::parse(&release.title))
+}
+
+/// The releases that match `query` and that `profile` accepts, best first: by profile
+/// score, then resolution, then seeders.
+pub fn rank_releases<'a>(
+    query: &SearchQuery,
+    releases: &'a [Release],
+    profile: &QualityProfile,
+) -> Vec<&'a Release> {
+    let mut scored: Vec<_> = releases
+        .iter()
+        .filter(|r| profile.accepts(&r.title, r.size))
+        .filter_map(|r| {
+            let resolution = match_release(query, r)?;
+            Some((
+                (profile.score(&r.title), resolution, r.seeders.unwrap_or(0)),
+                r,
+            ))
+        })
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, r)| r).collect()
+}
+
+pub struct TorznabClient {
+    client: Client,
+    /// Full Torznab API endpoint, e.g. `http://prowlarr:9696/1/api`.
+    base_url: String,
+    api_key: String,
+}
+
+impl TorznabClient {
+    pub fn new(base_url: String, api_key: String) -> Result<Self, AppError> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| AppError::Config(format!("Failed to build Torznab HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+
+    /// Build a client from `TORZNAB_URL` / `TORZNAB_API_KEY`. Returns `None` when
+    /// unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("TORZNAB_URL").ok()?;
+        let api_key = std::env::var("TORZNAB_API_KEY").unwrap_or_default();
+        if url.is_empty() {
+            return None;
+        }
+        match Self::new(url, api_key) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Failed to create Torznab client: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Search with the given Torznab parameters.
+    async fn fetch(&self, mut params: Vec<(&str, String)>) -> Result<Vec<Release>, AppError> {
+        if !self.api_key.is_empty() {
+            params.push(("apikey", self.api_key.clone()));
+        }
+        let body = self
+            .client
+            .get(&self.base_url)
+            .query(&params)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Http(e.without_url()))?
+            .text()
+            .await
+            .map_err(|e| AppError::Http(e.without_url()))?;
+        parse_feed(&body).map_err(AppError::Indexer)
+    }
+}
+
+#[async_trait::async_trait]
+impl TorrentIndexer for TorznabClient {
+    /// Movies use `t=movie` and shows `t=tvsearch`, restricted to the matching Newznab
+    /// category, with whatever ids and season/episode numbers the query carries. Those
+    /// come with the bare title as `q`: Jackett and Prowlarr add the year or episode
+    /// marker themselves, and a second copy in `q` usually finds nothing.
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<Release>, AppError> {
+        let (mode, category) = match query.media_type {
+            MediaType::Movie => ("movie", CATEGORY_MOVIES),
+            MediaType::Show => ("tvsearch", CATEGORY_TV),
+        };
+        let structured = query.tmdb_id.is_some()
+            || query.imdb_id.is_some()
+            || query.season.is_some()
+            || query.episode.is_some();
+        let text = if structured {
+            query.title.clone()
+        } else {
+            query.text()
+        };
+        let mut params = vec![
+            ("t", mode.to_string()),
+            ("q", text),
+            ("cat", category.to_string()),
+        ];
+        if let Some(id) = query.tmdb_id {
+            params.push(("tmdbid", id.to_string()));
+        }
+        if let Some(id) = &query.imdb_id {
+            params.push(("imdbid", id.clone()));
+        }
+        if let Some(season) = query.season {
+            params.push(("season", season.to_string()));
+        }
+        if let Some(episode) = query.episode {
+            params.push(("ep", episode.to_string()));
+        }
+        self.fetch(params).await
+    }
+}
+
+/// Parse a Torznab RSS feed. An `<error>` document (how Torznab reports bad keys or
+/// unsupported queries, usually with a 200 status) is returned as `Err`.
+pub fn parse_feed(xml: &str) -> Result<Vec<Release>, String> {
+    let root = Element::parse(xml.as_bytes()).map_err(|e| format!("invalid feed: {}", e))?;
+    if root.name == "error" {
+        return Err(format!(
+            "indexer returned error {}: {}",
+            root.attributes
+                .get("code")
+                .map(String::as_str)
+                .unwrap_or("?"),
+            root.attributes
+                .get("description")
+                .map(String::as_str)
+                .unwrap_or("unknown")
+        ));
+    }
+    let Some(channel) = root.get_child("channel") else {
+        return Err("feed has no channel".to_string());
+    };
+    Ok(child_elements(channel, "item")
+        .filter_map(parse_item)
+        .collect())
+}
+
+fn child_elements<'a>(parent: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    parent.children.iter().filter_map(move |node| match node {
+        XMLNode::Element(e) if e.name == name => Some(e),
+        _ => None,
+    })
+}
+
+fn parse_item(item: &Element) -> Option<Release> {
+    let title = item.get_child("title")?.get_text()?.trim().to_string();
+    if title.is_empty() {
+        return None;
+    }
+    let mut release = Release {
+        title,
+        ..Default::default()
+    };
+    if let Some(size) = item.get_child("size").and_then(|e| e.get_text()) {
+        release.size = size.trim().parse().unwrap_or(0);
+    }
+    let link = item
+        .get_child("link")
+        .and_then(|e| e.get_text())
+        .map(|t| t.trim().to_string());
+    if let Some(enclosure) = item.get_child("enclosure") {
+        if release.size == 0 {
+            release.size = enclosure
+                .attributes
+                .get("length")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(0);
+        }
+        if let Some(url) = enclosure.attributes.get("url") {
+            if url.starts_with("magnet:") {
+                release.magnet = Some(url.clone());
+            }
+        }
+    }
+    if release.magnet.is_none() {
+        release.magnet = link.filter(|l| l.starts_with("magnet:"));
+    }
+    for attr in child_elements(item, "attr") {
+        let (Some(name), Some(value)) = (attr.attributes.get("name"), attr.attributes.get("value"))
+        else {
+            continue;
+        };
+        match name.as_str() {
+            "seeders" => release.seeders = value.parse().ok(),
+            "infohash" => release.info_hash = Some(value.to_lowercase()),
+            "magneturl" => release.magnet = Some(value.clone()),
+            "size" if release.size == 0 => release.size = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    if release.info_hash.is_none() {
+        release.info_hash = release.magnet.as_deref().and_then(magnet_hash);
+    }
+    Some(release)
+}
+
+/// Extract the lowercase hex info hash from a magnet URI's `xt=urn:btih:` parameter.
+fn magnet_hash(magnet: &str) -> Option<String> {
+    let start = magnet.find("urn:btih:")? + "urn:btih:".len();
+    let hash: String = magnet[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    (hash.len() == 40).then(|| hash.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_once;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:torznab="http://torznab.com/schemas/2015/feed">
+  <channel>
+    <title>Indexer</title>
+    <item>
+      <title>Inception.2010.1080p.BluRay.x264-GROUP</title>
+      <size>10737418240</size>
+      <link>https://indexer/download/1</link>
+      <enclosure url="https://indexer/download/1" length="10737418240" type="application/x-bittorrent"/>
+      <torznab:attr name="seeders" value="120"/>
+      <torznab:attr name="infohash" value="ABCDEF0123456789ABCDEF0123456789ABCDEF01"/>
+    </item>
+    <item>
+      <title>Inception.2010.720p.WEB</title>
+      <link>magnet:?xt=urn:btih:1111111111111111111111111111111111111111&amp;dn=Inception</link>
+      <torznab:attr name="size" value="4000"/>
+    </item>
+    <item>
+      <title>Torrent file only</title>
+      <link>https://indexer/download/3</link>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn parse_feed_reads_items_and_torznab_attrs() {
+        let releases = parse_feed(FEED).unwrap();
+        assert_eq!(releases.len(), 3);
+
+        assert_eq!(releases[0].title, "Inception.2010.1080p.BluRay.x264-GROUP");
+        assert_eq!(releases[0].size, 10737418240);
+        assert_eq!(releases[0].seeders, Some(120));
+        assert_eq!(
+            releases[0].info_hash.as_deref(),
+            Some("abcdef0123456789abcdef0123456789abcdef01")
+        );
+        assert_eq!(
+            releases[0].magnet_uri().as_deref(),
+            Some("magnet:?xt=urn:btih:abcdef0123456789abcdef0123456789abcdef01")
+        );
+
+        // Hash recovered from the magnet link; size from the torznab attr.
+        assert_eq!(releases[1].size, 4000);
+        assert_eq!(
+            releases[1].info_hash.as_deref(),
+            Some("1111111111111111111111111111111111111111")
+        );
+        assert!(releases[1]
+            .magnet_uri()
+            .unwrap()
+            .starts_with("magnet:?xt=urn:btih:1111"));
+
+        // No hash and no magnet: nothing the provider can add.
+        assert_eq!(releases[2].magnet_uri(), None);
+    }
+
+    #[test]
+    fn parse_feed_surfaces_torznab_errors() {
+        let err = parse_feed(r#"<error code="100" description="Invalid API Key"/>"#).unwrap_err();
+        assert!(err.contains("100"));
+        assert!(err.contains("Invalid API Key"));
+        assert!(parse_feed("not xml").is_err());
+    }
+
+    #[tokio::test]
+    async fn movie_search_queries_endpoint_and_parses_response() {
+        let (base, server) = serve_once("application/rss+xml", FEED).await;
+        let url = format!("{}/api", base);
+        let client = TorznabClient::new(url, "key".to_string()).unwrap();
+        let query = SearchQuery {
+            imdb_id: Some("tt1375666".to_string()),
+            ..SearchQuery::movie("Inception", Some(2010))
+        };
+        let releases = client.search(&query).await.unwrap();
+        assert_eq!(releases.len(), 3);
+
+        let request = server.await.unwrap();
+        let request_line = request.lines().next().unwrap();
+        assert!(request_line
+            .starts_with("GET /api?t=movie&q=Inception&cat=2000&imdbid=tt1375666&apikey=key"));
+    }
+
+    #[tokio::test]
+    async fn search_without_ids_keeps_the_year_in_the_query() {
+        let (base, server) = serve_once("application/rss+xml", FEED).await;
+        let url = format!("{}/api", base);
+        let client = TorznabClient::new(url, String::new()).unwrap();
+        client
+            .search(&SearchQuery::movie("Inception", Some(2010)))
+            .await
+            .unwrap();
+
+        let request = server.await.unwrap();
+        let request_line = request.lines().next().unwrap();
+        assert!(request_line.starts_with("GET /api?t=movie&q=Inception+2010&cat=2000 "));
+    }
+
+    #[tokio::test]
+    async fn episode_search_sends_ids_season_and_episode() {
+        let (base, server) = serve_once("application/rss+xml", FEED).await;
+        let url = format!("{}/api", base);
+        let client = TorznabClient::new(url, String::new()).unwrap();
+        let query = SearchQuery {
+            tmdb_id: Some(1396),
+            season: Some(2),
+            episode: Some(5),
+            ..SearchQuery::show("Breaking Bad")
+        };
+        client.search(&query).await.unwrap();
+
+        let request = server.await.unwrap();
+        let request_line = request.lines().next().unwrap();
+        assert!(request_line
+            .starts_with("GET /api?t=tvsearch&q=Breaking+Bad&cat=5000&tmdbid=1396&season=2&ep=5 "));
+    }
+
+    fn release(title: &str, seeders: u32) -> Release {
+        Release {
+            title: title.to_string(),
+            seeders: Some(seeders),
+            info_hash: Some("abc".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rank_releases_filters_mismatches_and_prefers_resolution_then_seeders() {
+        let releases = vec![
+            release("Inception.2010.720p.WEB", 500),
+            release("Inception.2010.1080p.BluRay", 5),
+            release("Inception.2010.1080p.WEB", 50),
+            release("Inception.Dreams.2010.1080p", 100),
+            release("Inception.2011.1080p", 100),
+            Release {
+                info_hash: None,
+                ..release("Inception.2010.2160p", 500)
+            },
+        ];
+        let query = SearchQuery::movie("Inception", Some(2010));
+        let titles: Vec<&str> = rank_releases(&query, &releases, &QualityProfile::default())
+            .into_iter()
+            .map(|r| r.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Inception.2010.1080p.WEB",
+                "Inception.2010.1080p.BluRay",
+                "Inception.2010.720p.WEB"
+            ]
+        );
+    }
+
+    #[test]
+    fn rank_releases_requires_the_queried_episode() {
+        let query = SearchQuery {
+            season: Some(2),
+            episode: Some(2),
+            ..SearchQuery::show("Breaking Bad")
+        };
+        let releases = vec![
+            release("Breaking.Bad.S02E02.720p", 1),
+            release("Breaking.Bad.S02E03.720p", 1),
+            release("Breaking.Bad.S02.Complete", 1),
+            release("Better.Call.Saul.S02E02", 1),
+        ];
+        let ranked = rank_releases(&query, &releases, &QualityProfile::default());
+        assert_eq!(ranked, vec![&releases[0]]);
+        assert_eq!(query.text(), "Breaking Bad S02E02");
+    }
+
+    #[test]
+    fn rank_releases_only_accepts_packs_for_whole_shows() {
+        let query = SearchQuery::show("Breaking Bad");
+        let releases = vec![
+            release("Breaking.Bad.S01E01.1080p", 50),
+            release("Breaking.Bad.S01.1080p", 5),
+            release("Breaking.Bad.Complete.Series.720p", 1),
+        ];
+        let ranked = rank_releases(&query, &releases, &QualityProfile::default());
+        assert_eq!(ranked, vec![&releases[1], &releases[2]]);
+    }
+
+    #[test]
+    fn rank_releases_applies_quality_profile() {
+        let releases = vec![
+            release("Inception.2010.2160p.HDR.x265-GRP", 100),
+            release("Inception.2010.1080p.x264-GRP", 10),
+            release("Inception.2010.1080p.x264-BAD", 500),
+        ];
+        let profile = QualityProfile {
+            resolution: Some(crate::quality::Resolution::P1080),
+            banned_groups: vec!["bad".to_string()],
+            ..Default::default()
+        };
+        let query = SearchQuery::movie("Inception", Some(2010));
+        let titles: Vec<&str> = rank_releases(&query, &releases, &profile)
+            .into_iter()
+            .map(|r| r.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Inception.2010.1080p.x264-GRP",
+                "Inception.2010.2160p.HDR.x265-GRP"
+            ]
+        );
+    }
+}
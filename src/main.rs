@@ -1,12 +1,25 @@
 use dav_server::DavHandler;
+use debridmoviemapper::acquire::GRABS_TABLE;
+use debridmoviemapper::admin::{self, AdminApi};
+use debridmoviemapper::anime::{AnimeMapping, ANIME_SEASONS_TABLE};
 use debridmoviemapper::dav_fs::DebridFileSystem;
+use debridmoviemapper::details::{DetailsCache, DETAILS_TABLE};
+use debridmoviemapper::episode_catalog::{self, EpisodeCatalog, EPISODE_CATALOG_TABLE};
+use debridmoviemapper::episodes::EpisodeSync;
+use debridmoviemapper::indexer::{TorrentIndexer, TorznabClient};
+use debridmoviemapper::metadata_source::MetadataSources;
 use debridmoviemapper::provider::{choose_provider, DebridProvider, ProviderKind};
+use debridmoviemapper::quality::QualityProfile;
 use debridmoviemapper::rd_client::RealDebridClient;
 use debridmoviemapper::repair::RepairManager;
-use debridmoviemapper::tasks::{ScanConfig, MATCHES_TABLE};
+use debridmoviemapper::subtitles::SubtitleCache;
+use debridmoviemapper::tasks::{
+    backfill_identifications, ScanConfig, ScanState, HIDDEN_TABLE, MATCHES_TABLE, OVERRIDES_TABLE,
+};
 use debridmoviemapper::tmdb_client::TmdbClient;
 use debridmoviemapper::torbox_client::TorBoxClient;
-use debridmoviemapper::vfs::DebridVfs;
+use debridmoviemapper::vfs::{DebridVfs, VfsOptions};
+use debridmoviemapper::watchlist::WatchlistSync;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::Request;
@@ -78,7 +91,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Scan interval: {}s", scan_interval_secs);
 
     let tmdb_client = Arc::new(TmdbClient::new(tmdb_api_key)?);
-    let vfs = Arc::new(RwLock::new(DebridVfs::new()));
+    let quality = QualityProfile::from_env();
+    if quality != QualityProfile::default() {
+        info!("Quality profile: {:?}", quality);
+    }
     let repair_manager = Arc::new(RepairManager::new(provider.clone()));
 
     let jellyfin_client =
@@ -95,24 +111,104 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // clean error exit rather than a panic with a backtrace.
     let db = Arc::new(Database::create(&db_path)?);
 
-    // Ensure table exists on fresh databases
+    // Ensure tables exist on fresh databases
     {
         let write_txn = db.begin_write()?;
         write_txn.open_table(MATCHES_TABLE)?;
+        write_txn.open_table(OVERRIDES_TABLE)?;
+        write_txn.open_table(HIDDEN_TABLE)?;
+        write_txn.open_table(GRABS_TABLE)?;
+        write_txn.open_table(EPISODE_CATALOG_TABLE)?;
+        write_txn.open_table(ANIME_SEASONS_TABLE)?;
+        write_txn.open_table(DETAILS_TABLE)?;
+        let backfilled = backfill_identifications(&write_txn)?;
+        if backfilled > 0 {
+            info!(
+                "Recorded {} earlier unidentified match(es) for the review queue",
+                backfilled
+            );
+        }
+        if episode_catalog::drop_legacy_table(&write_txn)? {
+            info!("Dropped the legacy episode_titles table; its seasons are refetched into the episode catalog");
+        }
         write_txn.commit()?;
     }
 
+    let episode_catalog = EpisodeCatalog::from_env(&db).await.map(Arc::new);
+    if episode_catalog.is_some() {
+        info!("TMDB episode catalog enabled (RENAME_EPISODES / EPISODE_NFO)");
+    }
+    let details = DetailsCache::from_env(&db).await.map(Arc::new);
+    if details.is_some() {
+        info!("TMDB title details enabled (FULL_NFO / ARTWORK / COLLECTIONS)");
+    }
+    let subtitles = SubtitleCache::from_env().await.map(Arc::new);
+    if subtitles.is_some() {
+        info!("OpenSubtitles subtitle fetching enabled");
+    }
+    let anime = AnimeMapping::from_env(&db).await.map(Arc::new);
+    if anime.is_some() {
+        info!("Anime absolute episode mapping enabled");
+    }
+    let vfs = Arc::new(RwLock::new(DebridVfs::with_options(Arc::new(
+        VfsOptions::from_env(quality.clone(), episode_catalog, details, subtitles, anime),
+    ))));
+
+    let indexer =
+        TorznabClient::from_env().map(|client| Arc::new(client) as Arc<dyn TorrentIndexer>);
+    let watchlist = WatchlistSync::from_env(indexer.clone(), quality.clone()).map(Arc::new);
+    if watchlist.is_some() {
+        info!("Trakt watchlist sync enabled");
+    } else {
+        info!("Trakt watchlist sync disabled (set TRAKT_CLIENT_ID, TRAKT_USERNAME, TORZNAB_URL to enable)");
+    }
+    let episodes = EpisodeSync::from_env(indexer.clone(), quality.clone()).map(Arc::new);
+    if episodes.is_some() {
+        info!("New episode auto-grab enabled");
+    } else {
+        info!("New episode auto-grab disabled (set AUTO_GRAB_EPISODES=true and TORZNAB_URL to enable)");
+    }
+
+    let scan_state = Arc::new(ScanState::default());
+
+    let admin_api = match std::env::var("ADMIN_API_TOKEN") {
+        Ok(token) if !token.trim().is_empty() => {
+            info!(
+                "Admin API enabled at {} (dashboard at {})",
+                admin::API_PREFIX,
+                admin::UI_PATH
+            );
+            Some(Arc::new(AdminApi::new(
+                token.trim().to_string(),
+                provider.clone(),
+                tmdb_client.clone(),
+                vfs.clone(),
+                db.clone(),
+                repair_manager.clone(),
+                scan_state.clone(),
+            )))
+        }
+        _ => {
+            info!("Admin API disabled (set ADMIN_API_TOKEN to enable)");
+            None
+        }
+    };
+
     let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
 
     let scan_handle = tokio::spawn(debridmoviemapper::tasks::run_scan_loop(
         ScanConfig {
             rd_client: provider.clone(),
             tmdb_client: tmdb_client.clone(),
+            metadata_sources: Arc::new(MetadataSources::from_env(tmdb_client.clone())),
             vfs: vfs.clone(),
             db: db.clone(),
             repair_manager: repair_manager.clone(),
             interval_secs: scan_interval_secs,
             jellyfin_client,
+            state: scan_state,
+            watchlist,
+            episodes,
         },
         shutdown_rx,
     ));
@@ -180,6 +276,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 };
                 let io = TokioIo::new(stream);
                 let dav_handler = dav_handler.clone();
+                let admin_api = admin_api.clone();
 
                 tokio::task::spawn(async move {
                     let _permit = permit; // Hold permit until connection closes
@@ -188,7 +285,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             io,
                             service_fn(move |req: Request<hyper::body::Incoming>| {
                                 let dav_handler = dav_handler.clone();
-                                async move { Ok::<_, hyper::Error>(dav_handler.handle(req).await) }
+                                let admin_api = admin_api.clone();
+                                async move {
+                                    if let Some(api) = admin_api
+                                        .as_ref()
+                                        .filter(|_| admin::is_admin_path(req.uri().path()))
+                                    {
+                                        return Ok::<_, hyper::Error>(api.handle(req).await);
+                                    }
+                                    Ok::<_, hyper::Error>(dav_handler.handle(req).await)
+                                }
                             }),
                         )
                         .await
@@ -1,12 +1,31 @@
+pub mod acquire;
+pub mod admin;
+pub mod anime;
+pub mod artwork;
 pub mod dav_fs;
+pub mod details;
+pub mod episode_catalog;
+pub mod episodes;
 pub mod error;
 pub mod identification;
+pub mod indexer;
 pub mod jellyfin_client;
+pub mod metadata_source;
+pub mod naming;
+pub mod omdb_client;
+pub mod opensubtitles_client;
 pub mod provider;
+pub mod quality;
 pub mod ratelimit;
 pub mod rd_client;
 pub mod repair;
+pub mod subtitles;
 pub mod tasks;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod tmdb_client;
 pub mod torbox_client;
+pub mod trakt_client;
+pub mod tvdb_client;
 pub mod vfs;
+pub mod watchlist;
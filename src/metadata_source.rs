@@ -0,0 +1,242 @@
+//! Where titles are identified and described. TMDB is the primary source; TVDB and OMDb
+//! can be configured as fallbacks that are tried, in that order, when TMDB finds nothing.
+//! A source that errors ends the chain instead, so an outage never settles for a fallback.
+//! With `TVDB_SHOW_IDS=true`, torrents that look like shows are tried against TVDB first,
+//! so shows are keyed by the TVDB ids Jellyfin's TVDB plugin prefers.
+//!
+//! Every source returns search results in TMDB's shape (`TmdbSearchResult`), so scoring
+//! and match selection in `identification` work the same whichever source answered.
+
+use crate::error::AppError;
+use crate::identification::is_show_guess;
+use crate::omdb_client::OmdbClient;
+use crate::rd_client::TorrentFile;
+use crate::tmdb_client::{TitleDetails, TmdbClient, TmdbFindResponse, TmdbSearchResult};
+use crate::tvdb_client::TvdbClient;
+use crate::vfs::{env_flag, MediaType};
+use std::sync::Arc;
+use tracing::info;
+
+#[async_trait::async_trait]
+pub trait MetadataSource: Send + Sync {
+    /// Prefix of the external ids this source hands out (`tmdb`, `tvdb`, `imdb`).
+    fn id_source(&self) -> &'static str;
+
+    /// External id of the search result with id `id`.
+    fn external_id(&self, id: &str) -> String {
+        format!("{}:{}", self.id_source(), id)
+    }
+
+    /// Movies matching `query`. Empty when the source has no match; an error when it could
+    /// not be asked, so callers can tell an outage from a miss.
+    async fn search_movie(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError>;
+
+    /// Shows matching `query`, as `search_movie`.
+    async fn search_tv(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError>;
+
+    /// Details of the title with this source's id `id` (the part of the external id after
+    /// the prefix). `None` if the request fails.
+    async fn details(&self, media_type: &MediaType, id: &str) -> Option<TitleDetails>;
+
+    /// Alternative and translated titles of a search result, for sources that keep them
+    /// apart from search results.
+    async fn alternative_titles(&self, _media_type: &MediaType, _id: u32) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The titles an id from another database (`imdb_id`, `tvdb_id`) belongs to, for
+    /// sources that can look it up.
+    async fn find(&self, _external_id: &str, _external_source: &str) -> Option<TmdbFindResponse> {
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataSource for TmdbClient {
+    fn id_source(&self) -> &'static str {
+        "tmdb"
+    }
+
+    async fn search_movie(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        TmdbClient::search_movie(self, query, year).await
+    }
+
+    async fn search_tv(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        TmdbClient::search_tv(self, query, year).await
+    }
+
+    async fn details(&self, media_type: &MediaType, id: &str) -> Option<TitleDetails> {
+        TmdbClient::details(self, media_type, id.parse().ok()?).await
+    }
+
+    async fn alternative_titles(&self, media_type: &MediaType, id: u32) -> Vec<String> {
+        TmdbClient::alternative_titles(self, media_type, id).await
+    }
+
+    async fn find(&self, external_id: &str, external_source: &str) -> Option<TmdbFindResponse> {
+        TmdbClient::find(self, external_id, external_source).await
+    }
+}
+
+/// The configured sources: the fallback chain for identification, and the source behind
+/// each external id for details.
+pub struct MetadataSources {
+    /// In fallback order; the first is the primary source.
+    sources: Vec<Arc<dyn MetadataSource>>,
+    /// Try TVDB first for torrents that look like shows.
+    tvdb_shows: bool,
+}
+
+impl MetadataSources {
+    pub fn new(sources: Vec<Arc<dyn MetadataSource>>, tvdb_shows: bool) -> Self {
+        Self {
+            sources,
+            tvdb_shows,
+        }
+    }
+
+    /// TMDB, followed by TVDB (`TVDB_API_KEY`) and OMDb (`OMDB_API_KEY`) when configured.
+    /// `TVDB_SHOW_IDS=true` puts TVDB first for shows.
+    pub fn from_env(tmdb: Arc<TmdbClient>) -> Self {
+        let mut sources: Vec<Arc<dyn MetadataSource>> = vec![tmdb];
+        let tvdb = TvdbClient::from_env().map(Arc::new);
+        let tvdb_shows = tvdb.is_some() && env_flag("TVDB_SHOW_IDS");
+        if let Some(tvdb) = tvdb {
+            sources.push(tvdb);
+        }
+        if let Some(omdb) = OmdbClient::from_env() {
+            sources.push(Arc::new(omdb));
+        }
+        if sources.len() > 1 {
+            let names: Vec<&str> = sources.iter().map(|s| s.id_source()).collect();
+            info!(
+                "Metadata sources: {}{}",
+                names.join(" → "),
+                if tvdb_shows {
+                    " (TVDB first for shows)"
+                } else {
+                    ""
+                }
+            );
+        }
+        Self::new(sources, tvdb_shows)
+    }
+
+    /// Sources to identify a torrent with `files` against, in order.
+    pub fn chain_for(&self, files: &[TorrentFile]) -> Vec<&dyn MetadataSource> {
+        let mut chain: Vec<&dyn MetadataSource> = self.sources.iter().map(|s| &**s).collect();
+        if self.tvdb_shows && is_show_guess(files) {
+            // Stable: TVDB moves to the front, the rest keep their order.
+            chain.sort_by_key(|s| s.id_source() != "tvdb");
+        }
+        chain
+    }
+
+    /// Details for a title by its external id (`tvdb:81189`), from the source that issued
+    /// it. `None` for ids of unconfigured sources or if the request fails.
+    pub async fn details(&self, media_type: &MediaType, external_id: &str) -> Option<TitleDetails> {
+        let (prefix, id) = external_id.split_once(':')?;
+        let source = self.sources.iter().find(|s| s.id_source() == prefix)?;
+        source.details(media_type, id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    #[async_trait::async_trait]
+    impl MetadataSource for Named {
+        fn id_source(&self) -> &'static str {
+            self.0
+        }
+        async fn search_movie(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(Vec::new())
+        }
+        async fn search_tv(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> Result<Vec<TmdbSearchResult>, AppError> {
+            Ok(Vec::new())
+        }
+        async fn details(&self, _: &MediaType, id: &str) -> Option<TitleDetails> {
+            Some(TitleDetails {
+                plot: Some(format!("{} {}", self.0, id)),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn sources(tvdb_shows: bool) -> MetadataSources {
+        MetadataSources::new(
+            vec![
+                Arc::new(Named("tmdb")),
+                Arc::new(Named("tvdb")),
+                Arc::new(Named("imdb")),
+            ],
+            tvdb_shows,
+        )
+    }
+
+    fn file(path: &str) -> TorrentFile {
+        TorrentFile {
+            id: 1,
+            path: path.to_string(),
+            bytes: 1,
+            selected: 1,
+        }
+    }
+
+    #[test]
+    fn chain_puts_tvdb_first_for_shows_only_when_asked() {
+        let ids = |chain: Vec<&dyn MetadataSource>| -> Vec<&str> {
+            chain.iter().map(|s| s.id_source()).collect()
+        };
+        let show = [file("Show.S01E01.mkv")];
+        let movie = [file("Movie.2010.mkv")];
+        assert_eq!(
+            ids(sources(false).chain_for(&show)),
+            ["tmdb", "tvdb", "imdb"]
+        );
+        assert_eq!(
+            ids(sources(true).chain_for(&show)),
+            ["tvdb", "tmdb", "imdb"]
+        );
+        assert_eq!(
+            ids(sources(true).chain_for(&movie)),
+            ["tmdb", "tvdb", "imdb"]
+        );
+    }
+
+    #[tokio::test]
+    async fn details_route_by_external_id_prefix() {
+        let sources = sources(false);
+        let details = sources.details(&MediaType::Show, "tvdb:81189").await;
+        assert_eq!(details.unwrap().plot.as_deref(), Some("tvdb 81189"));
+        assert!(sources.details(&MediaType::Show, "trakt:1").await.is_none());
+        assert!(sources.details(&MediaType::Show, "81189").await.is_none());
+    }
+}
@@ -0,0 +1,248 @@
+//! Naming strategies for the VFS: how title folders, movie files and episode files are
+//! named, and whether NFO files are written. Selected with `NAMING_PROFILE`.
+
+use crate::episode_catalog::CatalogEpisode;
+use crate::tmdb_client::TitleDetails;
+use crate::vfs::{parse_episode_range, sanitize_filename, DebridVfs, MediaMetadata, MediaType};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Decides the names `DebridVfs::build_with` gives to what it exposes.
+pub trait NamingStrategy: Send + Sync + Debug {
+    /// Folder name for a title. Unidentified titles (no external id) may share a name;
+    /// the VFS numbers them to keep folders unique.
+    fn folder_name(&self, metadata: &MediaMetadata) -> String;
+
+    /// Stem for a movie's video file (`Title (Year)`), or `None` to keep the torrent's own
+    /// file names. Also prefixes multi-version file names when set.
+    fn movie_stem(&self, metadata: &MediaMetadata) -> Option<String>;
+
+    /// Name for an episode file given the original filename, or `None` to keep it.
+    fn episode_name(&self, metadata: &MediaMetadata, filename: &str) -> Option<String>;
+
+    /// File name and content of the title's NFO, or `None` if the profile writes none.
+    /// `details` is set in full NFO mode once the title's TMDB details are cached.
+    fn nfo(
+        &self,
+        metadata: &MediaMetadata,
+        details: Option<&TitleDetails>,
+    ) -> Option<(&'static str, Vec<u8>)>;
+
+    /// Content of the NFO describing an episode file that covers `episodes` (number and
+    /// TMDB details, when cached) of `season`, or `None` if the profile writes none.
+    fn episode_nfo(
+        &self,
+        metadata: &MediaMetadata,
+        season: u32,
+        episodes: &[(u32, Option<CatalogEpisode>)],
+    ) -> Option<Vec<u8>>;
+}
+
+/// Jellyfin/Kodi layout: `Title [tmdbid-123]` folders, original file names and a
+/// `movie.nfo`/`tvshow.nfo` per title.
+#[derive(Debug, Default)]
+pub struct JellyfinNaming;
+
+impl NamingStrategy for JellyfinNaming {
+    fn folder_name(&self, metadata: &MediaMetadata) -> String {
+        let base_name = sanitize_filename(&metadata.title);
+        match &metadata.external_id {
+            Some(id) => match id.split_once(':') {
+                Some((source, raw_id)) => format!("{} [{}id-{}]", base_name, source, raw_id),
+                None => format!("{} [id={}]", base_name, id),
+            },
+            None => base_name,
+        }
+    }
+
+    fn movie_stem(&self, _metadata: &MediaMetadata) -> Option<String> {
+        None
+    }
+
+    fn episode_name(&self, _metadata: &MediaMetadata, _filename: &str) -> Option<String> {
+        None
+    }
+
+    fn nfo(
+        &self,
+        metadata: &MediaMetadata,
+        details: Option<&TitleDetails>,
+    ) -> Option<(&'static str, Vec<u8>)> {
+        let name = match metadata.media_type {
+            MediaType::Movie => "movie.nfo",
+            MediaType::Show => "tvshow.nfo",
+        };
+        Some((name, DebridVfs::generate_nfo(metadata, details)))
+    }
+
+    fn episode_nfo(
+        &self,
+        metadata: &MediaMetadata,
+        season: u32,
+        episodes: &[(u32, Option<CatalogEpisode>)],
+    ) -> Option<Vec<u8>> {
+        Some(DebridVfs::generate_episode_nfo(metadata, season, episodes))
+    }
+}
+
+/// `Title (Year)`, or the bare title when the year is unknown.
+pub(crate) fn title_with_year(metadata: &MediaMetadata) -> String {
+    let title = sanitize_filename(&metadata.title);
+    match &metadata.year {
+        Some(year) => format!("{} ({})", title, year),
+        None => title,
+    }
+}
+
+/// Plex layout: `Title (Year) {tmdb-123}` folders, `Title (Year).ext` movie files,
+/// `Show (Year) - s01e02.ext` episode files (`s01e01-e02` for a multi-episode file), and
+/// no NFOs (Plex ignores them).
+#[derive(Debug, Default)]
+pub struct PlexNaming;
+
+impl NamingStrategy for PlexNaming {
+    fn folder_name(&self, metadata: &MediaMetadata) -> String {
+        let base_name = title_with_year(metadata);
+        match metadata
+            .external_id
+            .as_deref()
+            .and_then(|id| id.split_once(':'))
+        {
+            Some((source, raw_id)) => format!("{} {{{}-{}}}", base_name, source, raw_id),
+            None => base_name,
+        }
+    }
+
+    fn movie_stem(&self, metadata: &MediaMetadata) -> Option<String> {
+        Some(title_with_year(metadata))
+    }
+
+    fn episode_name(&self, metadata: &MediaMetadata, filename: &str) -> Option<String> {
+        let (season, first, last) = parse_episode_range(filename)?;
+        let ext = filename.rfind('.').map(|i| &filename[i..]).unwrap_or("");
+        let mut name = format!(
+            "{} - s{:02}e{:02}",
+            title_with_year(metadata),
+            season,
+            first
+        );
+        if last > first {
+            name.push_str(&format!("-e{:02}", last));
+        }
+        name.push_str(ext);
+        Some(name)
+    }
+
+    fn nfo(
+        &self,
+        _metadata: &MediaMetadata,
+        _details: Option<&TitleDetails>,
+    ) -> Option<(&'static str, Vec<u8>)> {
+        None
+    }
+
+    fn episode_nfo(
+        &self,
+        _metadata: &MediaMetadata,
+        _season: u32,
+        _episodes: &[(u32, Option<CatalogEpisode>)],
+    ) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Strategy named by `NAMING_PROFILE` (`jellyfin`, the default, or `plex`).
+pub fn naming_from_env() -> Arc<dyn NamingStrategy> {
+    match std::env::var("NAMING_PROFILE") {
+        Ok(v) if v.trim().eq_ignore_ascii_case("plex") => Arc::new(PlexNaming),
+        Ok(v) if v.trim().is_empty() || v.trim().eq_ignore_ascii_case("jellyfin") => {
+            Arc::new(JellyfinNaming)
+        }
+        Ok(v) => {
+            warn!(
+                "Invalid NAMING_PROFILE value '{}', falling back to jellyfin",
+                v
+            );
+            Arc::new(JellyfinNaming)
+        }
+        Err(_) => Arc::new(JellyfinNaming),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(media_type: MediaType, external_id: Option<&str>) -> MediaMetadata {
+        MediaMetadata {
+            title: "Blade Runner: 2049".to_string(),
+            year: Some("2017".to_string()),
+            media_type,
+            external_id: external_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn jellyfin_naming_matches_existing_layout() {
+        let movie = metadata(MediaType::Movie, Some("tmdb:335984"));
+        assert_eq!(
+            JellyfinNaming.folder_name(&movie),
+            "Blade Runner - 2049 [tmdbid-335984]"
+        );
+        assert_eq!(JellyfinNaming.movie_stem(&movie), None);
+        assert_eq!(JellyfinNaming.nfo(&movie, None).unwrap().0, "movie.nfo");
+        assert_eq!(
+            JellyfinNaming.folder_name(&metadata(MediaType::Show, None)),
+            "Blade Runner - 2049"
+        );
+    }
+
+    #[test]
+    fn plex_naming_uses_brace_hints_and_renames_files() {
+        let movie = metadata(MediaType::Movie, Some("tmdb:335984"));
+        assert_eq!(
+            PlexNaming.folder_name(&movie),
+            "Blade Runner - 2049 (2017) {tmdb-335984}"
+        );
+        assert_eq!(
+            PlexNaming.movie_stem(&movie).as_deref(),
+            Some("Blade Runner - 2049 (2017)")
+        );
+        assert!(PlexNaming.nfo(&movie, None).is_none());
+
+        let show = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            ..metadata(MediaType::Show, Some("tmdb:95396"))
+        };
+        assert_eq!(
+            PlexNaming
+                .episode_name(&show, "Severance.S01E02.1080p.WEB.mkv")
+                .as_deref(),
+            Some("Severance (2022) - s01e02.mkv")
+        );
+        assert_eq!(PlexNaming.episode_name(&show, "Severance.Pilot.mkv"), None);
+    }
+
+    #[test]
+    fn plex_naming_keeps_multi_episode_ranges() {
+        let show = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            ..metadata(MediaType::Show, Some("tmdb:95396"))
+        };
+        assert_eq!(
+            PlexNaming
+                .episode_name(&show, "Severance.S01E01E02.1080p.WEB.mkv")
+                .as_deref(),
+            Some("Severance (2022) - s01e01-e02.mkv")
+        );
+        assert_eq!(
+            PlexNaming
+                .episode_name(&show, "Severance.S01E01-E03.1080p.WEB.mkv")
+                .as_deref(),
+            Some("Severance (2022) - s01e01-e03.mkv")
+        );
+    }
+}
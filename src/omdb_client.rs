@@ -0,0 +1,332 @@
+use crate::error::AppError;
+use crate::metadata_source::MetadataSource;
+use crate::tmdb_client::{CastMember, TitleDetails, TmdbFindResponse, TmdbSearchResult};
+use crate::vfs::MediaType;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{error, warn};
+
+const DEFAULT_OMDB_URL: &str = "https://www.omdbapi.com";
+
+/// `?s=`: a page of titles matching a query. Misses come back as `"Response": "False"`
+/// without `Search`.
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default, rename = "Search")]
+    search: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Year")]
+    year: String,
+    #[serde(rename = "imdbID")]
+    imdb_id: String,
+}
+
+/// `?i=`: one title. OMDb writes "N/A" for missing values.
+#[derive(Debug, Deserialize)]
+struct TitleResponse {
+    #[serde(rename = "Title")]
+    title: Option<String>,
+    #[serde(rename = "Year")]
+    year: Option<String>,
+    #[serde(rename = "imdbID")]
+    imdb_id: Option<String>,
+    #[serde(rename = "Type")]
+    kind: Option<String>,
+    #[serde(rename = "Plot")]
+    plot: Option<String>,
+    #[serde(rename = "Genre")]
+    genre: Option<String>,
+    #[serde(rename = "Runtime")]
+    runtime: Option<String>,
+    #[serde(rename = "Rated")]
+    rated: Option<String>,
+    #[serde(rename = "Actors")]
+    actors: Option<String>,
+    #[serde(rename = "Poster")]
+    poster: Option<String>,
+    #[serde(rename = "Production")]
+    production: Option<String>,
+    #[serde(rename = "imdbRating")]
+    imdb_rating: Option<String>,
+    #[serde(rename = "imdbVotes")]
+    imdb_votes: Option<String>,
+}
+
+/// A value OMDb actually provided (not empty or "N/A").
+fn given(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty() && v != "N/A")
+}
+
+/// A comma-separated OMDb list ("Action, Crime, Drama").
+fn list(value: Option<String>) -> Vec<String> {
+    given(value)
+        .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The numeric part of an IMDb id (`tt0133093` → 133093), which is what search results
+/// carry as their id.
+fn imdb_number(imdb_id: &str) -> Option<u32> {
+    imdb_id.strip_prefix("tt")?.parse().ok()
+}
+
+/// Search results and lookups keep the first year of ranges like "2008–2013".
+fn first_year(year: &str) -> Option<String> {
+    let year: String = year.chars().take_while(|c| c.is_ascii_digit()).collect();
+    (year.len() == 4).then_some(year)
+}
+
+/// Client for the OMDb API, which keys titles by IMDb id.
+pub struct OmdbClient {
+    client: Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl std::fmt::Debug for OmdbClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OmdbClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OmdbClient {
+    pub fn new(base_url: String, api_key: String) -> Result<Self, AppError> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| AppError::Config(format!("Failed to build OMDb HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+
+    /// Build a client from `OMDB_API_KEY` (and optionally `OMDB_API_URL`). Returns `None`
+    /// when unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("OMDB_API_KEY").ok()?;
+        if api_key.trim().is_empty() {
+            return None;
+        }
+        let base_url =
+            std::env::var("OMDB_API_URL").unwrap_or_else(|_| DEFAULT_OMDB_URL.to_string());
+        match Self::new(base_url, api_key.trim().to_string()) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Failed to create OMDb client: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, params: &[(&str, &str)]) -> Option<T> {
+        let url = format!("{}/", self.base_url);
+        let result = self
+            .client
+            .get(&url)
+            .query(&[("apikey", self.api_key.as_str())])
+            .query(params)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        match result {
+            Ok(resp) => match resp.json().await {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    error!("OMDb response could not be read: {}", e.without_url());
+                    None
+                }
+            },
+            Err(e) => {
+                error!("OMDb request failed: {}", e.without_url());
+                None
+            }
+        }
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        kind: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        let mut params = vec![("s", query), ("type", kind)];
+        if let Some(year) = year {
+            params.push(("y", year));
+        }
+        let Some(resp) = self.get::<SearchResponse>(&params).await else {
+            return Err(AppError::Metadata("OMDb search failed".to_string()));
+        };
+        Ok(resp
+            .search
+            .into_iter()
+            .filter_map(|hit| {
+                Some(TmdbSearchResult {
+                    id: imdb_number(&hit.imdb_id)?,
+                    title: hit.title,
+                    original_title: None,
+                    release_date: first_year(&hit.year),
+                    popularity: 0.0,
+                    vote_average: None,
+                    vote_count: None,
+                    alternative_titles: Vec::new(),
+                })
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataSource for OmdbClient {
+    fn id_source(&self) -> &'static str {
+        "imdb"
+    }
+
+    fn external_id(&self, id: &str) -> String {
+        format!("imdb:tt{:0>7}", id)
+    }
+
+    async fn search_movie(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        self.search(query, "movie", year).await
+    }
+
+    async fn search_tv(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        self.search(query, "series", year).await
+    }
+
+    async fn details(&self, _media_type: &MediaType, id: &str) -> Option<TitleDetails> {
+        let resp = self
+            .get::<TitleResponse>(&[("i", id), ("plot", "full")])
+            .await?;
+        given(resp.imdb_id.clone())?;
+        let rating = given(resp.imdb_rating)
+            .and_then(|r| r.parse::<f64>().ok())
+            .zip(given(resp.imdb_votes).and_then(|v| v.replace(',', "").parse::<u32>().ok()))
+            .filter(|&(_, votes)| votes > 0);
+        Some(TitleDetails {
+            plot: given(resp.plot),
+            genres: list(resp.genre),
+            studios: list(resp.production),
+            runtime: given(resp.runtime)
+                .and_then(|r| r.trim_end_matches(" min").parse().ok())
+                .filter(|&m: &u32| m > 0),
+            certification: given(resp.rated),
+            cast: list(resp.actors)
+                .into_iter()
+                .map(|name| CastMember {
+                    name,
+                    role: None,
+                    thumb_url: None,
+                })
+                .collect(),
+            poster_url: given(resp.poster),
+            rating,
+            ..Default::default()
+        })
+    }
+
+    async fn find(&self, external_id: &str, external_source: &str) -> Option<TmdbFindResponse> {
+        if external_source != "imdb_id" {
+            return None;
+        }
+        let resp = self.get::<TitleResponse>(&[("i", external_id)]).await?;
+        let result = TmdbSearchResult {
+            id: imdb_number(&given(resp.imdb_id)?)?,
+            title: given(resp.title)?,
+            original_title: None,
+            release_date: given(resp.year).and_then(|y| first_year(&y)),
+            popularity: 0.0,
+            vote_average: None,
+            vote_count: None,
+            alternative_titles: Vec::new(),
+        };
+        let mut found = TmdbFindResponse::default();
+        match given(resp.kind).as_deref() {
+            Some("series") => found.tv_results.push(result),
+            _ => found.movie_results.push(result),
+        }
+        Some(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_json;
+
+    #[tokio::test]
+    async fn search_maps_hits_to_imdb_numbered_results() {
+        let (base, server) = spawn_json(
+            r#"{"Search": [
+                {"Title": "Breaking Bad", "Year": "2008–2013", "imdbID": "tt0903747", "Type": "series"},
+                {"Title": "Odd", "Year": "2010", "imdbID": "nm0000001", "Type": "series"}
+            ], "totalResults": "2", "Response": "True"}"#,
+        )
+        .await;
+        let client = OmdbClient::new(base, "key".to_string()).unwrap();
+        let results = client.search_tv("Breaking Bad", None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 903747);
+        assert_eq!(results[0].release_date.as_deref(), Some("2008"));
+        assert_eq!(
+            client.external_id(&results[0].id.to_string()),
+            "imdb:tt0903747"
+        );
+        assert_eq!(
+            server.await.unwrap().lines().next(),
+            Some("GET /?apikey=key&s=Breaking+Bad&type=series HTTP/1.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn details_skip_missing_values() {
+        let (base, _server) = spawn_json(
+            r#"{"Title": "The Matrix", "Year": "1999", "Rated": "R", "Runtime": "136 min",
+                "Genre": "Action, Sci-Fi", "Actors": "Keanu Reeves, Laurence Fishburne",
+                "Plot": "A hacker learns the truth.", "Poster": "N/A", "imdbRating": "8.7",
+                "imdbVotes": "2,100,000", "imdbID": "tt0133093", "Type": "movie",
+                "Production": "N/A", "Response": "True"}"#,
+        )
+        .await;
+        let client = OmdbClient::new(base, "key".to_string()).unwrap();
+        let details = client
+            .details(&MediaType::Movie, "tt0133093")
+            .await
+            .unwrap();
+        assert_eq!(details.plot.as_deref(), Some("A hacker learns the truth."));
+        assert_eq!(details.genres, vec!["Action", "Sci-Fi"]);
+        assert!(details.studios.is_empty());
+        assert_eq!(details.runtime, Some(136));
+        assert_eq!(details.certification.as_deref(), Some("R"));
+        assert_eq!(details.cast.len(), 2);
+        assert_eq!(details.poster_url, None);
+        assert_eq!(details.rating, Some((8.7, 2_100_000)));
+    }
+
+    #[test]
+    fn first_year_reads_ranges() {
+        assert_eq!(first_year("2008–2013").as_deref(), Some("2008"));
+        assert_eq!(first_year("2019–").as_deref(), Some("2019"));
+        assert_eq!(first_year("N/A"), None);
+    }
+}
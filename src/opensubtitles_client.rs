@@ -0,0 +1,271 @@
+use crate::error::AppError;
+use bytes::Bytes;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_OPENSUBTITLES_URL: &str = "https://api.opensubtitles.com/api/v1";
+/// OpenSubtitles rejects requests without an identifying user agent.
+const USER_AGENT: &str = concat!("DebridMovieMapper v", env!("CARGO_PKG_VERSION"));
+/// Bytes hashed from each end of a file by the OpenSubtitles hash.
+pub const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// What to search subtitles for: a movie, or an episode of a show, by TMDB id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleQuery {
+    pub tmdb_id: u32,
+    /// Season and episode, for an episode of the show `tmdb_id`.
+    pub episode: Option<(u32, u32)>,
+    /// OpenSubtitles hash of the video file, when it could be computed.
+    pub moviehash: Option<String>,
+    pub languages: Vec<String>,
+}
+
+/// One downloadable subtitle file from a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleMatch {
+    pub file_id: u64,
+    pub language: String,
+    /// Whether it was made for exactly this file (its hash matched).
+    pub hash_match: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    attributes: SearchAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchAttributes {
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    moviehash_match: bool,
+    #[serde(default)]
+    files: Vec<SearchFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchFile {
+    file_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadResponse {
+    link: String,
+}
+
+/// Client for an OpenSubtitles-compatible REST API (search and download).
+pub struct OpenSubtitlesClient {
+    client: Client,
+    base_url: String,
+    /// Pre-built sensitive header value so the API key is never printed in debug output.
+    api_key_header: HeaderValue,
+}
+
+impl std::fmt::Debug for OpenSubtitlesClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenSubtitlesClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpenSubtitlesClient {
+    pub fn new(base_url: String, api_key: String) -> Result<Self, AppError> {
+        let mut api_key_header = HeaderValue::from_str(&api_key).map_err(|e| {
+            AppError::Config(format!(
+                "OpenSubtitles API key contains invalid HTTP header characters: {}",
+                e
+            ))
+        })?;
+        api_key_header.set_sensitive(true);
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|e| {
+                AppError::Config(format!("Failed to build OpenSubtitles HTTP client: {}", e))
+            })?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key_header,
+        })
+    }
+
+    /// Build a client from `OPENSUBTITLES_API_KEY` (and optionally `OPENSUBTITLES_API_URL`).
+    /// Returns `None` when unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("OPENSUBTITLES_API_KEY").ok()?;
+        if api_key.trim().is_empty() {
+            return None;
+        }
+        let base_url = std::env::var("OPENSUBTITLES_API_URL")
+            .unwrap_or_else(|_| DEFAULT_OPENSUBTITLES_URL.to_string());
+        match Self::new(base_url, api_key.trim().to_string()) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Failed to create OpenSubtitles client: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Subtitle files matching `query`, in the API's ranking order.
+    pub async fn search(
+        &self,
+        query: &SubtitleQuery,
+    ) -> Result<Vec<SubtitleMatch>, reqwest::Error> {
+        // The API redirects unless parameters are lowercase and alphabetically ordered.
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some((_, episode)) = query.episode {
+            params.push(("episode_number", episode.to_string()));
+        }
+        params.push(("languages", query.languages.join(",").to_lowercase()));
+        if let Some(hash) = &query.moviehash {
+            params.push(("moviehash", hash.clone()));
+        }
+        match query.episode {
+            Some((season, _)) => {
+                params.push(("parent_tmdb_id", query.tmdb_id.to_string()));
+                params.push(("season_number", season.to_string()));
+            }
+            None => params.push(("tmdb_id", query.tmdb_id.to_string())),
+        }
+
+        let url = format!("{}/subtitles", self.base_url);
+        let resp: SearchResponse = self
+            .client
+            .get(&url)
+            .query(&params)
+            .header("Api-Key", self.api_key_header.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        Ok(resp
+            .data
+            .into_iter()
+            .filter_map(|result| {
+                let attributes = result.attributes;
+                let file_id = attributes.files.first()?.file_id;
+                Some(SubtitleMatch {
+                    file_id,
+                    language: attributes.language?.to_lowercase(),
+                    hash_match: attributes.moviehash_match,
+                })
+            })
+            .collect())
+    }
+
+    /// Download a subtitle file: request a temporary link, then fetch it.
+    pub async fn download(&self, file_id: u64) -> Result<Bytes, reqwest::Error> {
+        let url = format!("{}/download", self.base_url);
+        let link: DownloadResponse = self
+            .client
+            .post(&url)
+            .header("Api-Key", self.api_key_header.clone())
+            .json(&serde_json::json!({ "file_id": file_id }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        self.client
+            .get(&link.link)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url())?
+            .bytes()
+            .await
+            .map_err(|e| e.without_url())
+    }
+}
+
+/// OpenSubtitles hash of a file of `size` bytes: its size plus the little-endian u64
+/// words of its first and last `HASH_CHUNK_SIZE` bytes, wrapping. `None` for files
+/// shorter than one chunk or chunks of the wrong length.
+pub fn opensubtitles_hash(size: u64, head: &[u8], tail: &[u8]) -> Option<String> {
+    if size < HASH_CHUNK_SIZE as u64
+        || head.len() != HASH_CHUNK_SIZE
+        || tail.len() != HASH_CHUNK_SIZE
+    {
+        return None;
+    }
+    let hash = head
+        .chunks_exact(8)
+        .chain(tail.chunks_exact(8))
+        .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+        .fold(size, u64::wrapping_add);
+    Some(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_routes;
+
+    #[tokio::test]
+    async fn search_sends_sorted_episode_params() {
+        let (base, mut requests) = spawn_routes(vec![(
+            "/subtitles",
+            r#"{"data": [
+                {"attributes": {"language": "en", "moviehash_match": true, "files": [{"file_id": 7}]}},
+                {"attributes": {"language": "fr", "files": []}}
+            ]}"#,
+        )])
+        .await;
+        let client = OpenSubtitlesClient::new(base, "key".to_string()).unwrap();
+        let matches = client
+            .search(&SubtitleQuery {
+                tmdb_id: 1396,
+                episode: Some((1, 2)),
+                moviehash: Some("8e245d9679d31e12".to_string()),
+                languages: vec!["EN".to_string(), "fr".to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            matches,
+            vec![SubtitleMatch {
+                file_id: 7,
+                language: "en".to_string(),
+                hash_match: true,
+            }]
+        );
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "GET /subtitles?episode_number=2&languages=en%2Cfr&moviehash=8e245d9679d31e12\
+             &parent_tmdb_id=1396&season_number=1 HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn opensubtitles_hash_sums_size_and_chunks() {
+        let mut head = vec![0u8; HASH_CHUNK_SIZE];
+        head[0] = 1;
+        let mut tail = vec![0u8; HASH_CHUNK_SIZE];
+        tail[8] = 2;
+        assert_eq!(
+            opensubtitles_hash(1 << 20, &head, &tail).as_deref(),
+            Some("0000000000100003")
+        );
+        assert_eq!(opensubtitles_hash(100, &head, &tail), None);
+        assert_eq!(opensubtitles_hash(1 << 20, &head[..8], &tail), None);
+    }
+}
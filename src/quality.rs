@@ -0,0 +1,457 @@
+//! Release quality parsed from torrent and file names, and the configurable profile used
+//! to choose between releases of the same title.
+
+use regex::Regex;
+use std::sync::LazyLock;
+use tracing::warn;
+
+static RESOLUTION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:(2160|1080|720|576|480)[pi]|(4k|uhd)|(sd|dvdrip|xvid))\b").unwrap()
+});
+
+static DOLBY_VISION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(dv|dovi|dolby[ ._-]?vision)\b").unwrap());
+
+static HDR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(hdr(10)?(\+|plus)?|hlg)\b").unwrap());
+
+static CODEC_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:(av1)|([xh][ .]?265|hevc)|([xh][ .]?264|avc))\b").unwrap()
+});
+
+/// Audio formats, in the same order as `Audio`'s variants.
+static AUDIO_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:(atmos)|(truehd)|(dts[ ._-]?(?:hd|x|ma))|(dts)|(ddp|dd\+|e-?ac-?3)|(dd|ac-?3)|(aac))",
+    )
+    .unwrap()
+});
+
+/// Named cuts and editions of a film, as they appear in release names.
+static EDITION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(director'?s[ ._-]cut|extended(?:[ ._-](?:cut|edition))?|unrated|uncut|theatrical(?:[ ._-]cut)?|final[ ._-]cut|ultimate[ ._-]edition|special[ ._-]edition|remastered|imax|criterion)\b",
+    )
+    .unwrap()
+});
+
+/// Trailing `-GROUP` of a scene-style name (extension already stripped).
+static GROUP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"-([A-Za-z0-9]+)\s*(?:\[[^\]]*\])?$").unwrap());
+
+/// Vertical resolution bucket of a release, ordered worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Resolution {
+    Sd,
+    P720,
+    P1080,
+    P2160,
+}
+
+impl Resolution {
+    /// Resolution named in `name` (`1080p`, `2160p`, `4K`, `DVDRip`, ...), if any.
+    pub fn parse(name: &str) -> Option<Self> {
+        let cap = RESOLUTION_RE.captures(name)?;
+        if let Some(lines) = cap.get(1) {
+            return Some(match lines.as_str() {
+                "2160" => Self::P2160,
+                "1080" => Self::P1080,
+                "720" => Self::P720,
+                _ => Self::Sd,
+            });
+        }
+        if cap.get(2).is_some() {
+            return Some(Self::P2160);
+        }
+        Some(Self::Sd)
+    }
+
+    /// Conventional label, e.g. `1080p`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Sd => "SD",
+            Self::P720 => "720p",
+            Self::P1080 => "1080p",
+            Self::P2160 => "2160p",
+        }
+    }
+
+    /// Parse a configured value such as `1080p`, `2160p`/`4k`, `720p` or `sd`.
+    fn from_config(value: &str) -> Option<Self> {
+        Self::parse(value.trim())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    Av1,
+    H265,
+    H264,
+}
+
+impl Codec {
+    fn parse(name: &str) -> Option<Self> {
+        let cap = CODEC_RE.captures(name)?;
+        if cap.get(1).is_some() {
+            Some(Self::Av1)
+        } else if cap.get(2).is_some() {
+            Some(Self::H265)
+        } else {
+            Some(Self::H264)
+        }
+    }
+}
+
+/// Audio format of a release, ordered best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Audio {
+    Atmos,
+    TrueHd,
+    DtsHd,
+    Dts,
+    DolbyDigitalPlus,
+    DolbyDigital,
+    Aac,
+}
+
+impl Audio {
+    /// The best audio format named in `name` (`TrueHD.Atmos` is Atmos).
+    fn parse(name: &str) -> Option<Self> {
+        const ALL: [Audio; 7] = [
+            Audio::Atmos,
+            Audio::TrueHd,
+            Audio::DtsHd,
+            Audio::Dts,
+            Audio::DolbyDigitalPlus,
+            Audio::DolbyDigital,
+            Audio::Aac,
+        ];
+        AUDIO_RE
+            .captures_iter(name)
+            .filter_map(|cap| (1..=ALL.len()).find(|&i| cap.get(i).is_some()))
+            .map(|i| ALL[i - 1])
+            .min()
+    }
+}
+
+/// Everything a release name says about its quality.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseQuality {
+    pub resolution: Option<Resolution>,
+    pub hdr: bool,
+    pub dolby_vision: bool,
+    pub codec: Option<Codec>,
+    pub audio: Option<Audio>,
+    pub group: Option<String>,
+}
+
+impl ReleaseQuality {
+    pub fn parse(name: &str) -> Self {
+        let stem = crate::vfs::VIDEO_EXTENSIONS
+            .iter()
+            .find_map(|ext| {
+                let split = name.len().checked_sub(ext.len())?;
+                (name.is_char_boundary(split) && name[split..].eq_ignore_ascii_case(ext))
+                    .then(|| &name[..split])
+            })
+            .unwrap_or(name);
+        Self {
+            resolution: Resolution::parse(stem),
+            hdr: HDR_RE.is_match(stem),
+            dolby_vision: DOLBY_VISION_RE.is_match(stem),
+            codec: Codec::parse(stem),
+            audio: Audio::parse(stem),
+            group: GROUP_RE
+                .captures(stem)
+                .map(|cap| cap[1].to_string())
+                // A bare trailing number is an episode or part number, not a group.
+                .filter(|g| !g.bytes().all(|b| b.is_ascii_digit())),
+        }
+    }
+}
+
+/// Edition named in a release, title-cased with spaces (`Directors Cut`, `Extended`).
+pub fn edition(name: &str) -> Option<String> {
+    let found = EDITION_RE.find(name)?.as_str();
+    Some(
+        found
+            .split([' ', '.', '_', '-'])
+            .map(|word| {
+                let word = word.replace('\'', "").to_lowercase();
+                match word.as_str() {
+                    "imax" => "IMAX".to_string(),
+                    _ => {
+                        let mut chars = word.chars();
+                        chars
+                            .next()
+                            .map(|c| c.to_uppercase().chain(chars).collect())
+                            .unwrap_or_default()
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Short label distinguishing one release of a film from another, e.g. `2160p HDR` or
+/// `Extended 1080p`. `None` when the name says nothing about edition or quality.
+pub fn version_label(name: &str) -> Option<String> {
+    let quality = ReleaseQuality::parse(name);
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(edition(name));
+    parts.extend(quality.resolution.map(|r| r.label().to_string()));
+    if quality.dolby_vision {
+        parts.push("DV".to_string());
+    } else if quality.hdr {
+        parts.push("HDR".to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// How a profile treats an optional feature such as HDR.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preference {
+    #[default]
+    Any,
+    Prefer,
+    Avoid,
+}
+
+impl Preference {
+    fn from_config(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "any" | "" => Some(Self::Any),
+            "prefer" => Some(Self::Prefer),
+            "avoid" => Some(Self::Avoid),
+            _ => None,
+        }
+    }
+
+    fn score(self, present: bool, weight: i64) -> i64 {
+        match (self, present) {
+            (Self::Prefer, true) | (Self::Avoid, false) => weight,
+            _ => 0,
+        }
+    }
+}
+
+/// Preferences used to pick the release that represents a title in the VFS and to rank
+/// indexer candidates. The default profile has no preferences, so every release scores
+/// the same and callers fall back to their own ordering (largest torrent, best seeded).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityProfile {
+    /// Preferred resolution; releases further from it score lower.
+    pub resolution: Option<Resolution>,
+    pub hdr: Preference,
+    pub dolby_vision: Preference,
+    /// Preferred codecs, most preferred first.
+    pub codecs: Vec<Codec>,
+    /// Preferred audio formats, most preferred first.
+    pub audio: Vec<Audio>,
+    /// Releases larger than this many bytes are rejected.
+    pub max_size: Option<u64>,
+    /// Release groups (case-insensitive) that are rejected.
+    pub banned_groups: Vec<String>,
+}
+
+impl QualityProfile {
+    /// Build a profile from the `QUALITY_*` variables. Unset variables leave that
+    /// preference off; unparseable values are logged and ignored.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let mut profile = Self::default();
+        if let Some(v) = var("QUALITY_RESOLUTION") {
+            profile.resolution = Resolution::from_config(&v);
+            if profile.resolution.is_none() {
+                warn!("Invalid QUALITY_RESOLUTION value '{}', ignoring", v);
+            }
+        }
+        for (name, target) in [
+            ("QUALITY_HDR", &mut profile.hdr),
+            ("QUALITY_DOLBY_VISION", &mut profile.dolby_vision),
+        ] {
+            if let Some(v) = var(name) {
+                match Preference::from_config(&v) {
+                    Some(p) => *target = p,
+                    None => warn!("Invalid {} value '{}', expected any/prefer/avoid", name, v),
+                }
+            }
+        }
+        if let Some(v) = var("QUALITY_CODECS") {
+            profile.codecs = parse_list(&v, "QUALITY_CODECS", Codec::parse);
+        }
+        if let Some(v) = var("QUALITY_AUDIO") {
+            profile.audio = parse_list(&v, "QUALITY_AUDIO", Audio::parse);
+        }
+        if let Some(v) = var("QUALITY_MAX_SIZE_GB") {
+            match v.trim().parse::<f64>() {
+                Ok(gb) if gb > 0.0 => profile.max_size = Some((gb * 1_073_741_824.0) as u64),
+                _ => warn!("Invalid QUALITY_MAX_SIZE_GB value '{}', ignoring", v),
+            }
+        }
+        if let Some(v) = var("QUALITY_BANNED_GROUPS") {
+            profile.banned_groups = v
+                .split(',')
+                .map(|g| g.trim().to_string())
+                .filter(|g| !g.is_empty())
+                .collect();
+        }
+        profile
+    }
+
+    /// Whether the profile allows a release at all (size limit and banned groups).
+    pub fn accepts(&self, name: &str, size: u64) -> bool {
+        if self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+        match ReleaseQuality::parse(name).group {
+            Some(group) => !self
+                .banned_groups
+                .iter()
+                .any(|b| b.eq_ignore_ascii_case(&group)),
+            None => true,
+        }
+    }
+
+    /// How well `name` matches the profile's preferences; higher is better. Rejection is
+    /// separate (see `accepts`), so callers can still fall back to a rejected release.
+    pub fn score(&self, name: &str) -> i64 {
+        let quality = ReleaseQuality::parse(name);
+        let mut score = 0;
+        if let Some(wanted) = self.resolution {
+            score += match quality.resolution {
+                Some(found) => 400 - 100 * (wanted as i64 - found as i64).abs(),
+                None => 0,
+            };
+        }
+        score += self.hdr.score(quality.hdr || quality.dolby_vision, 50);
+        score += self.dolby_vision.score(quality.dolby_vision, 50);
+        score += rank_in(&self.codecs, quality.codec, 20);
+        score += rank_in(&self.audio, quality.audio, 10);
+        score
+    }
+}
+
+/// `weight` × how early `found` appears in `preferred` (first entry scores most).
+fn rank_in<T: PartialEq>(preferred: &[T], found: Option<T>, weight: i64) -> i64 {
+    found
+        .and_then(|f| preferred.iter().position(|p| *p == f))
+        .map(|i| (preferred.len() - i) as i64 * weight)
+        .unwrap_or(0)
+}
+
+fn parse_list<T>(value: &str, name: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<T> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .filter_map(|v| {
+            let parsed = parse(v);
+            if parsed.is_none() {
+                warn!("Unknown {} entry '{}', ignoring", name, v);
+            }
+            parsed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_parses_common_markers() {
+        assert_eq!(
+            Resolution::parse("Movie.2010.1080p.BluRay"),
+            Some(Resolution::P1080)
+        );
+        assert_eq!(
+            Resolution::parse("Movie 2160p UHD"),
+            Some(Resolution::P2160)
+        );
+        assert_eq!(Resolution::parse("Movie.4K.HDR"), Some(Resolution::P2160));
+        assert_eq!(
+            Resolution::parse("Show.S01E01.720p"),
+            Some(Resolution::P720)
+        );
+        assert_eq!(
+            Resolution::parse("Movie.1999.DVDRip.XviD"),
+            Some(Resolution::Sd)
+        );
+        assert_eq!(Resolution::parse("Movie.1999.576p"), Some(Resolution::Sd));
+        assert_eq!(Resolution::parse("Movie.2010.WEB"), None);
+        assert!(Resolution::P2160 > Resolution::P1080);
+    }
+
+    #[test]
+    fn release_quality_parses_name() {
+        let q =
+            ReleaseQuality::parse("Dune.2021.2160p.UHD.BluRay.DV.HDR10.TrueHD.Atmos.x265-GRP.mkv");
+        assert_eq!(q.resolution, Some(Resolution::P2160));
+        assert!(q.hdr && q.dolby_vision);
+        assert_eq!(q.codec, Some(Codec::H265));
+        assert_eq!(q.audio, Some(Audio::Atmos));
+        assert_eq!(q.group.as_deref(), Some("GRP"));
+
+        let q = ReleaseQuality::parse("Show - 137.mkv");
+        assert_eq!(q, ReleaseQuality::default());
+        let q = ReleaseQuality::parse("Movie.2010.1080p.WEB-DL.DDP5.1.H.264-NTb");
+        assert_eq!(q.codec, Some(Codec::H264));
+        assert_eq!(q.audio, Some(Audio::DolbyDigitalPlus));
+        assert_eq!(q.group.as_deref(), Some("NTb"));
+    }
+
+    #[test]
+    fn profile_scores_preferences_and_rejects_banned_or_oversized() {
+        let profile = QualityProfile {
+            resolution: Some(Resolution::P1080),
+            hdr: Preference::Prefer,
+            dolby_vision: Preference::Avoid,
+            codecs: vec![Codec::H265, Codec::H264],
+            max_size: Some(50),
+            banned_groups: vec!["yify".to_string()],
+            ..Default::default()
+        };
+        let hdr_1080 = profile.score("Movie.1080p.HDR.x265-A");
+        let sdr_1080 = profile.score("Movie.1080p.x264-A");
+        let dv_2160 = profile.score("Movie.2160p.DV.HDR.x265-A");
+        let sdr_720 = profile.score("Movie.720p.x264-A");
+        assert!(hdr_1080 > sdr_1080);
+        assert!(sdr_1080 > dv_2160);
+        assert!(sdr_1080 > sdr_720);
+
+        assert!(profile.accepts("Movie.1080p-GROUP", 50));
+        assert!(!profile.accepts("Movie.1080p-GROUP", 51));
+        assert!(!profile.accepts("Movie.1080p.WEB-YIFY", 1));
+
+        // The default profile has no opinion.
+        let default = QualityProfile::default();
+        assert_eq!(
+            default.score("Movie.2160p.HDR"),
+            default.score("Movie.720p")
+        );
+        assert!(default.accepts("Movie.720p-YIFY", u64::MAX));
+    }
+
+    #[test]
+    fn version_label_combines_edition_resolution_and_hdr() {
+        assert_eq!(
+            version_label("Blade.Runner.1982.Final.Cut.2160p.UHD.DV.HDR-GRP").as_deref(),
+            Some("Final Cut 2160p DV")
+        );
+        assert_eq!(
+            version_label("Aliens.1986.Directors.Cut.1080p.BluRay").as_deref(),
+            Some("Directors Cut 1080p")
+        );
+        assert_eq!(
+            version_label("Dunkirk.2017.IMAX.2160p.HDR").as_deref(),
+            Some("IMAX 2160p HDR")
+        );
+        assert_eq!(
+            version_label("Movie.2010.720p.WEB").as_deref(),
+            Some("720p")
+        );
+        assert_eq!(version_label("Movie.2010.WEB"), None);
+    }
+}
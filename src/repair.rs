@@ -6,7 +6,7 @@ use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub enum RepairState {
     Healthy,
     Broken,
@@ -486,6 +486,16 @@ impl RepairManager {
             .collect()
     }
 
+    /// Current repair state of every torrent the manager is tracking. Torrents that have
+    /// never been marked broken are absent (implicitly healthy).
+    pub async fn repair_states(&self) -> HashMap<String, RepairState> {
+        let health_map = self.health_status.read().await;
+        health_map
+            .iter()
+            .map(|(id, h)| (id.clone(), h.state.clone()))
+            .collect()
+    }
+
     /// Get summary of repair status
     pub async fn get_status_summary(&self) -> (usize, usize, usize) {
         let health_map = self.health_status.read().await;
@@ -0,0 +1,603 @@
+//! External subtitle files shipped inside torrents (`.srt`, `.ass`, ...). The VFS exposes
+//! each next to the video it belongs to, named `Video.<lang>[.forced][.sdh].<ext>` so media
+//! servers pick it up as an external subtitle track. Videos without bundled subtitles can
+//! get `.lang.srt` files fetched from an OpenSubtitles-compatible API (`SubtitleCache`).
+
+use crate::dav_fs::DebridFileSystem;
+use crate::opensubtitles_client::{
+    opensubtitles_hash, OpenSubtitlesClient, SubtitleQuery, HASH_CHUNK_SIZE,
+};
+use crate::provider::FileLocator;
+use crate::rd_client::TorrentInfo;
+use crate::vfs::{
+    parse_episode, parse_episode_range, streamable_files, subtitle_files, MediaMetadata, MediaType,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// Videos searched per refresh; OpenSubtitles limits downloads per day.
+const MAX_VIDEOS_PER_REFRESH: usize = 10;
+/// Before searching again for a video that got no subtitles.
+const RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub const SUBTITLE_EXTENSIONS: &[&str] = &[".srt", ".ass", ".ssa", ".sub", ".idx", ".vtt"];
+
+/// ISO 639-1 code per language, with the tokens that name it in subtitle filenames.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("en", &["en", "eng", "english"]),
+    ("fr", &["fr", "fre", "fra", "french", "francais"]),
+    ("de", &["de", "ger", "deu", "german", "deutsch"]),
+    ("es", &["es", "spa", "spanish", "espanol", "castellano"]),
+    ("it", &["it", "ita", "italian", "italiano"]),
+    ("pt", &["pt", "por", "portuguese", "brazilian"]),
+    ("nl", &["nl", "dut", "nld", "dutch"]),
+    ("sv", &["sv", "swe", "swedish"]),
+    ("da", &["da", "dan", "danish"]),
+    ("no", &["no", "nor", "nob", "norwegian"]),
+    ("fi", &["fi", "fin", "finnish"]),
+    ("pl", &["pl", "pol", "polish"]),
+    ("cs", &["cs", "cze", "ces", "czech"]),
+    ("hu", &["hu", "hun", "hungarian"]),
+    ("ro", &["ro", "rum", "ron", "romanian"]),
+    ("el", &["el", "gre", "ell", "greek"]),
+    ("tr", &["tr", "tur", "turkish"]),
+    ("ru", &["ru", "rus", "russian"]),
+    ("uk", &["uk", "ukr", "ukrainian"]),
+    ("ar", &["ar", "ara", "arabic"]),
+    ("he", &["he", "heb", "hebrew"]),
+    ("hi", &["hin", "hindi"]),
+    ("ja", &["ja", "jpn", "japanese"]),
+    ("ko", &["ko", "kor", "korean"]),
+    ("zh", &["zh", "chi", "zho", "chinese"]),
+    ("th", &["th", "tha", "thai"]),
+    ("vi", &["vi", "vie", "vietnamese"]),
+    ("id", &["ind", "indonesian"]),
+];
+
+pub fn is_subtitle_file(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    SUBTITLE_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
+}
+
+/// Language code named by one of `tokens`, preferring the last (release tags such as
+/// `.en.srt` trail the title, which may itself contain a language word).
+pub fn detect_language<'t>(
+    tokens: impl DoubleEndedIterator<Item = &'t str>,
+) -> Option<&'static str> {
+    tokens.rev().find_map(|token| {
+        let token = token.to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|(_, names)| names.contains(&token.as_str()))
+            .map(|(code, _)| *code)
+    })
+}
+
+fn stem(filename: &str) -> &str {
+    filename.rfind('.').map_or(filename, |i| &filename[..i])
+}
+
+/// VFS name for subtitle `subtitle_path` if it belongs to the video at `video_path`
+/// (exposed as `video_name`): its name starts with the video's, it sits in a folder named
+/// after the video (`Subs/<video>/2_English.srt`), or the video is the torrent's only one.
+pub fn subtitle_name(
+    video_path: &str,
+    video_name: &str,
+    subtitle_path: &str,
+    only_video: bool,
+) -> Option<String> {
+    let video_file = video_path.rsplit('/').next().unwrap_or(video_path);
+    let video_stem = stem(video_file);
+    let mut components: Vec<&str> = subtitle_path.split('/').collect();
+    let subtitle_file = components.pop()?;
+    let subtitle_stem = stem(subtitle_file);
+    let ext = subtitle_file[subtitle_stem.len()..].to_lowercase();
+
+    let prefixed = subtitle_stem
+        .get(..video_stem.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(video_stem))
+        && !subtitle_stem[video_stem.len()..].starts_with(char::is_alphanumeric);
+    let tags = if prefixed {
+        &subtitle_stem[video_stem.len()..]
+    } else if only_video
+        || components
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(video_stem))
+    {
+        subtitle_stem
+    } else {
+        return None;
+    };
+    let tokens: Vec<&str> = tags
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let has = |names: &[&str]| {
+        tokens
+            .iter()
+            .any(|t| names.iter().any(|n| t.eq_ignore_ascii_case(n)))
+    };
+
+    let mut name = stem(video_name).to_string();
+    if let Some(language) = detect_language(tokens.iter().copied()) {
+        name.push('.');
+        name.push_str(language);
+    }
+    if has(&["forced"]) {
+        name.push_str(".forced");
+    }
+    if has(&["sdh", "cc"]) {
+        name.push_str(".sdh");
+    }
+    Some(name + &ext)
+}
+
+/// Subtitles fetched from an OpenSubtitles-compatible API for videos that ship without
+/// any, stored as `<torrent hash>-<file id>.<lang>.srt` in a cache directory.
+#[derive(Debug)]
+pub struct SubtitleCache {
+    dir: PathBuf,
+    languages: Vec<String>,
+    client: OpenSubtitlesClient,
+    /// Fetched languages per video key, with each file's size.
+    index: RwLock<HashMap<String, BTreeMap<String, u64>>>,
+    attempted: Mutex<HashMap<String, Instant>>,
+}
+
+/// A video to fetch subtitles for.
+struct Target {
+    key: String,
+    locator: FileLocator,
+    file_size: u64,
+    query: SubtitleQuery,
+}
+
+/// Cache key of a torrent's video file.
+fn video_key(torrent_hash: &str, file_id: u32) -> String {
+    format!("{}-{}", torrent_hash.to_lowercase(), file_id)
+}
+
+impl SubtitleCache {
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        languages: Vec<String>,
+        client: OpenSubtitlesClient,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            languages,
+            client,
+            index: RwLock::new(HashMap::new()),
+            attempted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Enabled by `OPENSUBTITLES_API_KEY`; fetches `SUBTITLE_LANGUAGES` (default `en`)
+    /// into `SUBTITLE_CACHE_DIR` (default `subtitles`) and indexes what is already there.
+    pub async fn from_env() -> Option<Self> {
+        let client = OpenSubtitlesClient::from_env()?;
+        let languages: Vec<String> = std::env::var("SUBTITLE_LANGUAGES")
+            .unwrap_or_else(|_| "en".to_string())
+            .split(',')
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let dir = std::env::var("SUBTITLE_CACHE_DIR")
+            .ok()
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or_else(|| "subtitles".to_string());
+        let cache = Self::new(dir, languages, client);
+        cache.load().await;
+        Some(cache)
+    }
+
+    /// Index the subtitles already in the cache directory.
+    pub async fn load(&self) {
+        let dir = self.dir.clone();
+        let index = tokio::task::spawn_blocking(move || {
+            let mut index: HashMap<String, BTreeMap<String, u64>> = HashMap::new();
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                return index;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let Some((key, language)) = name
+                    .strip_suffix(".srt")
+                    .and_then(|stem| stem.rsplit_once('.'))
+                else {
+                    continue;
+                };
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                index
+                    .entry(key.to_string())
+                    .or_default()
+                    .insert(language.to_string(), size);
+            }
+            index
+        })
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to index cached subtitles: {:?}", e);
+            HashMap::new()
+        });
+        *self.index.write().unwrap_or_else(|e| e.into_inner()) = index;
+    }
+
+    /// Fetched subtitles of a torrent's video file: language, cache path and size.
+    pub fn fetched(&self, torrent_hash: &str, file_id: u32) -> Vec<(String, PathBuf, u64)> {
+        let key = video_key(torrent_hash, file_id);
+        let index = self.index.read().unwrap_or_else(|e| e.into_inner());
+        index
+            .get(&key)
+            .map(|languages| {
+                languages
+                    .iter()
+                    .map(|(language, &size)| {
+                        let path = self.dir.join(format!("{}.{}.srt", key, language));
+                        (language.clone(), path, size)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Identified videos without bundled subtitles that still miss a configured language.
+    fn targets(&self, library: &HashMap<String, (TorrentInfo, MediaMetadata)>) -> Vec<Target> {
+        let index = self.index.read().unwrap_or_else(|e| e.into_inner());
+        let mut targets = BTreeMap::new();
+        for (info, metadata) in library.values() {
+            let Some(tmdb_id) = metadata.tmdb_id() else {
+                continue;
+            };
+            let videos = streamable_files(info);
+            let subtitles = subtitle_files(info);
+            let only_video = videos.len() == 1;
+            for (file, link) in &videos {
+                let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+                let bundled = subtitles.iter().any(|(subtitle, _)| {
+                    subtitle_name(&file.path, filename, &subtitle.path, only_video).is_some()
+                });
+                let key = video_key(&info.hash, file.id);
+                let complete = index
+                    .get(&key)
+                    .is_some_and(|fetched| self.languages.iter().all(|l| fetched.contains_key(l)));
+                if bundled || complete {
+                    continue;
+                }
+                let episode = match metadata.media_type {
+                    MediaType::Movie => None,
+                    MediaType::Show => match parse_episode_range(filename)
+                        .map(|(season, first, _)| (season, first))
+                        .or_else(|| parse_episode(filename))
+                    {
+                        Some(episode) => Some(episode),
+                        None => continue,
+                    },
+                };
+                targets.insert(
+                    key.clone(),
+                    Target {
+                        key,
+                        locator: FileLocator {
+                            hash: info.hash.clone(),
+                            torrent_id: info.id.clone(),
+                            file_id: file.id,
+                            file_path: file.path.clone(),
+                            link: link.clone(),
+                        },
+                        file_size: file.bytes,
+                        query: SubtitleQuery {
+                            tmdb_id,
+                            episode,
+                            moviehash: None,
+                            languages: self.languages.clone(),
+                        },
+                    },
+                );
+            }
+        }
+        targets.into_values().collect()
+    }
+
+    /// Fetch subtitles for videos that have none, hashing each through `media` so the
+    /// API can prefer subtitles made for that exact file. Returns whether any were added,
+    /// i.e. whether the VFS should be rebuilt.
+    pub async fn refresh(
+        &self,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+        media: &DebridFileSystem,
+    ) -> bool {
+        let targets = self.targets(library);
+        let due: Vec<Target> = {
+            let mut attempted = self.attempted.lock().await;
+            let due: Vec<_> = targets
+                .into_iter()
+                .filter(|t| {
+                    attempted
+                        .get(&t.key)
+                        .is_none_or(|at| at.elapsed() >= RETRY_AFTER)
+                })
+                .take(MAX_VIDEOS_PER_REFRESH)
+                .collect();
+            for target in &due {
+                attempted.insert(target.key.clone(), Instant::now());
+            }
+            due
+        };
+
+        let mut changed = false;
+        for mut target in due {
+            target.query.moviehash = self.hash(media, &target).await;
+            let matches = match self.client.search(&target.query).await {
+                Ok(matches) => matches,
+                Err(e) => {
+                    warn!("Subtitle search for {} failed: {}", target.key, e);
+                    continue;
+                }
+            };
+            let missing: Vec<String> = {
+                let index = self.index.read().unwrap_or_else(|e| e.into_inner());
+                let fetched = index.get(&target.key);
+                self.languages
+                    .iter()
+                    .filter(|l| fetched.is_none_or(|f| !f.contains_key(*l)))
+                    .cloned()
+                    .collect()
+            };
+            for language in missing {
+                // Prefer a subtitle made for this exact file, then the API's ranking.
+                let for_language = || matches.iter().filter(|m| m.language == language);
+                let Some(best) = for_language()
+                    .filter(|m| m.hash_match)
+                    .chain(for_language())
+                    .next()
+                else {
+                    continue;
+                };
+                let content = match self.client.download(best.file_id).await {
+                    Ok(content) => content,
+                    Err(e) => {
+                        // Usually the daily download quota: try again next refresh.
+                        warn!("Subtitle download for {} failed: {}", target.key, e);
+                        return changed;
+                    }
+                };
+                let name = format!("{}.{}.srt", target.key, language);
+                if let Err(e) = self.store(&name, content.to_vec()).await {
+                    error!("Failed to cache subtitle {}: {}", name, e);
+                    continue;
+                }
+                self.index
+                    .write()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .entry(target.key.clone())
+                    .or_default()
+                    .insert(language, content.len() as u64);
+                changed = true;
+            }
+        }
+        if changed {
+            info!("Subtitles: fetched new subtitles, rebuilding VFS");
+        }
+        changed
+    }
+
+    /// OpenSubtitles hash of the target's video, from ranged reads of both ends.
+    async fn hash(&self, media: &DebridFileSystem, target: &Target) -> Option<String> {
+        let chunk = HASH_CHUNK_SIZE as u64;
+        if target.file_size < chunk {
+            return None;
+        }
+        let read = |pos| {
+            media.read_range(
+                target.locator.clone(),
+                target.file_size,
+                pos,
+                HASH_CHUNK_SIZE,
+            )
+        };
+        let head = read(0).await.ok()?;
+        let tail = read(target.file_size - chunk).await.ok()?;
+        opensubtitles_hash(target.file_size, &head, &tail)
+    }
+
+    /// Write through a temporary file so a crash never leaves a truncated subtitle behind.
+    async fn store(&self, name: &str, content: Vec<u8>) -> std::io::Result<()> {
+        let dir = self.dir.clone();
+        let name = name.to_string();
+        tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&dir)?;
+            let tmp = dir.join(format!("{}.part", name));
+            std::fs::write(&tmp, &content)?;
+            std::fs::rename(&tmp, dir.join(name))
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A stand-in OpenSubtitles API: search, download link, then the file itself.
+    async fn spawn_opensubtitles() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let link = format!(r#"{{"link": "{}/file/7.srt", "remaining": 19}}"#, base);
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 8192];
+                let n = sock.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let body = if request.starts_with("GET /subtitles?") {
+                    r#"{"data": [
+                        {"attributes": {"language": "en", "files": [{"file_id": 6}]}},
+                        {"attributes": {"language": "en", "moviehash_match": true, "files": [{"file_id": 7}]}}
+                    ]}"#
+                    .to_string()
+                } else if request.starts_with("POST /download") && request.contains("7") {
+                    link.clone()
+                } else if request.starts_with("GET /file/7.srt") {
+                    "1\n00:00:01,000 --> 00:00:02,000\nHello\n".to_string()
+                } else {
+                    String::new()
+                };
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                );
+                let _ = sock.write_all(head.as_bytes()).await;
+                let _ = sock.write_all(body.as_bytes()).await;
+                let _ = sock.flush().await;
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn refresh_fetches_subtitles_for_videos_without_any() {
+        use crate::provider::{DebridProvider, MockProvider};
+        use crate::rd_client::TorrentFile;
+        use crate::vfs::{DebridVfs, VfsNode, VfsOptions};
+        use std::sync::Arc;
+
+        let base = spawn_opensubtitles().await;
+        let dir = std::env::temp_dir().join(format!("subtitle-test-{}", std::process::id()));
+        let client = OpenSubtitlesClient::new(base, "key".to_string()).unwrap();
+        let cache = Arc::new(SubtitleCache::new(&dir, vec!["en".to_string()], client));
+
+        let file = |id: u32, path: &str| TorrentFile {
+            id,
+            path: path.to_string(),
+            bytes: 1000,
+            selected: 1,
+        };
+        let bare = TorrentInfo {
+            id: "bare".to_string(),
+            hash: "ABC".to_string(),
+            files: vec![file(1, "/Heat.1995.mkv")],
+            ..Default::default()
+        };
+        let bundled = TorrentInfo {
+            id: "bundled".to_string(),
+            hash: "def".to_string(),
+            files: vec![file(1, "/Up.2009.mkv"), file(2, "/Up.2009.en.srt")],
+            ..Default::default()
+        };
+        let movie = |title: &str, id: &str| MediaMetadata {
+            title: title.to_string(),
+            year: None,
+            media_type: MediaType::Movie,
+            external_id: Some(id.to_string()),
+        };
+        let library = HashMap::from([
+            (
+                "bare".to_string(),
+                (bare.clone(), movie("Heat", "tmdb:949")),
+            ),
+            ("bundled".to_string(), (bundled, movie("Up", "tmdb:14160"))),
+        ]);
+
+        let provider: Arc<dyn DebridProvider> = Arc::new(MockProvider::default());
+        let media = DebridFileSystem::new(
+            provider.clone(),
+            Arc::new(tokio::sync::RwLock::new(DebridVfs::new())),
+            Arc::new(crate::repair::RepairManager::new(provider)),
+            reqwest::Client::new(),
+        );
+        assert_eq!(cache.targets(&library).len(), 1);
+        assert!(cache.refresh(&library, &media).await);
+        // Fetched, so nothing is left to search for even after a restart.
+        assert!(cache.targets(&library).is_empty());
+        assert!(!cache.refresh(&library, &media).await);
+
+        let options = VfsOptions {
+            subtitles: Some(cache),
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(vec![(bare, movie("Heat", "tmdb:949"))], Arc::new(options));
+        let VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let VfsNode::Directory { children: movies } = &children["Movies"] else {
+            panic!("Movies is a directory");
+        };
+        let VfsNode::Directory { children: folder } = &movies["Heat [tmdbid-949]"] else {
+            panic!("movie folder is a directory");
+        };
+        let VfsNode::CachedFile { path, file_size } = &folder["Heat.1995.en.srt"] else {
+            panic!("fetched subtitle is a cached file");
+        };
+        assert_eq!(*file_size, 38);
+        assert_eq!(path, &dir.join("abc-1.en.srt"));
+
+        let reloaded = SubtitleCache::new(
+            &dir,
+            vec!["en".to_string()],
+            OpenSubtitlesClient::new("http://unused".to_string(), "key".to_string()).unwrap(),
+        );
+        reloaded.load().await;
+        assert_eq!(reloaded.fetched("abc", 1).len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn subtitle_name_matches_and_tags_language() {
+        let video = "/Movie.2019.1080p/Movie.2019.1080p.mkv";
+        assert_eq!(
+            subtitle_name(
+                video,
+                "Movie (2019).mkv",
+                "/Movie.2019.1080p/Movie.2019.1080p.eng.forced.srt",
+                false
+            )
+            .as_deref(),
+            Some("Movie (2019).en.forced.srt")
+        );
+        // RARBG layout: language in a numbered file inside a folder named after the video
+        assert_eq!(
+            subtitle_name(
+                "/Show/Show.S01E01.mkv",
+                "Show.S01E01.mkv",
+                "/Show/Subs/Show.S01E01/3_English.SDH.srt",
+                false
+            )
+            .as_deref(),
+            Some("Show.S01E01.en.sdh.srt")
+        );
+        // Another episode's subtitle in a multi-video torrent
+        assert_eq!(
+            subtitle_name(
+                "/Show/Show.S01E01.mkv",
+                "Show.S01E01.mkv",
+                "/Show/Show.S01E02.srt",
+                false
+            ),
+            None
+        );
+        // The only video takes any subtitle; a bare name gets no language tag
+        assert_eq!(
+            subtitle_name(video, "Movie.2019.1080p.mkv", "/Subs/subs.ASS", true).as_deref(),
+            Some("Movie.2019.1080p.ass")
+        );
+    }
+
+    #[test]
+    fn detect_language_prefers_trailing_tokens() {
+        assert_eq!(
+            detect_language(["The", "French", "Dispatch", "de"].into_iter()),
+            Some("de")
+        );
+        assert_eq!(detect_language(["2", "Portuguese"].into_iter()), Some("pt"));
+        assert_eq!(detect_language(["Movie", "2019"].into_iter()), None);
+        assert!(is_subtitle_file("/a/B.SRT"));
+        assert!(!is_subtitle_file("/a/b.mkv"));
+    }
+}
@@ -1,11 +1,16 @@
-use crate::identification::identify_torrent;
+use crate::dav_fs::DebridFileSystem;
+use crate::error::AppError;
+use crate::identification::{
+    identify_with_sources, read_bundled_nfo, Identification, MatchStrategy,
+};
+use crate::metadata_source::MetadataSources;
 use crate::provider::DebridProvider;
 use crate::repair::RepairManager;
 use crate::tmdb_client::TmdbClient;
 use crate::vfs::{DebridVfs, MediaMetadata};
 use futures_util::StreamExt;
 use redb::{ReadableDatabase, ReadableTable, TableDefinition};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -13,26 +18,187 @@ use tracing::{error, info, warn};
 
 pub const MATCHES_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("matches");
 
+/// How each match in `MATCHES_TABLE` was reached (scores and strategy), keyed by torrent
+/// id. Torrents pinned by a manual override have no record.
+pub const IDENTIFICATIONS_TABLE: TableDefinition<&str, &[u8]> =
+    TableDefinition::new("identifications");
+
+/// Manual identification overrides, keyed by lowercase torrent hash. Keying by hash rather
+/// than torrent id means an override survives repair replacements and re-adds of the same
+/// torrent, both of which change the id but never the hash.
+pub const OVERRIDES_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("overrides");
+
+/// Torrents manually hidden from the VFS, keyed by lowercase torrent hash (for the same
+/// reason as `OVERRIDES_TABLE`). The value is the torrent's filename, kept for readability.
+pub const HIDDEN_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("hidden");
+
+/// Live view of the scan loop's library, shared with the admin API. The scan loop
+/// publishes its latest data here and drains the requests the admin API queues.
+#[derive(Debug, Default)]
+pub struct ScanState {
+    /// Torrent id → (info, metadata with overrides applied) as of the last VFS update.
+    /// Includes manually hidden torrents so they can still be inspected and unhidden.
+    library: RwLock<HashMap<String, (crate::rd_client::TorrentInfo, MediaMetadata)>>,
+    /// Torrent ids whose identification should be discarded and redone on the next scan.
+    reidentify: RwLock<HashSet<String>>,
+    /// Wakes the scan loop early so admin changes apply without waiting a full interval.
+    rescan: tokio::sync::Notify,
+    /// Progress of the current (or last) scan, mirrored from the progress log lines.
+    progress: RwLock<ScanProgress>,
+}
+
+/// Scan loop progress as reported by the admin API's status endpoint.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ScanProgress {
+    /// Whether a scan is running right now.
+    pub scanning: bool,
+    /// New torrents identified so far in the current scan.
+    pub identified: usize,
+    /// New torrents the current scan has to identify.
+    pub to_identify: usize,
+    /// Torrents in the library after the last VFS update.
+    pub library_size: usize,
+    /// Unix timestamp (seconds) of the last completed scan.
+    pub last_scan_completed: Option<i64>,
+    /// Error from the last scan, if it failed to list torrents.
+    pub last_error: Option<String>,
+}
+
+impl ScanState {
+    /// Snapshot of the library as of the last VFS update.
+    pub async fn library(&self) -> HashMap<String, (crate::rd_client::TorrentInfo, MediaMetadata)> {
+        self.library.read().await.clone()
+    }
+
+    /// Look up a single torrent from the last VFS update.
+    pub async fn entry(
+        &self,
+        torrent_id: &str,
+    ) -> Option<(crate::rd_client::TorrentInfo, MediaMetadata)> {
+        self.library.read().await.get(torrent_id).cloned()
+    }
+
+    /// Queue a torrent for re-identification and wake the scan loop.
+    pub async fn request_reidentify(&self, torrent_id: &str) {
+        self.reidentify.write().await.insert(torrent_id.to_string());
+        self.request_rescan();
+    }
+
+    /// Drains and returns the queued re-identification requests.
+    pub async fn take_reidentify(&self) -> HashSet<String> {
+        std::mem::take(&mut *self.reidentify.write().await)
+    }
+
+    /// Ask the scan loop to start its next scan now rather than after the interval.
+    pub fn request_rescan(&self) {
+        self.rescan.notify_one();
+    }
+
+    /// Progress of the current (or last) scan.
+    pub async fn progress(&self) -> ScanProgress {
+        self.progress.read().await.clone()
+    }
+
+    async fn begin_scan(&self) {
+        let mut progress = self.progress.write().await;
+        progress.scanning = true;
+        progress.identified = 0;
+        progress.to_identify = 0;
+    }
+
+    async fn record_progress(&self, identified: usize, to_identify: usize) {
+        let mut progress = self.progress.write().await;
+        progress.identified = identified;
+        progress.to_identify = to_identify;
+    }
+
+    async fn finish_scan(&self, error: Option<String>) {
+        let mut progress = self.progress.write().await;
+        progress.scanning = false;
+        progress.last_scan_completed = Some(chrono::Utc::now().timestamp());
+        progress.last_error = error;
+    }
+
+    pub(crate) async fn publish(&self, data: &[(crate::rd_client::TorrentInfo, MediaMetadata)]) {
+        let library = data
+            .iter()
+            .map(|(info, metadata)| (info.id.clone(), (info.clone(), metadata.clone())))
+            .collect::<HashMap<_, _>>();
+        self.progress.write().await.library_size = library.len();
+        *self.library.write().await = library;
+    }
+}
+
+/// Manual corrections loaded from the database at the start of each scan.
+#[derive(Debug, Default)]
+pub struct Corrections {
+    /// Lowercase hash → pinned metadata.
+    pub overrides: HashMap<String, MediaMetadata>,
+    /// Lowercase hashes of torrents hidden from the VFS.
+    pub hidden: HashSet<String>,
+}
+
+impl Corrections {
+    pub fn override_for(&self, hash: &str) -> Option<&MediaMetadata> {
+        self.overrides.get(&hash.to_lowercase())
+    }
+
+    pub fn is_hidden(&self, hash: &str) -> bool {
+        self.hidden.contains(&hash.to_lowercase())
+    }
+
+    /// Replace the metadata of every torrent whose hash has a manual override.
+    fn apply_overrides(&self, data: &mut [(crate::rd_client::TorrentInfo, MediaMetadata)]) {
+        if self.overrides.is_empty() {
+            return;
+        }
+        for (torrent_info, metadata) in data.iter_mut() {
+            if let Some(pinned) = self.override_for(&torrent_info.hash) {
+                *metadata = pinned.clone();
+            }
+        }
+    }
+}
+
 pub struct ScanConfig {
     pub rd_client: Arc<dyn DebridProvider>,
     pub tmdb_client: Arc<TmdbClient>,
+    /// Identification fallback chain and the source of each title's details.
+    pub metadata_sources: Arc<MetadataSources>,
     pub vfs: Arc<RwLock<DebridVfs>>,
     pub db: Arc<redb::Database>,
     pub repair_manager: Arc<RepairManager>,
     pub interval_secs: u64,
     pub jellyfin_client: Option<Arc<crate::jellyfin_client::JellyfinClient>>,
+    pub state: Arc<ScanState>,
+    pub watchlist: Option<Arc<crate::watchlist::WatchlistSync>>,
+    pub episodes: Option<Arc<crate::episodes::EpisodeSync>>,
 }
 
 pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch::Receiver<bool>) {
     let ScanConfig {
         rd_client,
         tmdb_client,
+        metadata_sources,
         vfs,
         db,
         repair_manager,
         interval_secs,
         jellyfin_client,
+        state,
+        watchlist,
+        episodes,
     } = config;
+    // Reads torrent files (bundled NFOs, subtitle hashes) the same way WebDAV reads do
+    let media = DebridFileSystem::new(
+        rd_client.clone(),
+        vfs.clone(),
+        repair_manager.clone(),
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default(),
+    );
     // Load persisted matches from DB on startup
     let db_clone = db.clone();
     let persisted: HashMap<String, (crate::rd_client::TorrentInfo, MediaMetadata)> =
@@ -75,7 +241,16 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
     // only captures genuinely new/changed content, not the entire library.
     if !seen_torrents.is_empty() {
         let persisted_data: Vec<_> = seen_torrents.values().cloned().collect();
-        update_vfs(&vfs, &persisted_data, &repair_manager, &None).await;
+        let corrections = load_corrections(&db).await;
+        update_vfs(
+            &vfs,
+            &persisted_data,
+            &repair_manager,
+            &corrections,
+            &state,
+            &None,
+        )
+        .await;
         info!(
             "Pre-populated VFS with {} persisted entries",
             persisted_data.len()
@@ -84,6 +259,8 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
 
     info!("Scan task: running initial scan immediately");
 
+    let mut watchlist_task = None;
+    let mut episodes_task = None;
     loop {
         if *shutdown.borrow() {
             info!("Scan task: shutdown requested, exiting");
@@ -98,8 +275,24 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
             );
         }
 
+        // Re-read corrections every scan so changes written to the database take effect
+        // without a restart.
+        let corrections = load_corrections(&db).await;
+
+        // Forget identifications the admin API asked to redo, both in memory and in redb,
+        // so those torrents fall through to `identify_torrent` below.
+        let reidentify = state.take_reidentify().await;
+        if !reidentify.is_empty() {
+            info!("Re-identifying {} torrent(s) on request", reidentify.len());
+            for id in &reidentify {
+                seen_torrents.remove(id);
+            }
+            remove_matches(&db, reidentify.into_iter().collect()).await;
+        }
+
         info!("Refreshing torrent list...");
-        match rd_client.get_torrents().await {
+        state.begin_scan().await;
+        let scan_error = match rd_client.get_torrents().await {
             Ok(torrents) => {
                 if torrents.is_empty() {
                     warn!("No torrents found in {} account.", rd_client.name());
@@ -153,6 +346,17 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
                                                             new_id.as_str(),
                                                             data_bytes.as_slice(),
                                                         )?;
+                                                        let mut records = write_txn
+                                                            .open_table(IDENTIFICATIONS_TABLE)?;
+                                                        let record = records
+                                                            .remove(old_id.as_str())?
+                                                            .map(|r| r.value().to_vec());
+                                                        if let Some(record) = record {
+                                                            records.insert(
+                                                                new_id.as_str(),
+                                                                record.as_slice(),
+                                                            )?;
+                                                        }
                                                     }
                                                     write_txn.commit()?;
                                                     Ok(())
@@ -211,30 +415,58 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
                 if !to_identify.is_empty() {
                     let new_total = to_identify.len();
                     info!("Identifying {} new torrents...", new_total);
+                    state.record_progress(0, new_total).await;
                     let mut stream = futures_util::stream::iter(to_identify)
                         .map(|torrent| {
                             let rd_client = rd_client.clone();
-                            let tmdb_client = tmdb_client.clone();
+                            let metadata_sources = metadata_sources.clone();
+                            let media = media.clone();
+                            let pinned = corrections.override_for(&torrent.hash).cloned();
                             async move {
                                 match rd_client.get_torrent_info(&torrent.id).await {
                                     Ok(info) => {
-                                        let metadata = identify_torrent(&info, &tmdb_client).await;
-                                        Ok::<
-                                            (String, crate::rd_client::TorrentInfo, MediaMetadata),
-                                            reqwest::Error,
-                                        >((
-                                            torrent.id, info, metadata,
+                                        // A manual override short-circuits TMDB entirely.
+                                        let (metadata, identification) = match pinned {
+                                            Some(metadata) => {
+                                                info!(
+                                                    "Using manual override for {}: {}",
+                                                    info.filename, metadata.title
+                                                );
+                                                (metadata, None)
+                                            }
+                                            None => {
+                                                let nfo = read_bundled_nfo(&info, &media).await;
+                                                // A source that could not be searched leaves
+                                                // the torrent out of this scan, unpersisted,
+                                                // so the next scan identifies it again.
+                                                let identification = identify_with_sources(
+                                                    &info,
+                                                    nfo.as_deref(),
+                                                    &metadata_sources,
+                                                )
+                                                .await?;
+                                                (
+                                                    identification.metadata.clone(),
+                                                    Some(identification),
+                                                )
+                                            }
+                                        };
+                                        Ok::<_, AppError>((
+                                            torrent.id,
+                                            info,
+                                            metadata,
+                                            identification,
                                         ))
                                     }
-                                    Err(e) => Err(e),
+                                    Err(e) => Err(e.into()),
                                 }
                             }
                         })
                         .buffer_unordered(1);
 
                     let mut processed_new = 0;
-                    // Batch pending DB writes: (id, serialized_bytes)
-                    let mut pending_db_writes: Vec<(String, Vec<u8>)> = Vec::new();
+                    // Batch pending DB writes
+                    let mut pending_db_writes: Vec<PendingMatch> = Vec::new();
 
                     while let Some(result) = tokio::select! {
                         result = stream.next() => result,
@@ -244,15 +476,21 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
                             if !pending_db_writes.is_empty() {
                                 flush_db_writes(&db, &mut pending_db_writes).await;
                             }
-                            update_vfs(&vfs, &current_data, &repair_manager, &jellyfin_client).await;
+                            update_vfs(&vfs, &current_data, &repair_manager, &corrections, &state, &jellyfin_client).await;
                             return;
                         }
                     } {
                         processed_new += 1;
                         match result {
-                            Ok((id, info, metadata)) => {
+                            Ok((id, info, metadata, identification)) => {
                                 if let Ok(data_bytes) = serde_json::to_vec(&(&info, &metadata)) {
-                                    pending_db_writes.push((id.clone(), data_bytes));
+                                    let record =
+                                        identification.and_then(|i| serde_json::to_vec(&i).ok());
+                                    pending_db_writes.push(PendingMatch {
+                                        id: id.clone(),
+                                        data_bytes,
+                                        record,
+                                    });
                                 }
                                 seen_torrents.insert(id, (info.clone(), metadata.clone()));
                                 current_data.push((info, metadata));
@@ -268,12 +506,28 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
                                 "Progress: {}/{} new torrents identified",
                                 processed_new, new_total
                             );
-                            update_vfs(&vfs, &current_data, &repair_manager, &jellyfin_client)
-                                .await;
+                            state.record_progress(processed_new, new_total).await;
+                            update_vfs(
+                                &vfs,
+                                &current_data,
+                                &repair_manager,
+                                &corrections,
+                                &state,
+                                &jellyfin_client,
+                            )
+                            .await;
                         }
                     }
                 } else {
-                    update_vfs(&vfs, &current_data, &repair_manager, &jellyfin_client).await;
+                    update_vfs(
+                        &vfs,
+                        &current_data,
+                        &repair_manager,
+                        &corrections,
+                        &state,
+                        &jellyfin_client,
+                    )
+                    .await;
                 }
 
                 let current_ids: std::collections::HashSet<&str> =
@@ -286,37 +540,99 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
                 // Remove stale entries from redb to prevent them from reloading on restart
                 if !stale_ids.is_empty() {
                     info!("Removing {} stale entries from database", stale_ids.len());
-                    let db_clone = db.clone();
-                    match tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
-                        let write_txn = db_clone.begin_write()?;
-                        {
-                            let mut table = write_txn.open_table(MATCHES_TABLE)?;
-                            for id in &stale_ids {
-                                table.remove(id.as_str())?;
-                            }
-                        }
-                        write_txn.commit()?;
-                        Ok(())
-                    })
-                    .await
-                    {
-                        Ok(Ok(())) => {}
-                        Ok(Err(e)) => {
-                            error!("Failed to remove stale entries from database: {}", e)
-                        }
-                        Err(e) => {
-                            error!("Failed to remove stale entries from database: {:?}", e)
-                        }
-                    }
+                    remove_matches(&db, stale_ids).await;
                 }
                 info!("VFS update complete.");
+                None
             }
-            Err(e) => error!("Failed to get torrents: {}", e),
+            Err(e) => {
+                error!("Failed to get torrents: {}", e);
+                Some(e.to_string())
+            }
+        };
+        state.finish_scan(scan_error).await;
+
+        // Newly cached episode details, anime seasons, title details and subtitles only
+        // change names and files beside the media, so rebuild from the library rather than
+        // rescanning the provider.
+        let options = vfs.read().await.options.clone();
+        if options.episodes.is_some()
+            || options.anime.is_some()
+            || options.details.is_some()
+            || options.subtitles.is_some()
+        {
+            let library = state.library().await;
+            let mut renamed = false;
+            if let Some(anime) = &options.anime {
+                renamed |= anime.refresh(&db, &tmdb_client, &library).await;
+            }
+            if let Some(catalog) = &options.episodes {
+                renamed |= catalog.refresh(&db, &tmdb_client, &library).await;
+            }
+            if let Some(details) = &options.details {
+                renamed |= details.refresh(&db, &metadata_sources, &library).await;
+            }
+            if let Some(subtitles) = &options.subtitles {
+                renamed |= subtitles.refresh(&library, &media).await;
+            }
+            if renamed {
+                let data: Vec<_> = library.into_values().collect();
+                update_vfs(
+                    &vfs,
+                    &data,
+                    &repair_manager,
+                    &corrections,
+                    &state,
+                    &jellyfin_client,
+                )
+                .await;
+            }
+        }
+
+        // Watchlist and episode grabs show up in the provider's torrent list, so rescan straight away
+        // to identify them instead of waiting a full interval.
+        if let Some(watchlist) = &watchlist {
+            let (watchlist, rd_client, db, state) = (
+                watchlist.clone(),
+                rd_client.clone(),
+                db.clone(),
+                state.clone(),
+            );
+            spawn_unless_running(&mut watchlist_task, async move {
+                let added = watchlist
+                    .run_if_due(&rd_client, &db, &state.library().await)
+                    .await;
+                if added > 0 {
+                    info!("Watchlist: added {} release(s), rescanning", added);
+                    state.request_rescan();
+                }
+            });
+        }
+        if let Some(episodes) = &episodes {
+            let (episodes, rd_client, db, tmdb_client, state) = (
+                episodes.clone(),
+                rd_client.clone(),
+                db.clone(),
+                tmdb_client.clone(),
+                state.clone(),
+            );
+            spawn_unless_running(&mut episodes_task, async move {
+                let added = episodes
+                    .run_if_due(&rd_client, &db, &tmdb_client, &state.library().await)
+                    .await;
+                if added > 0 {
+                    info!("Episodes: added {} release(s), rescanning", added);
+                    state.request_rescan();
+                }
+            });
         }
 
         info!("Scan task: sleeping {}s until next scan", interval_secs);
         tokio::select! {
             _ = tokio::time::sleep(Duration::from_secs(interval_secs)) => {}
+            _ = state.rescan.notified() => {
+                info!("Scan task: rescan requested");
+            }
             _ = shutdown.changed() => {
                 info!("Scan task: shutdown requested, exiting");
                 return;
@@ -325,6 +641,18 @@ pub async fn run_scan_loop(config: ScanConfig, mut shutdown: tokio::sync::watch:
     }
 }
 
+/// Start `job` unless the previous one started here is still running, so slow grab runs
+/// (each add waits for the provider) never delay a scan or pile up.
+fn spawn_unless_running<F>(task: &mut Option<tokio::task::JoinHandle<()>>, job: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    if task.as_ref().is_some_and(|t| !t.is_finished()) {
+        return;
+    }
+    *task = Some(tokio::spawn(job));
+}
+
 /// Deduplicate torrents by hash, keeping the first-seen `downloaded` entry per hash.
 /// The debrid API returns torrents newest-first, so "first seen" is the newest. Torrents
 /// that are not `downloaded`, or that have no hash, are always kept (they can't be matched
@@ -368,9 +696,17 @@ fn stale_ids<V>(
         .collect()
 }
 
+/// A new match waiting to be persisted: the serialized `(TorrentInfo, MediaMetadata)` and,
+/// unless a manual override pinned it, the serialized `Identification`.
+struct PendingMatch {
+    id: String,
+    data_bytes: Vec<u8>,
+    record: Option<Vec<u8>>,
+}
+
 /// Flush a batch of pending DB writes in a single transaction.
 /// Clears `pending_writes` on success or failure.
-async fn flush_db_writes(db: &Arc<redb::Database>, pending_writes: &mut Vec<(String, Vec<u8>)>) {
+async fn flush_db_writes(db: &Arc<redb::Database>, pending_writes: &mut Vec<PendingMatch>) {
     let writes = std::mem::take(pending_writes);
     let count = writes.len();
     let db_clone = db.clone();
@@ -378,8 +714,17 @@ async fn flush_db_writes(db: &Arc<redb::Database>, pending_writes: &mut Vec<(Str
         let write_txn = db_clone.begin_write()?;
         {
             let mut table = write_txn.open_table(MATCHES_TABLE)?;
-            for (id, data_bytes) in &writes {
-                table.insert(id.as_str(), data_bytes.as_slice())?;
+            let mut records = write_txn.open_table(IDENTIFICATIONS_TABLE)?;
+            for write in &writes {
+                table.insert(write.id.as_str(), write.data_bytes.as_slice())?;
+                match &write.record {
+                    Some(record) => {
+                        records.insert(write.id.as_str(), record.as_slice())?;
+                    }
+                    None => {
+                        records.remove(write.id.as_str())?;
+                    }
+                }
             }
         }
         write_txn.commit()?;
@@ -403,20 +748,217 @@ async fn flush_db_writes(db: &Arc<redb::Database>, pending_writes: &mut Vec<(Str
     }
 }
 
+/// Remove identifications (and their records) from the persisted cache.
+async fn remove_matches(db: &Arc<redb::Database>, ids: Vec<String>) {
+    let db_clone = db.clone();
+    match tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db_clone.begin_write()?;
+        {
+            let mut table = write_txn.open_table(MATCHES_TABLE)?;
+            let mut records = write_txn.open_table(IDENTIFICATIONS_TABLE)?;
+            for id in &ids {
+                table.remove(id.as_str())?;
+                records.remove(id.as_str())?;
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            error!("Failed to remove entries from database: {}", e)
+        }
+        Err(e) => {
+            error!("Failed to remove entries from database: {:?}", e)
+        }
+    }
+}
+
+/// Load the identification records of all persisted matches, by torrent id. Undecodable
+/// entries are skipped; a missing table (fresh database) yields an empty map.
+pub async fn load_identifications(db: &Arc<redb::Database>) -> HashMap<String, Identification> {
+    let db_clone = db.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut records = HashMap::new();
+        if let Ok(read_txn) = db_clone.begin_read() {
+            if let Ok(table) = read_txn.open_table(IDENTIFICATIONS_TABLE) {
+                if let Ok(iter) = table.iter() {
+                    for (key, value) in iter.flatten() {
+                        if let Ok(record) = serde_json::from_slice(value.value()) {
+                            records.insert(key.value().to_string(), record);
+                        }
+                    }
+                }
+            }
+        }
+        records
+    })
+    .await
+    .unwrap_or_else(|e| {
+        error!("Failed to load identification records: {:?}", e);
+        HashMap::new()
+    })
+}
+
+/// Give every persisted match TMDB couldn't identify (no `external_id`) and that has no
+/// identification record an `Unidentified` one, so matches stored before records existed
+/// reach the review queue. Returns how many records were added.
+pub fn backfill_identifications(write_txn: &redb::WriteTransaction) -> Result<usize, redb::Error> {
+    let matches = write_txn.open_table(MATCHES_TABLE)?;
+    let mut records = write_txn.open_table(IDENTIFICATIONS_TABLE)?;
+    let mut missing = Vec::new();
+    for entry in matches.iter()? {
+        let (key, value) = entry?;
+        let Ok((info, metadata)) =
+            serde_json::from_slice::<(crate::rd_client::TorrentInfo, MediaMetadata)>(value.value())
+        else {
+            continue;
+        };
+        if metadata.external_id.is_none() && records.get(key.value())?.is_none() {
+            let record = Identification {
+                metadata,
+                query: info.filename,
+                strategy: MatchStrategy::Unidentified,
+                score: None,
+                runner_up: None,
+            };
+            missing.push((key.value().to_string(), record));
+        }
+    }
+    for (id, record) in &missing {
+        let bytes = serde_json::to_vec(record).map_err(|e| redb::Error::Io(e.into()))?;
+        records.insert(id.as_str(), bytes.as_slice())?;
+    }
+    Ok(missing.len())
+}
+
+/// Load all manual corrections (overrides and hidden torrents). Unreadable or undecodable
+/// entries are skipped; missing tables (fresh database) yield empty collections.
+pub async fn load_corrections(db: &Arc<redb::Database>) -> Corrections {
+    let db_clone = db.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut corrections = Corrections::default();
+        if let Ok(read_txn) = db_clone.begin_read() {
+            if let Ok(table) = read_txn.open_table(OVERRIDES_TABLE) {
+                if let Ok(iter) = table.iter() {
+                    for (key, value) in iter.flatten() {
+                        if let Ok(metadata) = serde_json::from_slice::<MediaMetadata>(value.value())
+                        {
+                            corrections
+                                .overrides
+                                .insert(key.value().to_string(), metadata);
+                        }
+                    }
+                }
+            }
+            if let Ok(table) = read_txn.open_table(HIDDEN_TABLE) {
+                if let Ok(iter) = table.iter() {
+                    for (key, _) in iter.flatten() {
+                        corrections.hidden.insert(key.value().to_string());
+                    }
+                }
+            }
+        }
+        corrections
+    })
+    .await
+    .unwrap_or_else(|e| {
+        error!("Failed to load manual corrections: {:?}", e);
+        Corrections::default()
+    })
+}
+
+/// Pin `metadata` as the identification for every torrent with `hash`, or clear the
+/// override when `metadata` is `None`. Takes effect on the next scan.
+pub async fn set_override(
+    db: &Arc<redb::Database>,
+    hash: &str,
+    metadata: Option<&MediaMetadata>,
+) -> Result<(), redb::Error> {
+    let key = hash.to_lowercase();
+    let data_bytes = match metadata {
+        Some(m) => Some(serde_json::to_vec(m).map_err(|e| {
+            redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })?),
+        None => None,
+    };
+    let db_clone = db.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db_clone.begin_write()?;
+        {
+            let mut table = write_txn.open_table(OVERRIDES_TABLE)?;
+            match &data_bytes {
+                Some(bytes) => {
+                    table.insert(key.as_str(), bytes.as_slice())?;
+                }
+                None => {
+                    table.remove(key.as_str())?;
+                }
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+/// Hide every torrent with `hash` from the VFS (`filename` is stored for readability), or
+/// unhide it when `filename` is `None`. Takes effect on the next scan.
+pub async fn set_hidden(
+    db: &Arc<redb::Database>,
+    hash: &str,
+    filename: Option<&str>,
+) -> Result<(), redb::Error> {
+    let key = hash.to_lowercase();
+    let filename = filename.map(str::to_string);
+    let db_clone = db.clone();
+    tokio::task::spawn_blocking(move || -> Result<(), redb::Error> {
+        let write_txn = db_clone.begin_write()?;
+        {
+            let mut table = write_txn.open_table(HIDDEN_TABLE)?;
+            match &filename {
+                Some(name) => {
+                    table.insert(key.as_str(), name.as_bytes())?;
+                }
+                None => {
+                    table.remove(key.as_str())?;
+                }
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
 async fn update_vfs(
     vfs: &Arc<RwLock<DebridVfs>>,
     current_data: &[(crate::rd_client::TorrentInfo, MediaMetadata)],
     repair_manager: &Arc<RepairManager>,
+    corrections: &Corrections,
+    state: &ScanState,
     jellyfin_client: &Option<Arc<crate::jellyfin_client::JellyfinClient>>,
 ) {
+    // Overrides win over both fresh and persisted identifications, so a correction applies
+    // to torrents identified before it was written.
+    let mut resolved = current_data.to_vec();
+    corrections.apply_overrides(&mut resolved);
+    state.publish(&resolved).await;
+
     let hidden_ids = repair_manager.hidden_torrent_ids().await;
-    let filtered: Vec<_> = current_data
-        .iter()
-        .filter(|(torrent_info, _)| !hidden_ids.contains(&torrent_info.id))
-        .map(|(torrent_info, metadata)| (torrent_info.clone(), metadata.clone()))
+    let filtered: Vec<_> = resolved
+        .into_iter()
+        .filter(|(torrent_info, _)| {
+            !hidden_ids.contains(&torrent_info.id) && !corrections.is_hidden(&torrent_info.hash)
+        })
         .collect();
     // Build VFS without holding the lock to avoid blocking WebDAV reads during scans
-    let new_vfs = DebridVfs::build(filtered);
+    let options = vfs.read().await.options.clone();
+    let new_vfs = DebridVfs::build_with(filtered, options);
     // Diff old vs new, then swap
     let mut vfs_lock = vfs.write().await;
     let changes = crate::vfs::diff_trees(&vfs_lock.root, &new_vfs.root, "");
@@ -448,6 +990,32 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn spawn_unless_running_skips_while_the_last_job_runs() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut task = None;
+        let count = runs.clone();
+        spawn_unless_running(&mut task, async move {
+            count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let _ = rx.await;
+        });
+        let count = runs.clone();
+        spawn_unless_running(&mut task, async move {
+            count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        tx.send(()).unwrap();
+        task.take().unwrap().await.unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let count = runs.clone();
+        spawn_unless_running(&mut task, async move {
+            count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        task.take().unwrap().await.unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
     #[test]
     fn dedup_keeps_first_downloaded_per_hash_and_flags_rest() {
         // The API returns newest-first, so the first downloaded entry per hash is kept and
@@ -490,6 +1058,172 @@ mod tests {
         assert_eq!(stale, vec!["gone".to_string()]);
     }
 
+    fn in_memory_db() -> Arc<redb::Database> {
+        Arc::new(
+            redb::Database::builder()
+                .create_with_backend(redb::backends::InMemoryBackend::new())
+                .unwrap(),
+        )
+    }
+
+    fn metadata(title: &str, external_id: Option<&str>) -> MediaMetadata {
+        MediaMetadata {
+            title: title.to_string(),
+            year: Some("2010".to_string()),
+            media_type: crate::vfs::MediaType::Movie,
+            external_id: external_id.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn overrides_round_trip_and_clear() {
+        let db = in_memory_db();
+        assert!(load_corrections(&db).await.overrides.is_empty());
+
+        let pinned = metadata("Inception", Some("tmdb:27205"));
+        set_override(&db, "ABCDEF", Some(&pinned)).await.unwrap();
+        let corrections = load_corrections(&db).await;
+        // Keys are normalised to lowercase so provider hash casing doesn't matter.
+        assert_eq!(corrections.override_for("AbCdEf"), Some(&pinned));
+
+        set_override(&db, "abcdef", None).await.unwrap();
+        assert!(load_corrections(&db).await.overrides.is_empty());
+    }
+
+    #[tokio::test]
+    async fn identification_records_follow_their_matches() {
+        let db = in_memory_db();
+        let record = Identification {
+            metadata: metadata("Inception", Some("tmdb:27205")),
+            query: "Inception.2010.mkv".to_string(),
+            strategy: crate::identification::MatchStrategy::Direct,
+            score: Some(1520.0),
+            runner_up: Some(410.0),
+        };
+        let data_bytes =
+            serde_json::to_vec(&(crate::rd_client::TorrentInfo::default(), &record.metadata))
+                .unwrap();
+        let mut pending = vec![
+            PendingMatch {
+                id: "t1".to_string(),
+                data_bytes: data_bytes.clone(),
+                record: Some(serde_json::to_vec(&record).unwrap()),
+            },
+            // Pinned by an override: matched, but without a record
+            PendingMatch {
+                id: "t2".to_string(),
+                data_bytes,
+                record: None,
+            },
+        ];
+        flush_db_writes(&db, &mut pending).await;
+        assert!(pending.is_empty());
+        let records = load_identifications(&db).await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records.get("t1"), Some(&record));
+
+        remove_matches(&db, vec!["t1".to_string()]).await;
+        assert!(load_identifications(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn backfill_records_legacy_unidentified_matches_once() {
+        let db = in_memory_db();
+        let info = crate::rd_client::TorrentInfo {
+            filename: "mystery.2019.mkv".to_string(),
+            ..Default::default()
+        };
+        {
+            let write_txn = db.begin_write().unwrap();
+            {
+                let mut table = write_txn.open_table(MATCHES_TABLE).unwrap();
+                for (id, external_id) in [("t1", None), ("t2", Some("tmdb:27205"))] {
+                    let bytes =
+                        serde_json::to_vec(&(&info, metadata("Mystery", external_id))).unwrap();
+                    table.insert(id, bytes.as_slice()).unwrap();
+                }
+            }
+            assert_eq!(backfill_identifications(&write_txn).unwrap(), 1);
+            assert_eq!(backfill_identifications(&write_txn).unwrap(), 0);
+            write_txn.commit().unwrap();
+        }
+        let records = load_identifications(&db).await;
+        assert_eq!(records.len(), 1);
+        let record = &records["t1"];
+        assert_eq!(record.strategy, MatchStrategy::Unidentified);
+        assert_eq!(record.query, "mystery.2019.mkv");
+        assert!(record.needs_review());
+    }
+
+    #[tokio::test]
+    async fn hidden_round_trip_and_clear() {
+        let db = in_memory_db();
+        set_hidden(&db, "ABC", Some("Junk.mkv")).await.unwrap();
+        assert!(load_corrections(&db).await.is_hidden("abc"));
+        set_hidden(&db, "abc", None).await.unwrap();
+        assert!(!load_corrections(&db).await.is_hidden("abc"));
+    }
+
+    #[tokio::test]
+    async fn scan_state_drains_reidentify_requests() {
+        let state = ScanState::default();
+        state.request_reidentify("t1").await;
+        state.request_reidentify("t1").await;
+        state.request_reidentify("t2").await;
+        let mut ids: Vec<_> = state.take_reidentify().await.into_iter().collect();
+        ids.sort();
+        assert_eq!(ids, vec!["t1".to_string(), "t2".to_string()]);
+        assert!(state.take_reidentify().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn scan_progress_tracks_scan_lifecycle() {
+        let state = ScanState::default();
+        state.begin_scan().await;
+        state.record_progress(3, 10).await;
+        let progress = state.progress().await;
+        assert!(progress.scanning);
+        assert_eq!((progress.identified, progress.to_identify), (3, 10));
+        assert!(progress.last_scan_completed.is_none());
+
+        state.finish_scan(Some("boom".to_string())).await;
+        let progress = state.progress().await;
+        assert!(!progress.scanning);
+        assert!(progress.last_scan_completed.is_some());
+        assert_eq!(progress.last_error.as_deref(), Some("boom"));
+
+        // A new scan resets the per-scan counters but keeps the last completion time.
+        state.begin_scan().await;
+        let progress = state.progress().await;
+        assert_eq!((progress.identified, progress.to_identify), (0, 0));
+        assert!(progress.last_scan_completed.is_some());
+    }
+
+    #[test]
+    fn apply_overrides_replaces_metadata_by_hash_regardless_of_id() {
+        // Two torrents with different ids but the same hash (e.g. a repair replacement)
+        // both pick up the override; an unrelated hash is left alone.
+        let info = |id: &str, hash: &str| crate::rd_client::TorrentInfo {
+            id: id.to_string(),
+            hash: hash.to_string(),
+            ..Default::default()
+        };
+        let mut data = vec![
+            (info("old", "H1"), metadata("Wrong", Some("tmdb:1"))),
+            (info("new", "h1"), metadata("Wrong", Some("tmdb:1"))),
+            (info("other", "H2"), metadata("Other", Some("tmdb:2"))),
+        ];
+        let mut corrections = Corrections::default();
+        corrections
+            .overrides
+            .insert("h1".to_string(), metadata("Right", Some("tmdb:99")));
+
+        corrections.apply_overrides(&mut data);
+        assert_eq!(data[0].1.title, "Right");
+        assert_eq!(data[1].1.external_id.as_deref(), Some("tmdb:99"));
+        assert_eq!(data[2].1.title, "Other");
+    }
+
     /// Compile-time check: run_scan_loop has the expected signature.
     #[allow(dead_code)]
     async fn _assert_run_scan_loop_signature(
@@ -502,12 +1236,16 @@ mod tests {
     ) {
         let config = ScanConfig {
             rd_client,
+            metadata_sources: Arc::new(MetadataSources::from_env(tmdb_client.clone())),
             tmdb_client,
             vfs,
             db,
             repair_manager,
             interval_secs: 60,
             jellyfin_client: None,
+            state: Arc::new(ScanState::default()),
+            watchlist: None,
+            episodes: None,
         };
         run_scan_loop(config, shutdown).await;
     }
@@ -534,11 +1272,15 @@ mod provider_abstraction_tests {
         let _config = ScanConfig {
             rd_client: provider.clone(),
             tmdb_client: Arc::new(TmdbClient::new("k".to_string()).unwrap()),
+            metadata_sources: Arc::new(MetadataSources::new(Vec::new(), false)),
             vfs: Arc::new(RwLock::new(DebridVfs::new())),
             db,
             repair_manager: Arc::new(RepairManager::new(provider)),
             interval_secs: 60,
             jellyfin_client: None,
+            state: Arc::new(ScanState::default()),
+            watchlist: None,
+            episodes: None,
         };
     }
 }
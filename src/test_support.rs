@@ -0,0 +1,65 @@
+//! Local HTTP servers standing in for the metadata, indexer and subtitle APIs in tests.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+
+/// Answer a single request with `body` as `content_type`; yields the raw request.
+pub(crate) async fn serve_once(
+    content_type: &'static str,
+    body: &'static str,
+) -> (String, JoinHandle<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let (mut sock, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 2048];
+        let n = sock.read(&mut buf).await.unwrap();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            body.len()
+        );
+        let _ = sock.write_all(head.as_bytes()).await;
+        let _ = sock.write_all(body.as_bytes()).await;
+        let _ = sock.flush().await;
+        String::from_utf8_lossy(&buf[..n]).to_string()
+    });
+    (format!("http://{}", addr), handle)
+}
+
+/// Answer a single request with `body` as JSON; yields the raw request.
+pub(crate) async fn spawn_json(body: &'static str) -> (String, JoinHandle<String>) {
+    serve_once("application/json", body).await
+}
+
+/// Answer every request with the JSON body of the first route its request line contains
+/// (`{}` for none); yields each request line.
+pub(crate) async fn spawn_routes(
+    routes: Vec<(&'static str, &'static str)>,
+) -> (String, UnboundedReceiver<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            let mut buf = vec![0u8; 8192];
+            let n = sock.read(&mut buf).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            let line = request.lines().next().unwrap_or("").to_string();
+            let body = routes
+                .iter()
+                .find(|(path, _)| line.contains(path))
+                .map_or("{}", |(_, body)| body);
+            let _ = tx.send(line);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            let _ = sock.write_all(head.as_bytes()).await;
+            let _ = sock.write_all(body.as_bytes()).await;
+            let _ = sock.flush().await;
+        }
+    });
+    (format!("http://{}", addr), rx)
+}
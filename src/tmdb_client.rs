@@ -1,15 +1,20 @@
 use crate::error::AppError;
+use crate::vfs::MediaType;
 use rand::Rng;
 use reqwest::{Client, RequestBuilder};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::Instant;
 use tracing::{error, warn};
 
 const MAX_RETRY_AFTER_SECS: u64 = 300; // Cap Retry-After to 5 minutes
+const DEFAULT_BASE_URL: &str = "https://api.themoviedb.org/3";
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TmdbSearchResult {
     pub id: u32,
     #[serde(alias = "name")]
@@ -22,6 +27,9 @@ pub struct TmdbSearchResult {
     pub popularity: f64,
     pub vote_average: Option<f64>,
     pub vote_count: Option<u32>,
+    /// Alternative and translated titles, when looked up with `alternative_titles`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternative_titles: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,19 +38,375 @@ struct TmdbResponse {
     pub results: Vec<TmdbSearchResult>,
 }
 
+/// `GET /movie/{id}` or `/tv/{id}` with `alternative_titles` and `translations` appended.
+/// Movies list alternative titles under `titles` and translated titles as `title`; shows
+/// use `results` and `name`.
+#[derive(Debug, Deserialize)]
+struct TmdbTitlesResponse {
+    alternative_titles: Option<TmdbAlternativeTitles>,
+    translations: Option<TmdbTranslations>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbAlternativeTitles {
+    #[serde(default, alias = "results")]
+    titles: Vec<TmdbAlternativeTitle>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbAlternativeTitle {
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbTranslations {
+    #[serde(default)]
+    translations: Vec<TmdbTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbTranslation {
+    data: Option<TmdbTranslationData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbTranslationData {
+    #[serde(default, alias = "name")]
+    title: String,
+}
+
+/// `GET /find/{external_id}`: the movies and shows an IMDb or TVDB id belongs to.
+#[derive(Debug, Deserialize, Default)]
+pub struct TmdbFindResponse {
+    #[serde(default)]
+    pub movie_results: Vec<TmdbSearchResult>,
+    #[serde(default)]
+    pub tv_results: Vec<TmdbSearchResult>,
+}
+
+/// `GET /tv/{id}`: the parts of a show's details needed to walk its seasons.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbTvDetails {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub seasons: Vec<TmdbSeasonSummary>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbSeasonSummary {
+    pub season_number: u32,
+    #[serde(default)]
+    pub episode_count: u32,
+    pub air_date: Option<String>,
+}
+
+/// `GET /tv/{id}/season/{n}`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbSeason {
+    pub season_number: u32,
+    #[serde(default)]
+    pub episodes: Vec<TmdbEpisode>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbEpisode {
+    pub id: Option<u64>,
+    pub season_number: u32,
+    pub episode_number: u32,
+    #[serde(default)]
+    pub name: String,
+    /// `YYYY-MM-DD`; absent for episodes without a scheduled date.
+    pub air_date: Option<String>,
+}
+
+/// `GET /tv/{id}/episode_groups`.
+#[derive(Debug, Deserialize)]
+struct TmdbEpisodeGroupsResponse {
+    #[serde(default)]
+    results: Vec<TmdbEpisodeGroupSummary>,
+}
+
+/// One alternative episode ordering of a show. `kind` is TMDB's group type: 1 original
+/// air date, 2 absolute, 3 DVD, 4 digital, 5 story arc, 6 production, 7 TV.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbEpisodeGroupSummary {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: u32,
+}
+
+/// `GET /tv/episode_group/{id}`: the ordering's groups (seasons, arcs), each listing the
+/// show's episodes by their regular season and episode numbers.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbEpisodeGroup {
+    #[serde(default)]
+    pub groups: Vec<TmdbEpisodeGroupPart>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbEpisodeGroupPart {
+    #[serde(default)]
+    pub order: u32,
+    #[serde(default)]
+    pub episodes: Vec<TmdbGroupEpisode>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TmdbGroupEpisode {
+    pub season_number: u32,
+    pub episode_number: u32,
+    #[serde(default)]
+    pub order: u32,
+}
+
+/// `GET /movie/{id}` or `/tv/{id}` with `credits` and certifications appended. Movie and
+/// TV fields differ (runtime, studios, certifications); both shapes deserialize here.
+#[derive(Debug, Deserialize)]
+struct TmdbDetailsResponse {
+    #[serde(default)]
+    overview: String,
+    #[serde(default)]
+    tagline: String,
+    #[serde(default)]
+    genres: Vec<TmdbNamed>,
+    #[serde(default)]
+    production_companies: Vec<TmdbNamed>,
+    #[serde(default)]
+    networks: Vec<TmdbNamed>,
+    runtime: Option<u32>,
+    #[serde(default)]
+    episode_run_time: Vec<u32>,
+    release_dates: Option<TmdbResults<TmdbReleaseDates>>,
+    content_ratings: Option<TmdbResults<TmdbContentRating>>,
+    credits: Option<TmdbCredits>,
+    poster_path: Option<String>,
+    backdrop_path: Option<String>,
+    vote_average: Option<f64>,
+    vote_count: Option<u32>,
+    #[serde(default)]
+    seasons: Vec<TmdbSeasonPoster>,
+    belongs_to_collection: Option<TmdbCollection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbCollection {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbSeasonPoster {
+    season_number: u32,
+    poster_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbNamed {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbResults<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbReleaseDates {
+    iso_3166_1: String,
+    #[serde(default)]
+    release_dates: Vec<TmdbCertification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbCertification {
+    #[serde(default)]
+    certification: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbContentRating {
+    iso_3166_1: String,
+    #[serde(default)]
+    rating: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbCredits {
+    #[serde(default)]
+    cast: Vec<TmdbCast>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbCast {
+    name: String,
+    character: Option<String>,
+    profile_path: Option<String>,
+}
+
+/// Descriptive metadata for a full NFO, normalised across movies and shows.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TitleDetails {
+    pub plot: Option<String>,
+    pub tagline: Option<String>,
+    pub genres: Vec<String>,
+    pub studios: Vec<String>,
+    /// Minutes.
+    pub runtime: Option<u32>,
+    /// US certification (`PG-13`, `TV-MA`).
+    pub certification: Option<String>,
+    pub cast: Vec<CastMember>,
+    pub poster_url: Option<String>,
+    pub fanart_url: Option<String>,
+    /// TMDB user rating out of 10, with its vote count.
+    pub rating: Option<(f64, u32)>,
+    /// Poster URL per season number (shows only).
+    #[serde(default)]
+    pub season_posters: BTreeMap<u32, String>,
+    /// The TMDB collection (box set) a movie belongs to.
+    #[serde(default)]
+    pub collection: Option<TitleCollection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TitleCollection {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastMember {
+    pub name: String,
+    pub role: Option<String>,
+    pub thumb_url: Option<String>,
+}
+
+/// Cast members kept per title.
+const TOP_CAST: usize = 10;
+const CERTIFICATION_COUNTRY: &str = "US";
+const IMAGE_BASE_URL: &str = "https://image.tmdb.org/t/p/original";
+
+impl From<TmdbDetailsResponse> for TitleDetails {
+    fn from(resp: TmdbDetailsResponse) -> Self {
+        let non_empty = |s: String| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        let image = |path: Option<String>| path.map(|p| format!("{}{}", IMAGE_BASE_URL, p));
+        let certification = match (resp.release_dates, resp.content_ratings) {
+            (Some(dates), _) => dates
+                .results
+                .into_iter()
+                .find(|r| r.iso_3166_1 == CERTIFICATION_COUNTRY)
+                .and_then(|r| {
+                    r.release_dates
+                        .into_iter()
+                        .map(|d| d.certification)
+                        .find(|c| !c.is_empty())
+                }),
+            (None, Some(ratings)) => ratings
+                .results
+                .into_iter()
+                .find(|r| r.iso_3166_1 == CERTIFICATION_COUNTRY)
+                .and_then(|r| non_empty(r.rating)),
+            (None, None) => None,
+        };
+        let studios = if resp.networks.is_empty() {
+            resp.production_companies
+        } else {
+            resp.networks
+        };
+        Self {
+            plot: non_empty(resp.overview),
+            tagline: non_empty(resp.tagline),
+            genres: resp.genres.into_iter().map(|g| g.name).collect(),
+            studios: studios.into_iter().map(|s| s.name).collect(),
+            runtime: resp
+                .runtime
+                .or_else(|| resp.episode_run_time.first().copied())
+                .filter(|&m| m > 0),
+            certification,
+            cast: resp
+                .credits
+                .map(|c| c.cast)
+                .unwrap_or_default()
+                .into_iter()
+                .take(TOP_CAST)
+                .map(|c| CastMember {
+                    name: c.name,
+                    role: c.character.and_then(non_empty),
+                    thumb_url: image(c.profile_path),
+                })
+                .collect(),
+            poster_url: image(resp.poster_path),
+            fanart_url: image(resp.backdrop_path),
+            rating: resp
+                .vote_average
+                .zip(resp.vote_count)
+                .filter(|&(_, votes)| votes > 0),
+            season_posters: resp
+                .seasons
+                .into_iter()
+                .filter_map(|s| Some((s.season_number, image(s.poster_path)?)))
+                .collect(),
+            collection: resp
+                .belongs_to_collection
+                .map(|c| TitleCollection {
+                    id: c.id,
+                    name: c.name.trim().to_string(),
+                })
+                .filter(|c| !c.name.is_empty()),
+        }
+    }
+}
+
 /// Minimum interval between TMDB requests (TMDB allows ~40 req/s; 100ms is conservative).
 const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
 
+/// How long looked-up alternative titles are reused. Every identification without an exact
+/// match asks for them, and rescans and re-identifies ask again for the same titles.
+const ALTERNATIVE_TITLES_TTL: Duration = Duration::from_secs(24 * 3600);
+const MAX_ALTERNATIVE_TITLES_CACHED: usize = 10_000;
+
+/// `alternative_titles` answers by (`movie`/`tv`, id), with when they were fetched.
+type AlternativeTitlesCache = HashMap<(&'static str, u32), (Instant, Vec<String>)>;
+
+/// Whether a fixture-backed client records live responses or replays recorded ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Query TMDB and keep every successful response; `save_fixtures` writes them out.
+    Record,
+    /// Answer from the recorded responses only; the network is never touched.
+    Replay,
+}
+
+/// Recorded TMDB responses keyed by request (see `fixture_key`), kept in a JSON file so
+/// identification can be exercised offline.
+struct Fixtures {
+    path: PathBuf,
+    mode: FixtureMode,
+    responses: std::sync::Mutex<BTreeMap<String, serde_json::Value>>,
+}
+
 pub struct TmdbClient {
     client: Client,
     api_key: String,
+    base_url: String,
     last_request: Mutex<Instant>,
+    fixtures: Option<Fixtures>,
+    alternative_titles: std::sync::Mutex<AlternativeTitlesCache>,
 }
 
 impl TmdbClient {
     /// Construct a TMDB client. Returns a configuration error (rather than panicking) if the
     /// HTTP client cannot be built, matching `RealDebridClient::new`/`TorBoxClient::new`.
     pub fn new(api_key: String) -> Result<Self, AppError> {
+        Self::with_base_url(api_key, DEFAULT_BASE_URL.to_string())
+    }
+
+    /// Construct a client against a non-default API root (a local stand-in in tests).
+    pub fn with_base_url(api_key: String, base_url: String) -> Result<Self, AppError> {
         let client = Client::builder()
             .timeout(Duration::from_secs(10))
             .build()
@@ -50,50 +414,272 @@ impl TmdbClient {
         Ok(Self {
             client,
             api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
             // Start in the past so the first request fires immediately.
             last_request: Mutex::new(Instant::now() - MIN_REQUEST_INTERVAL),
+            fixtures: None,
+            alternative_titles: std::sync::Mutex::new(HashMap::new()),
         })
     }
 
-    pub async fn search_movie(&self, query: &str, year: Option<&str>) -> Vec<TmdbSearchResult> {
-        let url = "https://api.themoviedb.org/3/search/movie";
+    /// Back the client with the fixture corpus at `path`. Replaying requires the corpus to
+    /// exist; recording starts from it when it does, so new responses are added to the
+    /// ones already there.
+    pub fn with_fixtures(
+        mut self,
+        path: impl Into<PathBuf>,
+        mode: FixtureMode,
+    ) -> Result<Self, AppError> {
+        let path = path.into();
+        let responses = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+                AppError::Config(format!(
+                    "Invalid TMDB fixture corpus {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && mode == FixtureMode::Record => {
+                BTreeMap::new()
+            }
+            Err(e) => {
+                return Err(AppError::Config(format!(
+                    "Cannot read TMDB fixture corpus {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        self.fixtures = Some(Fixtures {
+            path,
+            mode,
+            responses: std::sync::Mutex::new(responses),
+        });
+        Ok(self)
+    }
+
+    /// Write the recorded responses back to the fixture corpus. Does nothing unless the
+    /// client is recording.
+    pub fn save_fixtures(&self) -> std::io::Result<()> {
+        let Some(fixtures) = self
+            .fixtures
+            .as_ref()
+            .filter(|f| f.mode == FixtureMode::Record)
+        else {
+            return Ok(());
+        };
+        let content = {
+            let responses = fixtures.responses.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec_pretty(&*responses).map_err(std::io::Error::other)?
+        };
+        if let Some(dir) = fixtures.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&fixtures.path, content)
+    }
+
+    pub async fn search_movie(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        let url = format!("{}/search/movie", self.base_url);
         let mut params = vec![("api_key", self.api_key.as_str()), ("query", query)];
         let year_string;
         if let Some(y) = year {
             year_string = y.to_string();
             params.push(("primary_release_year", &year_string));
         }
-        self.search(url, params).await
+        self.search(&url, params).await
     }
 
-    pub async fn search_tv(&self, query: &str, year: Option<&str>) -> Vec<TmdbSearchResult> {
-        let url = "https://api.themoviedb.org/3/search/tv";
+    pub async fn search_tv(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        let url = format!("{}/search/tv", self.base_url);
         let mut params = vec![("api_key", self.api_key.as_str()), ("query", query)];
         let year_string;
         if let Some(y) = year {
             year_string = y.to_string();
             params.push(("first_air_date_year", &year_string));
         }
-        self.search(url, params).await
+        self.search(&url, params).await
+    }
+
+    /// A page of search results. Empty when TMDB has no match; an error when it could not
+    /// be asked, which callers must not take for a miss.
+    async fn search(
+        &self,
+        url: &str,
+        params: Vec<(&str, &str)>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        self.fetch::<TmdbResponse>(url, &params)
+            .await
+            .map(|resp| resp.results)
+            .map_err(|e| AppError::Metadata(format!("TMDB search failed: {}", e)))
+    }
+
+    /// Look up an id from another database (`external_source` is `imdb_id` or `tvdb_id`).
+    /// `None` if the request fails.
+    pub async fn find(&self, external_id: &str, external_source: &str) -> Option<TmdbFindResponse> {
+        let url = format!("{}/find/{}", self.base_url, external_id);
+        self.get(&url, &[("external_source", external_source)])
+            .await
+    }
+
+    /// A show's details, including its season list. `None` if the request fails.
+    pub async fn tv_details(&self, id: u32) -> Option<TmdbTvDetails> {
+        let url = format!("{}/tv/{}", self.base_url, id);
+        self.get(&url, &[]).await
+    }
+
+    /// One season of a show with its episodes. `None` if the request fails.
+    pub async fn tv_season(&self, id: u32, season: u32) -> Option<TmdbSeason> {
+        let url = format!("{}/tv/{}/season/{}", self.base_url, id, season);
+        self.get(&url, &[]).await
     }
 
-    async fn search(&self, url: &str, params: Vec<(&str, &str)>) -> Vec<TmdbSearchResult> {
-        match self
-            .fetch_with_retry(|| self.client.get(url).query(&params))
+    /// The alternative episode orderings (absolute, DVD, story arcs) defined for a show.
+    /// `None` if the request fails.
+    pub async fn tv_episode_groups(&self, id: u32) -> Option<Vec<TmdbEpisodeGroupSummary>> {
+        let url = format!("{}/tv/{}/episode_groups", self.base_url, id);
+        self.get::<TmdbEpisodeGroupsResponse>(&url, &[])
             .await
+            .map(|resp| resp.results)
+    }
+
+    /// One episode ordering with its episodes. `None` if the request fails.
+    pub async fn episode_group(&self, group_id: &str) -> Option<TmdbEpisodeGroup> {
+        let url = format!("{}/tv/episode_group/{}", self.base_url, group_id);
+        self.get(&url, &[]).await
+    }
+
+    /// Plot, genres, cast, artwork and the like for a movie or show, in one request.
+    /// `None` if the request fails.
+    pub async fn details(&self, media_type: &MediaType, id: u32) -> Option<TitleDetails> {
+        let (path, append) = match media_type {
+            MediaType::Movie => ("movie", "credits,release_dates"),
+            MediaType::Show => ("tv", "credits,content_ratings"),
+        };
+        let url = format!("{}/{}/{}", self.base_url, path, id);
+        self.get::<TmdbDetailsResponse>(&url, &[("append_to_response", append)])
+            .await
+            .map(TitleDetails::from)
+    }
+
+    /// Alternative titles (romaji, localized release titles) and translated titles of a
+    /// movie or show, in one request, without duplicates. Empty if the request fails.
+    /// Answers are reused for `ALTERNATIVE_TITLES_TTL`; failures are not.
+    pub async fn alternative_titles(&self, media_type: &MediaType, id: u32) -> Vec<String> {
+        let path = match media_type {
+            MediaType::Movie => "movie",
+            MediaType::Show => "tv",
+        };
+        if let Some((fetched, titles)) = self
+            .alternative_titles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(path, id))
         {
-            Ok(resp) => resp.results,
+            if fetched.elapsed() < ALTERNATIVE_TITLES_TTL {
+                return titles.clone();
+            }
+        }
+        let url = format!("{}/{}/{}", self.base_url, path, id);
+        let Some(resp) = self
+            .get::<TmdbTitlesResponse>(
+                &url,
+                &[("append_to_response", "alternative_titles,translations")],
+            )
+            .await
+        else {
+            return Vec::new();
+        };
+        let alternative = resp
+            .alternative_titles
+            .into_iter()
+            .flat_map(|a| a.titles)
+            .map(|t| t.title);
+        let translated = resp
+            .translations
+            .into_iter()
+            .flat_map(|t| t.translations)
+            .filter_map(|t| t.data)
+            .map(|d| d.title);
+        let mut titles: Vec<String> = Vec::new();
+        for title in alternative.chain(translated) {
+            let title = title.trim();
+            if !title.is_empty() && !titles.iter().any(|t| t == title) {
+                titles.push(title.to_string());
+            }
+        }
+        let mut cache = self
+            .alternative_titles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_ALTERNATIVE_TITLES_CACHED {
+            cache.retain(|_, (fetched, _)| fetched.elapsed() < ALTERNATIVE_TITLES_TTL);
+        }
+        if cache.len() < MAX_ALTERNATIVE_TITLES_CACHED {
+            cache.insert((path, id), (Instant::now(), titles.clone()));
+        }
+        titles
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str, extra: &[(&str, &str)]) -> Option<T> {
+        let mut params = vec![("api_key", self.api_key.as_str())];
+        params.extend_from_slice(extra);
+        match self.fetch::<T>(url, &params).await {
+            Ok(resp) => Some(resp),
             Err(e) => {
-                error!("TMDB search failed: {}", e.without_url());
-                Vec::new()
+                error!("TMDB request failed: {}", e);
+                None
             }
         }
     }
 
-    async fn fetch_with_retry(
+    /// Fetch and decode a response: from the fixture corpus when replaying, otherwise from
+    /// TMDB (keeping a copy when recording).
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, String> {
+        let fixtures = self.fixtures.as_ref();
+        let key =
+            fixtures.map(|_| fixture_key(url.strip_prefix(&self.base_url).unwrap_or(url), params));
+        let value = match (fixtures, &key) {
+            (Some(fixtures), Some(key)) if fixtures.mode == FixtureMode::Replay => fixtures
+                .responses
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(key)
+                .cloned()
+                .ok_or_else(|| format!("no recorded response for {}", key))?,
+            _ => {
+                let value = self
+                    .fetch_with_retry::<serde_json::Value>(|| self.client.get(url).query(params))
+                    .await
+                    .map_err(|e| e.without_url().to_string())?;
+                if let (Some(fixtures), Some(key)) = (fixtures, key) {
+                    fixtures
+                        .responses
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(key, value.clone());
+                }
+                value
+            }
+        };
+        serde_json::from_value(value).map_err(|e| format!("unexpected response: {}", e))
+    }
+
+    async fn fetch_with_retry<T: DeserializeOwned>(
         &self,
         make_request: impl Fn() -> RequestBuilder,
-    ) -> Result<TmdbResponse, reqwest::Error> {
+    ) -> Result<T, reqwest::Error> {
         let mut last_error: Option<reqwest::Error> = None;
         let max_attempts = 10;
 
@@ -138,7 +724,7 @@ impl TmdbClient {
                     }
 
                     match resp.error_for_status() {
-                        Ok(resp) => return resp.json::<TmdbResponse>().await,
+                        Ok(resp) => return resp.json::<T>().await,
                         Err(e) => {
                             let e = e.without_url();
                             warn!(
@@ -176,6 +762,21 @@ impl TmdbClient {
     }
 }
 
+/// Fixture corpus key for a request: its path below the API root plus its query
+/// parameters sorted by name, without the API key (`/search/movie?query=Heat&year=1995`).
+fn fixture_key(path: &str, params: &[(&str, &str)]) -> String {
+    let mut sorted: Vec<&(&str, &str)> = params.iter().filter(|(k, _)| *k != "api_key").collect();
+    sorted.sort();
+    if sorted.is_empty() {
+        return path.to_string();
+    }
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for (k, v) in sorted {
+        query.append_pair(k, v);
+    }
+    format!("{}?{}", path, query.finish())
+}
+
 /// Build the synthetic Bad Gateway error returned when every retry attempt hit a retryable
 /// status code, so the function surfaces an error instead of panicking. The construction is
 /// infallible by design (a fixed valid status and static body), hence the `expect`s.
@@ -195,6 +796,7 @@ fn synthetic_exhausted_error() -> reqwest::Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::spawn_json;
 
     #[test]
     fn synthetic_exhausted_error_is_a_bad_gateway_error_not_a_panic() {
@@ -268,4 +870,180 @@ mod tests {
         assert_eq!(result.id, 789);
         assert_eq!(result.title, "Test");
     }
+
+    #[tokio::test]
+    async fn recorded_responses_replay_without_network() {
+        let corpus =
+            std::env::temp_dir().join(format!("tmdb-fixtures-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&corpus);
+        let (base_url, server) = spawn_json(
+            r#"{"results": [{"id": 949, "title": "Heat", "release_date": "1995-12-15", "popularity": 30.0}]}"#,
+        )
+        .await;
+        let recorder = TmdbClient::with_base_url("key".to_string(), format!("{}/3", base_url))
+            .unwrap()
+            .with_fixtures(&corpus, FixtureMode::Record)
+            .unwrap();
+        assert_eq!(
+            recorder.search_movie("Heat", Some("1995")).await.unwrap()[0].id,
+            949
+        );
+        server.await.unwrap();
+        recorder.save_fixtures().unwrap();
+
+        // The stand-in answered its only request: replay must come from the corpus.
+        let replay =
+            TmdbClient::with_base_url("other".to_string(), "http://127.0.0.1:9".to_string())
+                .unwrap()
+                .with_fixtures(&corpus, FixtureMode::Replay)
+                .unwrap();
+        let results = replay.search_movie("Heat", Some("1995")).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Heat");
+        assert!(replay.search_movie("Heat", None).await.is_err());
+        let _ = std::fs::remove_file(&corpus);
+    }
+
+    #[test]
+    fn fixture_key_sorts_params_and_drops_api_key() {
+        assert_eq!(
+            fixture_key(
+                "/search/tv",
+                &[
+                    ("api_key", "secret"),
+                    ("query", "The Office"),
+                    ("first_air_date_year", "2005")
+                ]
+            ),
+            "/search/tv?first_air_date_year=2005&query=The+Office"
+        );
+        assert_eq!(
+            fixture_key("/tv/1396", &[("api_key", "secret")]),
+            "/tv/1396"
+        );
+        assert!(TmdbClient::new(String::new())
+            .unwrap()
+            .with_fixtures("/nonexistent/corpus.json", FixtureMode::Replay)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn tv_season_fetches_episodes_from_base_url() {
+        let (base_url, server) = spawn_json(
+            r#"{"season_number": 2, "episodes": [
+                {"season_number": 2, "episode_number": 1, "name": "Seven Thirty-Seven", "air_date": "2009-03-08"},
+                {"season_number": 2, "episode_number": 2, "air_date": null}
+            ]}"#,
+        )
+        .await;
+        let client =
+            TmdbClient::with_base_url("key".to_string(), format!("{}/3", base_url)).unwrap();
+        let season = client.tv_season(1396, 2).await.unwrap();
+        assert_eq!(season.season_number, 2);
+        assert_eq!(season.episodes.len(), 2);
+        assert_eq!(season.episodes[0].name, "Seven Thirty-Seven");
+        assert_eq!(season.episodes[0].air_date.as_deref(), Some("2009-03-08"));
+        assert!(season.episodes[1].air_date.is_none());
+        assert_eq!(
+            server.await.unwrap().lines().next(),
+            Some("GET /3/tv/1396/season/2?api_key=key HTTP/1.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn details_normalises_movie_response() {
+        let (base_url, server) = spawn_json(
+            r#"{"overview": "A thief who steals secrets.", "tagline": "", "runtime": 148,
+                "genres": [{"id": 28, "name": "Action"}],
+                "production_companies": [{"id": 923, "name": "Legendary Pictures"}],
+                "release_dates": {"results": [
+                    {"iso_3166_1": "GB", "release_dates": [{"certification": "12A"}]},
+                    {"iso_3166_1": "US", "release_dates": [{"certification": ""}, {"certification": "PG-13"}]}
+                ]},
+                "credits": {"cast": [{"name": "Leonardo DiCaprio", "character": "Cobb", "profile_path": "/leo.jpg"}]},
+                "poster_path": "/poster.jpg", "backdrop_path": null,
+                "belongs_to_collection": {"id": 1, "name": " Dream Heists ", "poster_path": null},
+                "vote_average": 8.4, "vote_count": 35000}"#,
+        )
+        .await;
+        let client =
+            TmdbClient::with_base_url("key".to_string(), format!("{}/3", base_url)).unwrap();
+        let details = client.details(&MediaType::Movie, 27205).await.unwrap();
+        assert_eq!(details.plot.as_deref(), Some("A thief who steals secrets."));
+        assert_eq!(details.tagline, None);
+        assert_eq!(details.genres, vec!["Action"]);
+        assert_eq!(details.studios, vec!["Legendary Pictures"]);
+        assert_eq!(details.runtime, Some(148));
+        assert_eq!(details.certification.as_deref(), Some("PG-13"));
+        assert_eq!(details.cast[0].role.as_deref(), Some("Cobb"));
+        assert_eq!(
+            details.cast[0].thumb_url.as_deref(),
+            Some("https://image.tmdb.org/t/p/original/leo.jpg")
+        );
+        assert_eq!(
+            details.poster_url.as_deref(),
+            Some("https://image.tmdb.org/t/p/original/poster.jpg")
+        );
+        assert_eq!(details.fanart_url, None);
+        assert_eq!(details.rating, Some((8.4, 35000)));
+        assert_eq!(
+            details.collection,
+            Some(TitleCollection {
+                id: 1,
+                name: "Dream Heists".to_string(),
+            })
+        );
+        assert_eq!(server.await.unwrap().lines().next(), Some("GET /3/movie/27205?api_key=key&append_to_response=credits%2Crelease_dates HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn alternative_titles_merges_show_titles_and_translations() {
+        let (base_url, server) = spawn_json(
+            r#"{"id": 1429, "name": "Attack on Titan",
+                "alternative_titles": {"results": [
+                    {"iso_3166_1": "JP", "title": "Shingeki no Kyojin", "type": "Romaji"},
+                    {"iso_3166_1": "US", "title": "AoT", "type": ""}
+                ]},
+                "translations": {"translations": [
+                    {"iso_639_1": "fr", "data": {"name": "L'Attaque des Titans"}},
+                    {"iso_639_1": "en", "data": {"name": ""}},
+                    {"iso_639_1": "es", "data": {"name": "AoT"}}
+                ]}}"#,
+        )
+        .await;
+        let client =
+            TmdbClient::with_base_url("key".to_string(), format!("{}/3", base_url)).unwrap();
+        let titles = client.alternative_titles(&MediaType::Show, 1429).await;
+        assert_eq!(
+            titles,
+            vec!["Shingeki no Kyojin", "AoT", "L'Attaque des Titans"]
+        );
+        assert_eq!(server.await.unwrap().lines().next(), Some("GET /3/tv/1429?api_key=key&append_to_response=alternative_titles%2Ctranslations HTTP/1.1"));
+        // The stand-in answered its only request: the second lookup is cached.
+        assert_eq!(
+            client.alternative_titles(&MediaType::Show, 1429).await,
+            titles
+        );
+    }
+
+    #[tokio::test]
+    async fn tv_details_lists_seasons() {
+        let (base_url, server) = spawn_json(
+            r#"{"id": 1396, "name": "Breaking Bad", "seasons": [
+                {"season_number": 0, "episode_count": 9, "air_date": "2009-02-17"},
+                {"season_number": 1, "episode_count": 7, "air_date": "2008-01-20"}
+            ]}"#,
+        )
+        .await;
+        let client =
+            TmdbClient::with_base_url("key".to_string(), format!("{}/3", base_url)).unwrap();
+        let details = client.tv_details(1396).await.unwrap();
+        assert_eq!(details.name, "Breaking Bad");
+        assert_eq!(details.seasons.len(), 2);
+        assert_eq!(details.seasons[1].episode_count, 7);
+        assert_eq!(
+            server.await.unwrap().lines().next(),
+            Some("GET /3/tv/1396?api_key=key HTTP/1.1")
+        );
+    }
 }
@@ -0,0 +1,209 @@
+use crate::error::AppError;
+use crate::vfs::MediaType;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_TRAKT_URL: &str = "https://api.trakt.tv";
+
+/// Characters escaped in a URL path segment: everything but RFC 3986 unreserved ones.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A movie or show from a Trakt watchlist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchlistItem {
+    pub media_type: MediaType,
+    pub title: String,
+    pub year: Option<u32>,
+    pub tmdb_id: Option<u64>,
+    pub imdb_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawItem {
+    #[serde(rename = "type")]
+    kind: String,
+    movie: Option<RawMedia>,
+    show: Option<RawMedia>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMedia {
+    title: String,
+    year: Option<u32>,
+    #[serde(default)]
+    ids: RawIds,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawIds {
+    tmdb: Option<u64>,
+    imdb: Option<String>,
+}
+
+/// Read-only client for a user's public Trakt watchlist. Only the app's client id is
+/// needed (no OAuth), so the watchlist must be public.
+pub struct TraktClient {
+    client: Client,
+    base_url: String,
+    username: String,
+    /// Pre-built sensitive header value so the client id is never printed in debug output.
+    client_id_header: HeaderValue,
+}
+
+impl TraktClient {
+    pub fn new(base_url: String, client_id: String, username: String) -> Result<Self, AppError> {
+        let mut client_id_header = HeaderValue::from_str(&client_id).map_err(|e| {
+            AppError::Config(format!(
+                "Trakt client id contains invalid HTTP header characters: {}",
+                e
+            ))
+        })?;
+        client_id_header.set_sensitive(true);
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| AppError::Config(format!("Failed to build Trakt HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username,
+            client_id_header,
+        })
+    }
+
+    /// Build a client from `TRAKT_CLIENT_ID` / `TRAKT_USERNAME` (and optionally
+    /// `TRAKT_API_URL`). Returns `None` when unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let client_id = std::env::var("TRAKT_CLIENT_ID").ok()?;
+        let username = std::env::var("TRAKT_USERNAME").ok()?;
+        if client_id.is_empty() || username.is_empty() {
+            return None;
+        }
+        let base_url =
+            std::env::var("TRAKT_API_URL").unwrap_or_else(|_| DEFAULT_TRAKT_URL.to_string());
+        match Self::new(base_url, client_id, username) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Failed to create Trakt client: {}", e);
+                None
+            }
+        }
+    }
+
+    fn watchlist_url(&self) -> String {
+        format!(
+            "{}/users/{}/watchlist",
+            self.base_url,
+            utf8_percent_encode(&self.username, PATH_SEGMENT)
+        )
+    }
+
+    /// Movies and shows on the user's watchlist. Season and episode entries are skipped.
+    pub async fn watchlist(&self) -> Result<Vec<WatchlistItem>, reqwest::Error> {
+        let items: Vec<RawItem> = self
+            .client
+            .get(self.watchlist_url())
+            .header("trakt-api-version", "2")
+            .header("trakt-api-key", self.client_id_header.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        Ok(items.into_iter().filter_map(convert_item).collect())
+    }
+}
+
+fn convert_item(item: RawItem) -> Option<WatchlistItem> {
+    let (media_type, media) = match item.kind.as_str() {
+        "movie" => (MediaType::Movie, item.movie?),
+        "show" => (MediaType::Show, item.show?),
+        _ => return None,
+    };
+    Some(WatchlistItem {
+        media_type,
+        title: media.title,
+        year: media.year,
+        tmdb_id: media.ids.tmdb,
+        imdb_id: media.ids.imdb,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_json;
+
+    const WATCHLIST: &str = r#"[
+        {"rank": 1, "type": "movie", "movie": {"title": "Inception", "year": 2010,
+            "ids": {"trakt": 16662, "slug": "inception-2010", "imdb": "tt1375666", "tmdb": 27205}}},
+        {"rank": 2, "type": "show", "show": {"title": "Breaking Bad", "year": 2008,
+            "ids": {"trakt": 1388, "tvdb": 81189, "imdb": "tt0903747", "tmdb": 1396}}},
+        {"rank": 3, "type": "season", "season": {"number": 1}, "show": {"title": "X", "year": 2000, "ids": {}}}
+    ]"#;
+
+    #[tokio::test]
+    async fn watchlist_fetches_movies_and_shows() {
+        let (base, server) = spawn_json(WATCHLIST).await;
+        let client = TraktClient::new(base, "client-id".to_string(), "alice".to_string()).unwrap();
+        let items = client.watchlist().await.unwrap();
+        assert_eq!(
+            items,
+            vec![
+                WatchlistItem {
+                    media_type: MediaType::Movie,
+                    title: "Inception".to_string(),
+                    year: Some(2010),
+                    tmdb_id: Some(27205),
+                    imdb_id: Some("tt1375666".to_string()),
+                },
+                WatchlistItem {
+                    media_type: MediaType::Show,
+                    title: "Breaking Bad".to_string(),
+                    year: Some(2008),
+                    tmdb_id: Some(1396),
+                    imdb_id: Some("tt0903747".to_string()),
+                },
+            ]
+        );
+
+        let request = server.await.unwrap().to_lowercase();
+        assert!(request.starts_with("get /users/alice/watchlist "));
+        assert!(request.contains("trakt-api-key: client-id"));
+        assert!(request.contains("trakt-api-version: 2"));
+    }
+
+    #[test]
+    fn watchlist_url_escapes_username() {
+        let client = TraktClient::new(
+            DEFAULT_TRAKT_URL.to_string(),
+            "client-id".to_string(),
+            "j.doe/../me?x".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            client.watchlist_url(),
+            "https://api.trakt.tv/users/j.doe%2F..%2Fme%3Fx/watchlist"
+        );
+    }
+
+    #[test]
+    fn new_rejects_invalid_client_id() {
+        assert!(TraktClient::new(
+            DEFAULT_TRAKT_URL.to_string(),
+            "bad\nid".to_string(),
+            "alice".to_string()
+        )
+        .is_err());
+    }
+}
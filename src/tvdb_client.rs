@@ -0,0 +1,404 @@
+use crate::error::AppError;
+use crate::metadata_source::MetadataSource;
+use crate::tmdb_client::{TitleDetails, TmdbFindResponse, TmdbSearchResult};
+use crate::vfs::MediaType;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+
+const DEFAULT_TVDB_URL: &str = "https://api4.thetvdb.com/v4";
+
+/// Every TVDB v4 response wraps its payload in `data`.
+#[derive(Debug, Deserialize)]
+struct TvdbResponse<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginData {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    tvdb_id: String,
+    name: String,
+    #[serde(default)]
+    year: Option<String>,
+    #[serde(default)]
+    first_air_time: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// Title per three-letter language code.
+    #[serde(default)]
+    translations: HashMap<String, String>,
+}
+
+impl SearchHit {
+    /// The hit as a search result: its English title when there is one, with aliases and
+    /// translations as alternative titles. `None` if its id isn't numeric.
+    fn into_result(self) -> Option<TmdbSearchResult> {
+        let english = self.translations.get("eng").cloned();
+        let mut alternative_titles: Vec<String> = self.aliases;
+        for title in self.translations.into_values() {
+            if !alternative_titles.contains(&title) {
+                alternative_titles.push(title);
+            }
+        }
+        Some(TmdbSearchResult {
+            id: self.tvdb_id.parse().ok()?,
+            title: english.unwrap_or_else(|| self.name.clone()),
+            original_title: Some(self.name),
+            release_date: self.first_air_time.filter(|d| !d.is_empty()).or(self.year),
+            popularity: 0.0,
+            vote_average: None,
+            vote_count: None,
+            alternative_titles,
+        })
+    }
+}
+
+/// `GET /series/{id}` or `/movies/{id}`, for id lookups.
+#[derive(Debug, Deserialize)]
+struct BaseRecord {
+    id: u32,
+    name: String,
+    #[serde(default)]
+    year: Option<String>,
+    #[serde(default, rename = "firstAired")]
+    first_aired: Option<String>,
+}
+
+/// `GET /series/{id}/extended` or `/movies/{id}/extended`.
+#[derive(Debug, Deserialize)]
+struct ExtendedRecord {
+    #[serde(default)]
+    genres: Vec<Named>,
+    #[serde(default)]
+    image: Option<String>,
+    /// Movies.
+    #[serde(default)]
+    runtime: Option<u32>,
+    /// Shows.
+    #[serde(default, rename = "averageRuntime")]
+    average_runtime: Option<u32>,
+    /// Shows.
+    #[serde(default, rename = "originalNetwork")]
+    original_network: Option<Named>,
+    /// Movies.
+    #[serde(default)]
+    studios: Vec<Named>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+/// `GET /series/{id}/translations/eng`.
+#[derive(Debug, Deserialize)]
+struct Translation {
+    #[serde(default)]
+    overview: Option<String>,
+}
+
+/// Client for TheTVDB v4 API. Logs in with the project API key (and a subscriber PIN,
+/// if any) on first use and keeps the bearer token.
+pub struct TvdbClient {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    pin: Option<String>,
+    token: Mutex<Option<String>>,
+}
+
+impl std::fmt::Debug for TvdbClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TvdbClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TvdbClient {
+    pub fn new(base_url: String, api_key: String, pin: Option<String>) -> Result<Self, AppError> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| AppError::Config(format!("Failed to build TVDB HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            pin,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Build a client from `TVDB_API_KEY` (and optionally `TVDB_PIN` and `TVDB_API_URL`).
+    /// Returns `None` when unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("TVDB_API_KEY").ok()?;
+        if api_key.trim().is_empty() {
+            return None;
+        }
+        let pin = std::env::var("TVDB_PIN")
+            .ok()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        let base_url =
+            std::env::var("TVDB_API_URL").unwrap_or_else(|_| DEFAULT_TVDB_URL.to_string());
+        match Self::new(base_url, api_key.trim().to_string(), pin) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Failed to create TVDB client: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn login(&self) -> Result<String, reqwest::Error> {
+        let mut body = serde_json::json!({ "apikey": self.api_key });
+        if let Some(pin) = &self.pin {
+            body["pin"] = serde_json::Value::String(pin.clone());
+        }
+        let resp: TvdbResponse<LoginData> = self
+            .client
+            .post(format!("{}/login", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        Ok(resp.data.token)
+    }
+
+    /// Send an authenticated request, logging in first if needed and once more if the
+    /// token has expired.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        make_request: impl Fn() -> RequestBuilder,
+    ) -> Result<T, reqwest::Error> {
+        let mut renewed = false;
+        loop {
+            let token = {
+                let mut token = self.token.lock().await;
+                match token.as_ref() {
+                    Some(t) => t.clone(),
+                    None => token.insert(self.login().await?).clone(),
+                }
+            };
+            let resp = make_request()
+                .bearer_auth(&token)
+                .send()
+                .await
+                .map_err(|e| e.without_url())?;
+            if resp.status() == StatusCode::UNAUTHORIZED && !renewed {
+                *self.token.lock().await = None;
+                renewed = true;
+                continue;
+            }
+            let resp: TvdbResponse<T> = resp
+                .error_for_status()
+                .map_err(|e| e.without_url())?
+                .json()
+                .await
+                .map_err(|e| e.without_url())?;
+            return Ok(resp.data);
+        }
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        kind: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        let url = format!("{}/search", self.base_url);
+        let mut params = vec![("query", query), ("type", kind)];
+        if let Some(year) = year {
+            params.push(("year", year));
+        }
+        let hits = self
+            .get::<Vec<SearchHit>>(|| self.client.get(&url).query(&params))
+            .await?;
+        Ok(hits
+            .into_iter()
+            .filter_map(SearchHit::into_result)
+            .collect())
+    }
+}
+
+fn path_for(media_type: &MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movies",
+        MediaType::Show => "series",
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataSource for TvdbClient {
+    fn id_source(&self) -> &'static str {
+        "tvdb"
+    }
+
+    async fn search_movie(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        self.search(query, "movie", year).await
+    }
+
+    async fn search_tv(
+        &self,
+        query: &str,
+        year: Option<&str>,
+    ) -> Result<Vec<TmdbSearchResult>, AppError> {
+        self.search(query, "series", year).await
+    }
+
+    async fn details(&self, media_type: &MediaType, id: &str) -> Option<TitleDetails> {
+        let id: u32 = id.parse().ok()?;
+        let base = format!("{}/{}/{}", self.base_url, path_for(media_type), id);
+        let extended_url = format!("{}/extended", base);
+        let translation_url = format!("{}/translations/eng", base);
+        let (extended, translation) = tokio::join!(
+            self.get::<ExtendedRecord>(|| self
+                .client
+                .get(&extended_url)
+                .query(&[("short", "true")])),
+            self.get::<Translation>(|| self.client.get(&translation_url))
+        );
+        let extended = match extended {
+            Ok(extended) => extended,
+            Err(e) => {
+                error!("TVDB details request failed: {}", e);
+                return None;
+            }
+        };
+        let studios = match extended.original_network {
+            Some(network) => vec![network.name],
+            None => extended.studios.into_iter().map(|s| s.name).collect(),
+        };
+        Some(TitleDetails {
+            plot: translation
+                .ok()
+                .and_then(|t| t.overview)
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty()),
+            genres: extended.genres.into_iter().map(|g| g.name).collect(),
+            studios,
+            runtime: extended
+                .runtime
+                .or(extended.average_runtime)
+                .filter(|&m| m > 0),
+            poster_url: extended.image.filter(|i| !i.is_empty()),
+            ..Default::default()
+        })
+    }
+
+    async fn find(&self, external_id: &str, external_source: &str) -> Option<TmdbFindResponse> {
+        if external_source != "tvdb_id" {
+            return None;
+        }
+        let url = format!("{}/series/{}", self.base_url, external_id);
+        let record = match self.get::<BaseRecord>(|| self.client.get(&url)).await {
+            Ok(record) => record,
+            Err(e) => {
+                error!("TVDB lookup failed: {}", e);
+                return None;
+            }
+        };
+        Some(TmdbFindResponse {
+            movie_results: Vec::new(),
+            tv_results: vec![TmdbSearchResult {
+                id: record.id,
+                title: record.name,
+                original_title: None,
+                release_date: record.first_aired.filter(|d| !d.is_empty()).or(record.year),
+                popularity: 0.0,
+                vote_average: None,
+                vote_count: None,
+                alternative_titles: Vec::new(),
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_routes;
+
+    #[tokio::test]
+    async fn search_logs_in_and_maps_series_hits() {
+        let (base, mut requests) = spawn_routes(vec![
+            ("/login", r#"{"data": {"token": "t0k"}}"#),
+            (
+                "/search",
+                r#"{"data": [
+                    {"tvdb_id": "81189", "name": "Breaking Bad", "year": "2008",
+                     "first_air_time": "2008-01-20", "aliases": ["BB"],
+                     "translations": {"eng": "Breaking Bad", "deu": "Breaking Bad – Reue"}},
+                    {"tvdb_id": "not-a-number", "name": "Broken"}
+                ]}"#,
+            ),
+        ])
+        .await;
+        let client = TvdbClient::new(format!("{}/v4", base), "key".to_string(), None).unwrap();
+        let results = client
+            .search_tv("Breaking Bad", Some("2008"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 81189);
+        assert_eq!(results[0].title, "Breaking Bad");
+        assert_eq!(results[0].release_date.as_deref(), Some("2008-01-20"));
+        assert!(results[0].alternative_titles.contains(&"BB".to_string()));
+        assert!(results[0]
+            .alternative_titles
+            .contains(&"Breaking Bad – Reue".to_string()));
+        assert_eq!(client.external_id("81189"), "tvdb:81189");
+
+        assert_eq!(requests.recv().await.unwrap(), "POST /v4/login HTTP/1.1");
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "GET /v4/search?query=Breaking+Bad&type=series&year=2008 HTTP/1.1"
+        );
+    }
+
+    #[tokio::test]
+    async fn details_combine_extended_record_and_translation() {
+        let (base, _requests) = spawn_routes(vec![
+            ("/login", r#"{"data": {"token": "t0k"}}"#),
+            (
+                "/extended",
+                r#"{"data": {"genres": [{"name": "Drama"}], "image": "https://artworks.thetvdb.com/p.jpg",
+                    "averageRuntime": 47, "originalNetwork": {"name": "AMC"}}}"#,
+            ),
+            (
+                "/translations/eng",
+                r#"{"data": {"name": "Breaking Bad", "overview": " A chemistry teacher. "}}"#,
+            ),
+        ])
+        .await;
+        let client = TvdbClient::new(format!("{}/v4", base), "key".to_string(), None).unwrap();
+        let details = client.details(&MediaType::Show, "81189").await.unwrap();
+        assert_eq!(details.plot.as_deref(), Some("A chemistry teacher."));
+        assert_eq!(details.genres, vec!["Drama"]);
+        assert_eq!(details.studios, vec!["AMC"]);
+        assert_eq!(details.runtime, Some(47));
+        assert_eq!(
+            details.poster_url.as_deref(),
+            Some("https://artworks.thetvdb.com/p.jpg")
+        );
+    }
+}
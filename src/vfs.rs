@@ -1,10 +1,20 @@
+use crate::anime::AnimeMapping;
+use crate::artwork::ArtworkCache;
+use crate::details::DetailsCache;
+use crate::episode_catalog::{CatalogEpisode, EpisodeCatalog};
+use crate::naming::{naming_from_env, title_with_year, JellyfinNaming, NamingStrategy};
 use crate::provider::FileLocator;
-use crate::rd_client::TorrentInfo;
+use crate::quality::version_label;
+use crate::quality::QualityProfile;
+use crate::rd_client::{TorrentFile, TorrentInfo};
+use crate::subtitles::{is_subtitle_file, subtitle_name, SubtitleCache};
+use crate::tmdb_client::TitleDetails;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
-use std::sync::LazyLock;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const VIDEO_EXTENSIONS: &[&str] = &[
@@ -109,6 +119,79 @@ static SEASON_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"(?i)s(\d+)|season\s*(\d+)|\b(\d{1,2})x\d{1,3}\b|part\s*(\d+)").unwrap()
 });
 
+/// `S01E02` / `S01.E02` / `1x02` episode markers. Unlike `SEASON_RE` this requires both
+/// numbers, so it only matches files that name a specific episode.
+static EPISODE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bs(\d{1,2})[ ._-]?e(\d{1,3})|\b(\d{1,2})x(\d{2,3})\b").unwrap()
+});
+
+/// Season and episode number named by a filename, if it carries an explicit marker.
+pub fn parse_episode(filename: &str) -> Option<(u32, u32)> {
+    let cap = EPISODE_RE.captures(filename)?;
+    let season = cap.get(1).or_else(|| cap.get(3))?.as_str().parse().ok()?;
+    let episode = cap.get(2).or_else(|| cap.get(4))?.as_str().parse().ok()?;
+    Some((season, episode))
+}
+
+/// Continuation of a multi-episode marker after `S01E01`: `E02`, `-E02`, `-02`, `.E02`.
+static EPISODE_RANGE_NEXT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:[ ._]?-?[ ._]?e|-)(\d{1,3})\b").unwrap());
+
+/// Ranges spanning more episodes than this are treated as a misparse.
+const MAX_EPISODE_RANGE: u32 = 50;
+
+/// Season plus first and last episode named by a filename: `S01E02` is `(1, 2, 2)`,
+/// `S01E01E02`, `S01E01-E03` and `S01E01-03` cover a range.
+pub fn parse_episode_range(filename: &str) -> Option<(u32, u32, u32)> {
+    let (season, first) = parse_episode(filename)?;
+    let marker = EPISODE_RE.find(filename)?;
+    let mut last = first;
+    let mut rest = &filename[marker.end()..];
+    while let Some(next) = EPISODE_RANGE_NEXT_RE.captures(rest) {
+        let Ok(episode) = next[1].parse::<u32>() else {
+            break;
+        };
+        if episode <= last || episode > first + MAX_EPISODE_RANGE {
+            break;
+        }
+        last = episode;
+        rest = &rest[next.get(0)?.end()..];
+    }
+    Some((season, first, last))
+}
+
+/// Specials and OVAs without an `SxxEyy` marker, named as such or kept in a `Specials`
+/// directory. They belong in `Season 00`.
+static SPECIAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(specials?|ova|oad|ona)\b").unwrap());
+
+fn is_special(path: &str) -> bool {
+    let mut parts = path.rsplit('/');
+    let filename = parts.next().unwrap_or(path);
+    let stem = filename.rfind('.').map_or(filename, |i| &filename[..i]);
+    SPECIAL_RE.is_match(stem)
+        || parts
+            .next()
+            .is_some_and(|dir| dir.eq_ignore_ascii_case("specials"))
+}
+
+/// Absolute episode numbers as used by anime releases: `Show - 137.mkv`,
+/// `[Group] Show - 137v2 [1080p].mkv`, `Show E137.mkv` or `Show Episode 137.mkv`.
+static ABSOLUTE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:\s-\s|\b(?:ep?|episode)[\s.]?)(\d{1,4})(?:v\d)?(?:[\s.\[(]|$)").unwrap()
+});
+
+/// Absolute episode number of a file without an `SxxEyy` marker. Four-digit numbers that
+/// look like a year are rejected.
+pub fn parse_absolute_episode(filename: &str) -> Option<u32> {
+    if parse_episode(filename).is_some() {
+        return None;
+    }
+    let stem = filename.rfind('.').map_or(filename, |i| &filename[..i]);
+    let number: u32 = ABSOLUTE_RE.captures(stem)?.get(1)?.as_str().parse().ok()?;
+    (number > 0 && !(1900..=2100).contains(&number)).then_some(number)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum VfsNode {
     Directory {
@@ -123,6 +206,20 @@ pub enum VfsNode {
     VirtualFile {
         content: Vec<u8>,
     },
+    /// Artwork image proxied from TMDB's image CDN and cached on disk on first access.
+    Artwork {
+        url: String,
+    },
+    /// File in a local cache directory (fetched subtitles).
+    CachedFile {
+        path: PathBuf,
+        file_size: u64,
+    },
+    /// A movie folder listed again under `Collections/`, resolved to the folder at `target`
+    /// (`Movies/<folder>`) on lookup instead of being copied.
+    Link {
+        target: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -152,6 +249,16 @@ pub struct MediaMetadata {
     pub external_id: Option<String>,
 }
 
+impl MediaMetadata {
+    /// The TMDB id, when the title was identified through TMDB.
+    pub fn tmdb_id(&self) -> Option<u32> {
+        self.external_id
+            .as_deref()
+            .and_then(|id| id.strip_prefix("tmdb:"))
+            .and_then(|id| id.parse().ok())
+    }
+}
+
 impl PartialEq for MediaMetadata {
     fn eq(&self, other: &Self) -> bool {
         if self.media_type != other.media_type {
@@ -185,9 +292,116 @@ impl Hash for MediaMetadata {
     }
 }
 
+/// Settings that shape the tree `DebridVfs::build_with` produces. Each VFS carries the
+/// options it was built with so the scan loop can rebuild it the same way.
+#[derive(Debug, Clone)]
+pub struct VfsOptions {
+    /// Decides which torrent represents a title when several map to it.
+    pub quality: QualityProfile,
+    /// Expose every distinct release of a movie as a Jellyfin multi-version file
+    /// (`Title - 2160p.mkv`, `Title - 1080p.mkv`) instead of only the best one.
+    pub movie_versions: bool,
+    /// Folder, file and NFO naming for the target media server.
+    pub naming: Arc<dyn NamingStrategy>,
+    /// TMDB episode details used for episode titles and NFOs, when either is enabled.
+    pub episodes: Option<Arc<EpisodeCatalog>>,
+    /// Rename episodes to `Show - S01E02 - Title.ext`, so duplicates across torrents
+    /// collapse by episode number rather than filename.
+    pub rename_episodes: bool,
+    /// Write an `episodedetails` NFO next to every numbered episode, not only
+    /// multi-episode files.
+    pub episode_nfo: bool,
+    /// Cached TMDB details, used for full NFOs and artwork when either is enabled.
+    pub details: Option<Arc<DetailsCache>>,
+    /// Write the cached details into `movie.nfo`/`tvshow.nfo` (full NFO mode).
+    pub full_nfo: bool,
+    /// When set, media folders expose `poster.jpg`, `fanart.jpg` and season posters.
+    pub artwork: Option<Arc<ArtworkCache>>,
+    /// When set, videos without bundled subtitles get the ones fetched from OpenSubtitles.
+    pub subtitles: Option<Arc<SubtitleCache>>,
+    /// When set, absolute-numbered episodes (`Show - 137.mkv`) are placed in their TMDB
+    /// season and renamed `Show - S03E05.ext`.
+    pub anime: Option<Arc<AnimeMapping>>,
+    /// Expose trailers, featurettes and other bonus videos in Jellyfin's extras folders
+    /// (`trailers/`, `featurettes/`, ...) instead of dropping them.
+    pub extras: bool,
+    /// Besides a full-disc movie's main feature, expose its whole `BDMV`/`VIDEO_TS`
+    /// folder for players that play disc structures.
+    pub disc_folders: bool,
+    /// Write each movie's TMDB collection into `movie.nfo` as `<set>`, so media servers
+    /// build box sets.
+    pub collections: bool,
+    /// Also list every movie of a collection under `Collections/<Name>/`.
+    pub collection_folders: bool,
+}
+
+impl Default for VfsOptions {
+    fn default() -> Self {
+        Self {
+            quality: QualityProfile::default(),
+            movie_versions: false,
+            naming: Arc::new(JellyfinNaming),
+            episodes: None,
+            rename_episodes: false,
+            episode_nfo: false,
+            details: None,
+            full_nfo: false,
+            artwork: None,
+            subtitles: None,
+            anime: None,
+            extras: false,
+            disc_folders: false,
+            collections: false,
+            collection_folders: false,
+        }
+    }
+}
+
+impl VfsOptions {
+    /// Read `MOVIE_VERSIONS`, `NAMING_PROFILE`, `RENAME_EPISODES`, `EPISODE_NFO`,
+    /// `FULL_NFO`, `EXTRAS`, `DISC_FOLDERS`, `COLLECTIONS`, `COLLECTION_FOLDERS` and the
+    /// artwork settings (`ArtworkCache::from_env`). The
+    /// other options carry state loaded elsewhere: `QualityProfile::from_env`,
+    /// `EpisodeCatalog::from_env`, `DetailsCache::from_env`, `SubtitleCache::from_env` and
+    /// `AnimeMapping::from_env`.
+    pub fn from_env(
+        quality: QualityProfile,
+        episodes: Option<Arc<EpisodeCatalog>>,
+        details: Option<Arc<DetailsCache>>,
+        subtitles: Option<Arc<SubtitleCache>>,
+        anime: Option<Arc<AnimeMapping>>,
+    ) -> Self {
+        Self {
+            quality,
+            movie_versions: env_flag("MOVIE_VERSIONS"),
+            naming: naming_from_env(),
+            episodes,
+            rename_episodes: env_flag("RENAME_EPISODES"),
+            episode_nfo: env_flag("EPISODE_NFO"),
+            details,
+            full_nfo: env_flag("FULL_NFO"),
+            artwork: ArtworkCache::from_env().map(Arc::new),
+            subtitles,
+            anime,
+            extras: env_flag("EXTRAS"),
+            disc_folders: env_flag("DISC_FOLDERS"),
+            collections: env_flag("COLLECTIONS"),
+            collection_folders: env_flag("COLLECTION_FOLDERS"),
+        }
+    }
+}
+
+/// Whether a boolean environment variable is set to `1`, `true` or `yes`.
+pub(crate) fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
 pub struct DebridVfs {
     pub root: VfsNode,
     pub timestamps: HashMap<String, SystemTime>,
+    pub options: Arc<VfsOptions>,
 }
 
 impl Default for DebridVfs {
@@ -198,6 +412,11 @@ impl Default for DebridVfs {
 
 impl DebridVfs {
     pub fn new() -> Self {
+        Self::with_options(Arc::new(VfsOptions::default()))
+    }
+
+    /// An empty VFS that later rebuilds will configure with `options`.
+    pub fn with_options(options: Arc<VfsOptions>) -> Self {
         let mut children = BTreeMap::new();
         children.insert(
             "Movies".to_string(),
@@ -215,10 +434,19 @@ impl DebridVfs {
         Self {
             root: VfsNode::Directory { children },
             timestamps: HashMap::new(),
+            options,
         }
     }
 
+    /// Build with the default options.
     pub fn build(torrents: Vec<(TorrentInfo, MediaMetadata)>) -> Self {
+        Self::build_with(torrents, Arc::new(VfsOptions::default()))
+    }
+
+    pub fn build_with(
+        torrents: Vec<(TorrentInfo, MediaMetadata)>,
+        options: Arc<VfsOptions>,
+    ) -> Self {
         let mut movies_nodes = BTreeMap::new();
         let mut shows_nodes = BTreeMap::new();
         let mut timestamps: HashMap<String, SystemTime> = HashMap::new();
@@ -238,27 +466,47 @@ impl DebridVfs {
                 .then_with(|| a.external_id.cmp(&b.external_id))
         });
 
+        // Movie folders per collection name, for the `Collections` tree
+        let mut collections: BTreeMap<String, Vec<String>> = BTreeMap::new();
         let mut used_movie_names: HashMap<String, u32> = HashMap::new();
         let mut used_show_names: HashMap<String, u32> = HashMap::new();
 
         for metadata in sorted_metadata {
             let mut torrents = media_groups.remove(&metadata).unwrap();
-            // Sort torrents by size descending to pick the best/largest one
-            torrents.sort_by_key(|t| std::cmp::Reverse(t.bytes));
-
-            let base_name = sanitize_filename(&metadata.title);
+            // Best first: releases the quality profile accepts, then its preferences, then
+            // size (the largest torrent wins when the profile has no opinion).
+            let quality = &options.quality;
+            torrents.sort_by_cached_key(|t| {
+                std::cmp::Reverse((
+                    quality.accepts(&t.filename, t.bytes),
+                    quality.score(&t.filename),
+                    t.bytes,
+                ))
+            });
+
+            let naming = &options.naming;
+            let details = options.details.as_ref().and_then(|d| d.get(&metadata));
+            // Without full NFO mode, collection mode still writes the movie's `<set>`
+            let set_only = details
+                .as_ref()
+                .filter(|_| options.collections && !options.full_nfo)
+                .map(|d| TitleDetails {
+                    collection: d.collection.clone(),
+                    ..Default::default()
+                });
+            let nfo_details = details
+                .as_ref()
+                .filter(|_| options.full_nfo)
+                .or(set_only.as_ref());
+            let base_name = naming.folder_name(&metadata);
 
             let (used_names, nodes) = match metadata.media_type {
                 MediaType::Movie => (&mut used_movie_names, &mut movies_nodes),
                 MediaType::Show => (&mut used_show_names, &mut shows_nodes),
             };
 
-            let folder_name = if let Some(id) = &metadata.external_id {
-                if let Some((source, raw_id)) = id.split_once(':') {
-                    format!("{} [{}id-{}]", base_name, source, raw_id)
-                } else {
-                    format!("{} [id={}]", base_name, id)
-                }
+            let folder_name = if metadata.external_id.is_some() {
+                base_name
             } else {
                 let count = used_names.entry(base_name.clone()).or_insert(0);
                 let name = if *count == 0 {
@@ -269,7 +517,7 @@ impl DebridVfs {
                 *count += 1;
                 name
             };
-            // Sanitize the whole folder name: the `[<source>id-<id>]` suffix is derived from
+            // Sanitize the whole folder name: the id hint (`[<source>id-<id>]`) is derived from
             // `external_id`, so a crafted/cached value containing a path separator must not be
             // able to introduce one into the directory path. Idempotent for normal ids.
             let folder_name = sanitize_filename(&folder_name);
@@ -297,26 +545,82 @@ impl DebridVfs {
 
             match metadata.media_type {
                 MediaType::Movie => {
+                    let fetched = options.subtitles.as_deref();
                     let mut children = BTreeMap::new();
-                    // For movies, only take the largest torrent to avoid duplicates
-                    if let Some(torrent) = torrents.first() {
-                        let torrent_ts = parse_rd_date(&torrent.added);
-                        Self::add_torrent_files(&mut children, torrent, None);
-                        if !children.is_empty() {
-                            let prefix = format!("Movies/{}", folder_name);
-                            for name in children.keys() {
-                                timestamps.insert(format!("{}/{}", prefix, name), torrent_ts);
+                    let prefix = format!("Movies/{}", folder_name);
+                    let mut movie_ts = UNIX_EPOCH;
+                    if options.movie_versions && torrents.len() > 1 {
+                        // Every distinct release becomes a selectable version
+                        let stem = naming
+                            .movie_stem(&metadata)
+                            .unwrap_or_else(|| folder_name.clone());
+                        for (name, torrent_ts) in Self::add_movie_versions(
+                            &mut children,
+                            &torrents,
+                            &stem,
+                            options.disc_folders,
+                            fetched,
+                        ) {
+                            timestamps.insert(format!("{}/{}", prefix, name), torrent_ts);
+                            movie_ts = movie_ts.max(torrent_ts);
+                        }
+                    } else if let Some(torrent) = torrents.first() {
+                        // Otherwise only take the best torrent to avoid duplicates
+                        movie_ts = parse_rd_date(&torrent.added);
+                        let feature = disc_feature(torrent);
+                        match naming.movie_stem(&metadata) {
+                            // A full disc exposes its main feature under the title's name
+                            // rather than a stream number like `00000.m2ts`.
+                            stem if !feature.is_empty() => {
+                                let stem = stem.unwrap_or_else(|| title_with_year(&metadata));
+                                for name in Self::add_disc(
+                                    &mut children,
+                                    torrent,
+                                    feature,
+                                    &stem,
+                                    options.disc_folders,
+                                ) {
+                                    timestamps.insert(format!("{}/{}", prefix, name), movie_ts);
+                                }
+                            }
+                            // A lone video file takes the title's name; multi-part releases
+                            // keep their own names so the parts stay distinguishable.
+                            Some(stem) if streamable_files(torrent).len() == 1 => {
+                                Self::add_movie_file(&mut children, torrent, &stem, fetched);
+                            }
+                            _ => Self::add_torrent_files(&mut children, torrent, None, fetched),
+                        }
+                        for name in children.keys() {
+                            timestamps.insert(format!("{}/{}", prefix, name), movie_ts);
+                        }
+                    }
+                    if !children.is_empty() {
+                        if let Some((nfo_name, content)) = naming.nfo(&metadata, nfo_details) {
+                            children.insert(nfo_name.to_string(), VfsNode::VirtualFile { content });
+                            timestamps.insert(format!("{}/{}", prefix, nfo_name), movie_ts);
+                        }
+                        if let (Some(_), Some(details)) = (&options.artwork, &details) {
+                            for name in Self::add_artwork(&mut children, details) {
+                                timestamps.insert(format!("{}/{}", prefix, name), movie_ts);
                             }
-                            let nfo_content = Self::generate_nfo(&metadata);
-                            children.insert(
-                                "movie.nfo".to_string(),
-                                VfsNode::VirtualFile {
-                                    content: nfo_content,
-                                },
-                            );
-                            timestamps.insert(format!("{}/movie.nfo", prefix), torrent_ts);
-                            nodes.insert(folder_name, VfsNode::Directory { children });
                         }
+                        // Extras come from the best release only, like the movie itself
+                        if let Some(torrent) = torrents.first().filter(|_| options.extras) {
+                            for name in Self::add_extras(&mut children, torrent) {
+                                timestamps.insert(format!("{}/{}", prefix, name), movie_ts);
+                            }
+                        }
+                        if let Some(collection) = details
+                            .as_ref()
+                            .and_then(|d| d.collection.as_ref())
+                            .filter(|_| options.collection_folders)
+                        {
+                            collections
+                                .entry(sanitize_filename(&collection.name))
+                                .or_default()
+                                .push(folder_name.clone());
+                        }
+                        nodes.insert(folder_name, VfsNode::Directory { children });
                     }
                 }
                 MediaType::Show => {
@@ -325,33 +629,71 @@ impl DebridVfs {
                     let mut show_max_ts = UNIX_EPOCH;
 
                     // For shows, we process all torrents (e.g. different seasons)
-                    // Torrents are sorted by size descending, so larger (better quality) torrents
-                    // are processed first. If a file with the same name already exists in a season
+                    // Torrents are sorted best first (quality profile, then size), so preferred
+                    // torrents are processed first. If a file with the same name already exists in a season
                     // directory, skip it to avoid creating (1)/(2) duplicates from repair replacements.
-                    for torrent in torrents {
+                    // Renamed episodes are de-duplicated by episode number instead.
+                    let mut renamed_episodes = HashSet::new();
+                    for torrent in &torrents {
                         let torrent_ts = parse_rd_date(&torrent.added);
                         if torrent_ts > show_max_ts {
                             show_max_ts = torrent_ts;
                         }
-                        let selected_count =
-                            torrent.files.iter().filter(|f| f.selected == 1).count();
-                        if !torrent.links.is_empty() && selected_count != torrent.links.len() {
-                            tracing::warn!(
-                                "Torrent '{}': selected file count ({}) != link count ({})",
-                                torrent.filename,
-                                selected_count,
-                                torrent.links.len()
-                            );
-                        }
-                        let mut link_idx = 0;
-                        for file in &torrent.files {
-                            if file.selected == 1 {
-                                if is_video_file(&file.path) {
-                                    let link = torrent.links.get(link_idx).cloned();
-                                    if link.is_some() || torrent.links.is_empty() {
-                                        let filename =
-                                            file.path.split('/').next_back().unwrap_or(&file.path);
-                                        let season = SEASON_RE
+                        let videos = streamable_files(torrent);
+                        let only_video = videos.len() == 1;
+                        for (file, link) in videos {
+                            let filename = file.path.split('/').next_back().unwrap_or(&file.path);
+                            let range = parse_episode_range(filename);
+                            let anime_mapped =
+                                options.anime.as_ref().filter(|_| range.is_none()).and_then(
+                                    |anime| {
+                                        let (season, episode) = anime.map(
+                                            metadata.tmdb_id()?,
+                                            parse_absolute_episode(filename)?,
+                                        )?;
+                                        Some((season, episode, episode))
+                                    },
+                                );
+                            let numbered = range.or(anime_mapped);
+                            let catalog_episode = |season: u32, episode: u32| {
+                                let catalog = options.episodes.as_ref()?;
+                                catalog.episode(metadata.tmdb_id()?, season, episode)
+                            };
+                            // Episodes are renamed canonically when asked to, or when anime
+                            // mode placed an absolute number the original name can't convey.
+                            let renumbered = numbered
+                                .filter(|_| options.rename_episodes || anime_mapped.is_some());
+                            let (season, name) = match renumbered {
+                                Some((season, first, last)) => {
+                                    // Another release already provides these episodes
+                                    let added = (first..=last)
+                                        .filter(|&e| renamed_episodes.insert((season, e)))
+                                        .count();
+                                    if added == 0 {
+                                        continue;
+                                    }
+                                    let titles: Vec<String> = (first..=last)
+                                        .filter_map(|e| catalog_episode(season, e))
+                                        .map(|e| e.name)
+                                        .filter(|name| !name.trim().is_empty())
+                                        .collect();
+                                    let name = canonical_episode_name(
+                                        &metadata.title,
+                                        season,
+                                        first,
+                                        last,
+                                        Some(titles.join(" & "))
+                                            .filter(|t| !t.is_empty())
+                                            .as_deref(),
+                                        filename,
+                                    );
+                                    (season, name)
+                                }
+                                None => {
+                                    let season = match range {
+                                        Some((season, _, _)) => season,
+                                        None if is_special(&file.path) => 0,
+                                        None => SEASON_RE
                                             .captures(filename)
                                             .and_then(|cap| {
                                                 cap.get(1)
@@ -360,59 +702,105 @@ impl DebridVfs {
                                                     .or_else(|| cap.get(4))
                                             })
                                             .and_then(|m| m.as_str().parse::<u32>().ok())
-                                            .unwrap_or(1);
-
-                                        let season_name = format!("Season {:02}", season);
-                                        let season_dir = show_children
-                                            .entry(season_name.clone())
-                                            .or_insert_with(|| VfsNode::Directory {
-                                                children: BTreeMap::new(),
-                                            });
-
-                                        if let VfsNode::Directory {
-                                            children: season_children,
-                                        } = season_dir
-                                        {
-                                            // Skip if this episode already exists (from a larger/earlier torrent)
-                                            if season_children.contains_key(filename) {
-                                                link_idx += 1;
-                                                continue;
-                                            }
-                                            let strm_name = Self::add_path_to_tree(
-                                                season_children,
-                                                filename,
-                                                file.bytes,
-                                                FileLocator {
-                                                    hash: torrent.hash.clone(),
-                                                    torrent_id: torrent.id.clone(),
-                                                    file_id: file.id,
-                                                    file_path: file.path.clone(),
-                                                    link,
-                                                },
-                                            );
-                                            timestamps.insert(
-                                                format!(
-                                                    "{}/{}/{}",
-                                                    show_prefix, season_name, strm_name
-                                                ),
-                                                torrent_ts,
-                                            );
-                                        }
-                                    }
+                                            .unwrap_or(1),
+                                    };
+                                    let name = naming
+                                        .episode_name(&metadata, filename)
+                                        .unwrap_or_else(|| filename.to_string());
+                                    (season, name)
+                                }
+                            };
+
+                            let season_name = format!("Season {:02}", season);
+                            let season_dir = show_children
+                                .entry(season_name.clone())
+                                .or_insert_with(|| VfsNode::Directory {
+                                    children: BTreeMap::new(),
+                                });
+
+                            if let VfsNode::Directory {
+                                children: season_children,
+                            } = season_dir
+                            {
+                                // Skip if this episode already exists (from a larger/earlier torrent)
+                                if season_children.contains_key(&name) {
+                                    continue;
+                                }
+                                let strm_name = Self::add_path_to_tree(
+                                    season_children,
+                                    &name,
+                                    file.bytes,
+                                    FileLocator {
+                                        hash: torrent.hash.clone(),
+                                        torrent_id: torrent.id.clone(),
+                                        file_id: file.id,
+                                        file_path: file.path.clone(),
+                                        link,
+                                    },
+                                );
+                                timestamps.insert(
+                                    format!("{}/{}/{}", show_prefix, season_name, strm_name),
+                                    torrent_ts,
+                                );
+                                for subtitle in Self::add_subtitles(
+                                    season_children,
+                                    torrent,
+                                    file,
+                                    &strm_name,
+                                    only_video,
+                                    options.subtitles.as_deref(),
+                                ) {
+                                    timestamps.insert(
+                                        format!("{}/{}/{}", show_prefix, season_name, subtitle),
+                                        torrent_ts,
+                                    );
+                                }
+
+                                // A multi-episode file needs an NFO listing the episodes it
+                                // covers, or Jellyfin shows only the first. Other episodes get
+                                // one when episode NFOs are enabled.
+                                let Some((season, first, last)) = numbered
+                                    .filter(|(_, first, last)| last > first || options.episode_nfo)
+                                else {
+                                    continue;
+                                };
+                                let episodes: Vec<(u32, Option<CatalogEpisode>)> = (first..=last)
+                                    .map(|e| (e, catalog_episode(season, e)))
+                                    .collect();
+                                if let Some(content) =
+                                    naming.episode_nfo(&metadata, season, &episodes)
+                                {
+                                    let stem = strm_name
+                                        .rfind('.')
+                                        .map_or(strm_name.as_str(), |i| &strm_name[..i]);
+                                    let nfo_name = format!("{}.nfo", stem);
+                                    timestamps.insert(
+                                        format!("{}/{}/{}", show_prefix, season_name, nfo_name),
+                                        torrent_ts,
+                                    );
+                                    season_children
+                                        .insert(nfo_name, VfsNode::VirtualFile { content });
                                 }
-                                link_idx += 1;
                             }
                         }
                     }
                     if !show_children.is_empty() {
-                        let nfo_content = Self::generate_nfo(&metadata);
-                        show_children.insert(
-                            "tvshow.nfo".to_string(),
-                            VfsNode::VirtualFile {
-                                content: nfo_content,
-                            },
-                        );
-                        timestamps.insert(format!("{}/tvshow.nfo", show_prefix), show_max_ts);
+                        if let Some((nfo_name, content)) = naming.nfo(&metadata, nfo_details) {
+                            show_children
+                                .insert(nfo_name.to_string(), VfsNode::VirtualFile { content });
+                            timestamps.insert(format!("{}/{}", show_prefix, nfo_name), show_max_ts);
+                        }
+                        if let (Some(_), Some(details)) = (&options.artwork, &details) {
+                            for name in Self::add_artwork(&mut show_children, details) {
+                                timestamps.insert(format!("{}/{}", show_prefix, name), show_max_ts);
+                            }
+                        }
+                        for torrent in torrents.iter().filter(|_| options.extras) {
+                            let torrent_ts = parse_rd_date(&torrent.added);
+                            for name in Self::add_extras(&mut show_children, torrent) {
+                                timestamps.insert(format!("{}/{}", show_prefix, name), torrent_ts);
+                            }
+                        }
                         nodes.insert(
                             folder_name,
                             VfsNode::Directory {
@@ -425,6 +813,16 @@ impl DebridVfs {
         }
 
         let mut root_children = BTreeMap::new();
+        if options.collection_folders {
+            let collection_nodes =
+                Self::collection_tree(&collections, &movies_nodes, &mut timestamps);
+            root_children.insert(
+                "Collections".to_string(),
+                VfsNode::Directory {
+                    children: collection_nodes,
+                },
+            );
+        }
         root_children.insert(
             "Movies".to_string(),
             VfsNode::Directory {
@@ -444,7 +842,98 @@ impl DebridVfs {
         // Compute directory timestamps bottom-up (max of children)
         Self::compute_dir_timestamps(&root, "", &mut timestamps);
 
-        Self { root, timestamps }
+        Self {
+            root,
+            timestamps,
+            options,
+        }
+    }
+
+    /// `<Name>/<movie folder>` links to the movie folders in each collection, timestamped
+    /// with the newest file of the folder they point to.
+    fn collection_tree(
+        collections: &BTreeMap<String, Vec<String>>,
+        movies: &BTreeMap<String, VfsNode>,
+        timestamps: &mut HashMap<String, SystemTime>,
+    ) -> BTreeMap<String, VfsNode> {
+        let mut nodes = BTreeMap::new();
+        for (name, folders) in collections {
+            let mut children = BTreeMap::new();
+            for folder in folders.iter().filter(|f| movies.contains_key(*f)) {
+                let target = format!("Movies/{}", folder);
+                let prefix = format!("{}/", target);
+                let newest = timestamps
+                    .iter()
+                    .filter(|(path, _)| path.starts_with(&prefix))
+                    .map(|(_, ts)| *ts)
+                    .max()
+                    .unwrap_or(UNIX_EPOCH);
+                timestamps.insert(format!("Collections/{}/{}", name, folder), newest);
+                children.insert(folder.clone(), VfsNode::Link { target });
+            }
+            nodes.insert(name.clone(), VfsNode::Directory { children });
+        }
+        nodes
+    }
+
+    /// The node at `path` (relative to the root) and its canonical path, following a
+    /// collection link to the movie folder it lists. `None` for missing paths and `..`.
+    pub fn lookup(&self, path: &str) -> Option<(&VfsNode, String)> {
+        let mut current = &self.root;
+        let mut resolved = String::new();
+        for component in path.split('/') {
+            if component.is_empty() || component == "." {
+                continue;
+            }
+            if component == ".." {
+                return None;
+            }
+            let VfsNode::Directory { children } = current else {
+                return None;
+            };
+            current = children.get(component)?;
+            if !resolved.is_empty() {
+                resolved.push('/');
+            }
+            resolved.push_str(component);
+            if let VfsNode::Link { target } = current {
+                // Links only point at movie folders, so they are followed once.
+                current = self.lookup(target).map(|(node, _)| node)?;
+                resolved = target.clone();
+            }
+        }
+        Some((current, resolved))
+    }
+
+    /// Map each torrent id to the VFS paths of the media files it provides.
+    pub fn torrent_paths(&self) -> HashMap<String, Vec<String>> {
+        fn walk(node: &VfsNode, prefix: &str, out: &mut HashMap<String, Vec<String>>) {
+            match node {
+                VfsNode::Directory { children } => {
+                    for (name, child) in children {
+                        let child_path = if prefix.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{}/{}", prefix, name)
+                        };
+                        walk(child, &child_path, out);
+                    }
+                }
+                VfsNode::MediaFile { locator, .. } => {
+                    out.entry(locator.torrent_id.clone())
+                        .or_default()
+                        .push(prefix.to_string());
+                }
+                // Collection links list movies already counted under `Movies/`.
+                VfsNode::VirtualFile { .. }
+                | VfsNode::Artwork { .. }
+                | VfsNode::CachedFile { .. }
+                | VfsNode::Link { .. } => {}
+            }
+        }
+        let mut out = HashMap::new();
+        walk(&self.root, "", &mut out);
+        out
     }
 
     /// Walk the tree and insert directory timestamps as the max of their children.
@@ -481,7 +970,49 @@ impl DebridVfs {
         }
     }
 
-    fn generate_nfo(metadata: &MediaMetadata) -> Vec<u8> {
+    /// `<episodedetails>` NFO for a file covering `episodes` (number and TMDB details, when
+    /// cached) of `season`: one element per episode, which is how Jellyfin and Kodi
+    /// describe multi-episode files.
+    pub(crate) fn generate_episode_nfo(
+        metadata: &MediaMetadata,
+        season: u32,
+        episodes: &[(u32, Option<CatalogEpisode>)],
+    ) -> Vec<u8> {
+        let mut nfo =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\" ?>\n");
+        for (episode, details) in episodes {
+            nfo.push_str("<episodedetails>\n");
+            let title = details.as_ref().map(|d| d.name.as_str()).unwrap_or("");
+            if !title.trim().is_empty() {
+                nfo.push_str(&format!("  <title>{}</title>\n", xml_escape(title)));
+            }
+            nfo.push_str(&format!(
+                "  <showtitle>{}</showtitle>\n",
+                xml_escape(&metadata.title)
+            ));
+            nfo.push_str(&format!("  <season>{}</season>\n", season));
+            nfo.push_str(&format!("  <episode>{}</episode>\n", episode));
+            if let Some(details) = details {
+                if let Some(aired) = &details.air_date {
+                    nfo.push_str(&format!("  <aired>{}</aired>\n", xml_escape(aired)));
+                }
+                if let Some(id) = details.tmdb_id {
+                    nfo.push_str(&format!(
+                        "  <uniqueid type=\"tmdb\" default=\"true\">{}</uniqueid>\n",
+                        id
+                    ));
+                }
+            }
+            nfo.push_str("  <lockdata>false</lockdata>\n");
+            nfo.push_str("</episodedetails>\n");
+        }
+        nfo.into_bytes()
+    }
+
+    pub(crate) fn generate_nfo(
+        metadata: &MediaMetadata,
+        details: Option<&TitleDetails>,
+    ) -> Vec<u8> {
         let tag = match metadata.media_type {
             MediaType::Movie => "movie",
             MediaType::Show => "tvshow",
@@ -534,12 +1065,22 @@ impl DebridVfs {
                         xml_escape(path),
                         xml_escape(id)
                     ));
+                } else if source == "tvdb" || source == "imdb" {
+                    nfo.push_str(&format!(
+                        "  <{}id>{}</{}id>\n",
+                        source,
+                        xml_escape(id),
+                        source
+                    ));
                 }
             }
         }
 
-        // Don't include plot/outline - let Jellyfin fetch from TMDB
+        // Without full details, don't include plot/outline - let Jellyfin fetch from TMDB
         // Including placeholder text causes duplication in Jellyfin UI
+        if let Some(details) = details {
+            Self::push_details(&mut nfo, details);
+        }
 
         // Lockdata set to false allows Jellyfin to fetch full metadata from TMDB
         nfo.push_str("  <lockdata>false</lockdata>\n");
@@ -551,49 +1092,394 @@ impl DebridVfs {
         nfo.into_bytes()
     }
 
+    fn push_details(nfo: &mut String, details: &TitleDetails) {
+        let mut element = |tag: &str, value: &str| {
+            nfo.push_str(&format!("  <{0}>{1}</{0}>\n", tag, xml_escape(value)));
+        };
+        if let Some(plot) = &details.plot {
+            element("plot", plot);
+        }
+        if let Some(tagline) = &details.tagline {
+            element("tagline", tagline);
+        }
+        if let Some(runtime) = details.runtime {
+            element("runtime", &runtime.to_string());
+        }
+        if let Some(certification) = &details.certification {
+            element("mpaa", certification);
+        }
+        for genre in &details.genres {
+            element("genre", genre);
+        }
+        for studio in &details.studios {
+            element("studio", studio);
+        }
+        if let Some((rating, votes)) = details.rating {
+            nfo.push_str(&format!(
+                "  <ratings>\n    <rating name=\"themoviedb\" max=\"10\" default=\"true\">\n      <value>{:.1}</value>\n      <votes>{}</votes>\n    </rating>\n  </ratings>\n",
+                rating, votes
+            ));
+        }
+        if let Some(poster) = &details.poster_url {
+            nfo.push_str(&format!(
+                "  <thumb aspect=\"poster\">{}</thumb>\n",
+                xml_escape(poster)
+            ));
+        }
+        if let Some(fanart) = &details.fanart_url {
+            nfo.push_str(&format!(
+                "  <fanart>\n    <thumb>{}</thumb>\n  </fanart>\n",
+                xml_escape(fanart)
+            ));
+        }
+        if let Some(collection) = &details.collection {
+            nfo.push_str(&format!(
+                "  <set tmdbcolid=\"{}\">\n    <name>{}</name>\n  </set>\n",
+                collection.id,
+                xml_escape(&collection.name)
+            ));
+        }
+        for (order, actor) in details.cast.iter().enumerate() {
+            nfo.push_str("  <actor>\n");
+            nfo.push_str(&format!("    <name>{}</name>\n", xml_escape(&actor.name)));
+            if let Some(role) = &actor.role {
+                nfo.push_str(&format!("    <role>{}</role>\n", xml_escape(role)));
+            }
+            nfo.push_str(&format!("    <order>{}</order>\n", order));
+            if let Some(thumb) = &actor.thumb_url {
+                nfo.push_str(&format!("    <thumb>{}</thumb>\n", xml_escape(thumb)));
+            }
+            nfo.push_str("  </actor>\n");
+        }
+    }
+
+    /// Add `poster.jpg` and `fanart.jpg` to a title folder, plus `seasonXX-poster.jpg`
+    /// (`season-specials-poster.jpg` for season 0) for each season folder it holds.
+    /// Returns the names added.
+    fn add_artwork(
+        children: &mut BTreeMap<String, VfsNode>,
+        details: &TitleDetails,
+    ) -> Vec<String> {
+        let mut images: Vec<(String, &String)> = Vec::new();
+        if let Some(url) = &details.poster_url {
+            images.push(("poster.jpg".to_string(), url));
+        }
+        if let Some(url) = &details.fanart_url {
+            images.push(("fanart.jpg".to_string(), url));
+        }
+        for (season, url) in &details.season_posters {
+            if !children.contains_key(&format!("Season {:02}", season)) {
+                continue;
+            }
+            let name = match season {
+                0 => "season-specials-poster.jpg".to_string(),
+                n => format!("season{:02}-poster.jpg", n),
+            };
+            images.push((name, url));
+        }
+        images
+            .into_iter()
+            .map(|(name, url)| {
+                children.insert(name.clone(), VfsNode::Artwork { url: url.clone() });
+                name
+            })
+            .collect()
+    }
+
+    /// Add a full-disc torrent's main feature as `<stem>.<ext>` (`<stem> - partN.<ext>` for
+    /// a DVD title set split over several VOBs) and, with `disc_folders`, the disc folder
+    /// holding it. Returns the paths added: the feature's files, then the disc folder's.
+    fn add_disc(
+        destination: &mut BTreeMap<String, VfsNode>,
+        torrent: &TorrentInfo,
+        feature: Vec<(&TorrentFile, Option<String>)>,
+        stem: &str,
+        disc_folders: bool,
+    ) -> Vec<String> {
+        let root = feature.first().and_then(|(f, _)| disc_root(&f.path));
+        let parts = feature.len();
+        let mut added = Vec::new();
+        for (part, (file, link)) in feature.into_iter().enumerate() {
+            let ext = file.path.rfind('.').map_or("", |i| &file.path[i..]);
+            let name = match parts {
+                1 => format!("{}{}", stem, ext.to_lowercase()),
+                _ => format!("{} - part{}{}", stem, part + 1, ext.to_lowercase()),
+            };
+            let name = Self::add_path_to_tree(
+                destination,
+                &name,
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
+            );
+            added.push(name);
+        }
+        let Some(root) = root.filter(|_| disc_folders) else {
+            return added;
+        };
+        for (file, link) in selected_files(torrent, is_disc_file) {
+            // Only the disc the feature is on, from its BDMV/VIDEO_TS folder down
+            if disc_root(&file.path).map(|r| r.parent) != Some(root.parent) {
+                continue;
+            }
+            let relative = file.path[root.parent.len()..].trim_start_matches('/');
+            let name = Self::add_path_to_tree(
+                destination,
+                relative,
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
+            );
+            added.push(match relative.rfind('/') {
+                Some(i) => format!("{}/{}", &relative[..i], name),
+                None => name,
+            });
+        }
+        added
+    }
+
+    /// Add the torrent's extras to the title folder's Jellyfin extras folders, keeping
+    /// their file names. An extra whose name is already taken (by a better release) is
+    /// skipped. Returns the paths added, relative to the title folder.
+    fn add_extras(children: &mut BTreeMap<String, VfsNode>, torrent: &TorrentInfo) -> Vec<String> {
+        let mut added = Vec::new();
+        for (file, link) in extra_files(torrent) {
+            let Some(folder) = extra_folder(&file.path) else {
+                continue;
+            };
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            let exists = Self::directory_mut(children, folder)
+                .is_some_and(|extras| extras.contains_key(filename));
+            if exists {
+                continue;
+            }
+            let name = Self::add_path_to_tree(
+                children,
+                &format!("{}/{}", folder, filename),
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
+            );
+            added.push(format!("{}/{}", folder, name));
+        }
+        added
+    }
+
     fn add_torrent_files(
         destination: &mut BTreeMap<String, VfsNode>,
         torrent: &TorrentInfo,
         path_prefix: Option<&str>,
+        fetched: Option<&SubtitleCache>,
     ) {
-        let selected_count = torrent.files.iter().filter(|f| f.selected == 1).count();
-        if !torrent.links.is_empty() && selected_count != torrent.links.len() {
-            tracing::warn!(
-                "Torrent '{}': selected file count ({}) != link count ({})",
-                torrent.filename,
-                selected_count,
-                torrent.links.len()
+        let videos = streamable_files(torrent);
+        let only_video = videos.len() == 1;
+        for (file, link) in videos {
+            let filename = file.path.split('/').next_back().unwrap_or(&file.path);
+            let path = if let Some(prefix) = path_prefix {
+                format!("{}/{}", prefix, filename.trim_start_matches('/'))
+            } else {
+                filename.to_string()
+            };
+            let name = Self::add_path_to_tree(
+                destination,
+                &path,
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
             );
+            let parent = path.rfind('/').map(|i| &path[..i]);
+            let subtitles = match parent.and_then(|p| Self::directory_mut(destination, p)) {
+                Some(dir) => dir,
+                None => &mut *destination,
+            };
+            Self::add_subtitles(subtitles, torrent, file, &name, only_video, fetched);
         }
-        let mut link_idx = 0;
-        for file in &torrent.files {
-            if file.selected == 1 {
-                if is_video_file(&file.path) {
-                    let link = torrent.links.get(link_idx).cloned();
-                    if link.is_some() || torrent.links.is_empty() {
-                        let filename = file.path.split('/').next_back().unwrap_or(&file.path);
-                        let path = if let Some(prefix) = path_prefix {
-                            format!("{}/{}", prefix, filename.trim_start_matches('/'))
-                        } else {
-                            filename.to_string()
-                        };
-                        Self::add_path_to_tree(
-                            destination,
-                            &path,
-                            file.bytes,
-                            FileLocator {
-                                hash: torrent.hash.clone(),
-                                torrent_id: torrent.id.clone(),
-                                file_id: file.id,
-                                file_path: file.path.clone(),
-                                link,
-                            },
-                        );
-                    }
+    }
+
+    /// The directory at `path` below `root`, if it exists.
+    fn directory_mut<'a>(
+        root: &'a mut BTreeMap<String, VfsNode>,
+        path: &str,
+    ) -> Option<&'a mut BTreeMap<String, VfsNode>> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(root, |children, part| match children.get_mut(part) {
+                Some(VfsNode::Directory { children }) => Some(children),
+                _ => None,
+            })
+    }
+
+    /// Add the torrent's subtitle files that belong to `video` (exposed as `video_name`)
+    /// next to it, named `<video stem>.<lang>.<ext>`. The first subtitle to claim a name
+    /// wins. A video without any gets the subtitles `fetched` for it instead. Returns the
+    /// names added.
+    fn add_subtitles(
+        destination: &mut BTreeMap<String, VfsNode>,
+        torrent: &TorrentInfo,
+        video: &TorrentFile,
+        video_name: &str,
+        only_video: bool,
+        fetched: Option<&SubtitleCache>,
+    ) -> Vec<String> {
+        let mut added = Vec::new();
+        for (file, link) in subtitle_files(torrent) {
+            let Some(name) = subtitle_name(&video.path, video_name, &file.path, only_video) else {
+                continue;
+            };
+            if destination.contains_key(&name) {
+                continue;
+            }
+            destination.insert(
+                name.clone(),
+                VfsNode::MediaFile {
+                    file_size: file.bytes,
+                    locator: FileLocator {
+                        hash: torrent.hash.clone(),
+                        torrent_id: torrent.id.clone(),
+                        file_id: file.id,
+                        file_path: file.path.clone(),
+                        link,
+                    },
+                },
+            );
+            added.push(name);
+        }
+        if let Some(cache) = fetched.filter(|_| added.is_empty()) {
+            let stem = video_name
+                .rfind('.')
+                .map_or(video_name, |i| &video_name[..i]);
+            for (language, path, file_size) in cache.fetched(&torrent.hash, video.id) {
+                let name = format!("{}.{}.srt", stem, language);
+                if destination.contains_key(&name) {
+                    continue;
                 }
-                link_idx += 1;
+                destination.insert(name.clone(), VfsNode::CachedFile { path, file_size });
+                added.push(name);
             }
         }
+        added
+    }
+
+    /// Add the torrent's single video file as `<stem>.<ext>`.
+    fn add_movie_file(
+        destination: &mut BTreeMap<String, VfsNode>,
+        torrent: &TorrentInfo,
+        stem: &str,
+        fetched: Option<&SubtitleCache>,
+    ) {
+        for (file, link) in streamable_files(torrent) {
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            let ext = filename.rfind('.').map(|i| &filename[i..]).unwrap_or("");
+            let name = Self::add_path_to_tree(
+                destination,
+                &format!("{}{}", stem, ext),
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
+            );
+            Self::add_subtitles(destination, torrent, file, &name, true, fetched);
+        }
+    }
+
+    /// Add the main (largest) video file of each torrent as a Jellyfin multi-version file
+    /// named `<folder> - <label>.<ext>`, best torrent first. A torrent whose label repeats
+    /// an earlier one is the same version and is skipped. Disc releases contribute their
+    /// main feature the same way; with `disc_folders`, the first of them also brings its
+    /// disc folder (one `BDMV`/`VIDEO_TS` per movie folder). Returns the inserted names
+    /// with their torrent's timestamp.
+    fn add_movie_versions(
+        destination: &mut BTreeMap<String, VfsNode>,
+        torrents: &[TorrentInfo],
+        folder_name: &str,
+        disc_folders: bool,
+        fetched: Option<&SubtitleCache>,
+    ) -> Vec<(String, SystemTime)> {
+        let mut labels = HashSet::new();
+        let mut added = Vec::new();
+        let mut disc_folder_added = false;
+        for torrent in torrents {
+            let feature = disc_feature(torrent);
+            if !feature.is_empty() {
+                let label = version_label(&torrent.filename)
+                    .unwrap_or_else(|| format!("Version {}", labels.len() + 1));
+                if !labels.insert(label.clone()) {
+                    continue;
+                }
+                let torrent_ts = parse_rd_date(&torrent.added);
+                let with_folder = disc_folders && !disc_folder_added;
+                disc_folder_added |= with_folder;
+                for name in Self::add_disc(
+                    destination,
+                    torrent,
+                    feature,
+                    &format!("{} - {}", folder_name, label),
+                    with_folder,
+                ) {
+                    added.push((name, torrent_ts));
+                }
+                continue;
+            }
+            let Some((file, link)) = streamable_files(torrent)
+                .into_iter()
+                .max_by_key(|(f, _)| f.bytes)
+            else {
+                continue;
+            };
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            let label = version_label(&torrent.filename)
+                .or_else(|| version_label(filename))
+                .unwrap_or_else(|| format!("Version {}", labels.len() + 1));
+            if !labels.insert(label.clone()) {
+                continue;
+            }
+            let ext = filename.rfind('.').map(|i| &filename[i..]).unwrap_or("");
+            let name = Self::add_path_to_tree(
+                destination,
+                &format!("{} - {}{}", folder_name, label, ext),
+                file.bytes,
+                FileLocator {
+                    hash: torrent.hash.clone(),
+                    torrent_id: torrent.id.clone(),
+                    file_id: file.id,
+                    file_path: file.path.clone(),
+                    link,
+                },
+            );
+            let torrent_ts = parse_rd_date(&torrent.added);
+            let only_video = streamable_files(torrent).len() == 1;
+            for subtitle in
+                Self::add_subtitles(destination, torrent, file, &name, only_video, fetched)
+            {
+                added.push((subtitle, torrent_ts));
+            }
+            added.push((name, torrent_ts));
+        }
+        added
     }
 
     /// Insert a video file into the tree as a MediaFile node. Returns the final filename.
@@ -650,10 +1536,161 @@ impl DebridVfs {
     }
 }
 
+/// Selected video files of `torrent` paired with their restricted link. Links are paired
+/// by position among selected files (Real-Debrid); a file whose link is missing is skipped
+/// unless the provider uses no links at all (TorBox).
+pub(crate) fn streamable_files(torrent: &TorrentInfo) -> Vec<(&TorrentFile, Option<String>)> {
+    let selected_count = torrent.files.iter().filter(|f| f.selected == 1).count();
+    if !torrent.links.is_empty() && selected_count != torrent.links.len() {
+        tracing::warn!(
+            "Torrent '{}': selected file count ({}) != link count ({})",
+            torrent.filename,
+            selected_count,
+            torrent.links.len()
+        );
+    }
+    selected_files(torrent, is_video_file)
+}
+
+/// Selected subtitle files of `torrent` paired with their restricted link, like
+/// `streamable_files`.
+pub(crate) fn subtitle_files(torrent: &TorrentInfo) -> Vec<(&TorrentFile, Option<String>)> {
+    selected_files(torrent, is_subtitle_file)
+}
+
+/// Where a file sits on a disc: the path up to its disc's `BDMV` or `VIDEO_TS` folder
+/// (`parent`), and which of the two that is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DiscRoot<'a> {
+    parent: &'a str,
+    blu_ray: bool,
+}
+
+/// The disc a torrent file belongs to, if it is inside a `BDMV` or `VIDEO_TS` folder.
+fn disc_root(path: &str) -> Option<DiscRoot<'_>> {
+    let mut start = 0;
+    for part in path.split('/') {
+        let end = start + part.len();
+        // The folder itself, not a file named like it
+        if end < path.len() {
+            let blu_ray = part.eq_ignore_ascii_case("BDMV");
+            if blu_ray || part.eq_ignore_ascii_case("VIDEO_TS") {
+                return Some(DiscRoot {
+                    parent: &path[..start],
+                    blu_ray,
+                });
+            }
+        }
+        start = end + 1;
+    }
+    None
+}
+
+fn is_disc_file(path: &str) -> bool {
+    disc_root(path).is_some()
+}
+
+/// DVD title set and part of a `VTS_01_1.VOB`-style file. Part 0 is the title set's menu.
+static VOB_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^VTS_(\d{2})_(\d)\.VOB$").unwrap());
+
+/// Title set (disc parent and number) and part of a DVD feature VOB; `None` for menus
+/// and other files.
+fn vob_part(path: &str) -> Option<((&str, u32), u32)> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    let cap = VOB_RE.captures(filename)?;
+    let (set, part) = (cap[1].parse::<u32>().ok()?, cap[2].parse::<u32>().ok()?);
+    let parent = disc_root(path)?.parent;
+    (part != 0).then_some(((parent, set), part))
+}
+
+/// The main feature of a full-disc torrent, as the files to play in order: the largest
+/// Blu-ray stream (`BDMV/STREAM/*.m2ts`), or else the DVD title set with the most data,
+/// part by part. Empty when the torrent holds no disc structure. MPLS playlists aren't
+/// read (that would mean downloading them), so a feature split over several streams or
+/// a seamless-branching disc exposes only its largest stream. VOBs count as video only
+/// here, so DVD menus never pass for episodes or movie files elsewhere.
+pub(crate) fn disc_feature(torrent: &TorrentInfo) -> Vec<(&TorrentFile, Option<String>)> {
+    let videos = selected_files(torrent, is_disc_file);
+    let largest_stream = videos
+        .iter()
+        .filter(|(f, _)| disc_root(&f.path).is_some_and(|root| root.blu_ray))
+        .filter(|(f, _)| is_video_file(&f.path))
+        .max_by_key(|(f, _)| f.bytes);
+    if let Some(stream) = largest_stream {
+        return vec![stream.clone()];
+    }
+
+    let mut sizes: BTreeMap<(&str, u32), u64> = BTreeMap::new();
+    for (file, _) in &videos {
+        if let Some((title_set, _)) = vob_part(&file.path) {
+            *sizes.entry(title_set).or_default() += file.bytes;
+        }
+    }
+    let Some(main) = sizes
+        .into_iter()
+        .max_by_key(|(_, bytes)| *bytes)
+        .map(|(set, _)| set)
+    else {
+        return Vec::new();
+    };
+    let mut parts: Vec<_> = videos
+        .into_iter()
+        .filter(|(f, _)| vob_part(&f.path).is_some_and(|(set, _)| set == main))
+        .collect();
+    parts.sort_by_key(|(f, _)| vob_part(&f.path).map(|(_, part)| part));
+    parts
+}
+
+/// Selected extras (see `extra_folder`) of `torrent` paired with their restricted link,
+/// like `streamable_files`.
+pub(crate) fn extra_files(torrent: &TorrentInfo) -> Vec<(&TorrentFile, Option<String>)> {
+    selected_files(torrent, is_extra_file)
+}
+
+pub(crate) fn selected_files(
+    torrent: &TorrentInfo,
+    keep: fn(&str) -> bool,
+) -> Vec<(&TorrentFile, Option<String>)> {
+    torrent
+        .files
+        .iter()
+        .filter(|f| f.selected == 1)
+        .enumerate()
+        .filter(|(_, f)| keep(&f.path))
+        .filter_map(|(link_idx, f)| {
+            let link = torrent.links.get(link_idx).cloned();
+            (link.is_some() || torrent.links.is_empty()).then_some((f, link))
+        })
+        .collect()
+}
+
+/// `Show - S01E02 - Title.ext` (or `Show - S01E02.ext` without a title, and
+/// `Show - S01E01-E02 - ...` for a multi-episode file), keeping the extension of `filename`.
+fn canonical_episode_name(
+    show: &str,
+    season: u32,
+    first: u32,
+    last: u32,
+    title: Option<&str>,
+    filename: &str,
+) -> String {
+    let ext = filename.rfind('.').map(|i| &filename[i..]).unwrap_or("");
+    let mut name = format!("{} - S{:02}E{:02}", sanitize_filename(show), season, first);
+    if last > first {
+        name.push_str(&format!("-E{:02}", last));
+    }
+    if let Some(title) = title.map(sanitize_filename).filter(|t| !t.is_empty()) {
+        name.push_str(" - ");
+        name.push_str(&title);
+    }
+    name + ext
+}
+
 /// Replace characters that are invalid in filenames or interpreted as path separators.
 /// Covers POSIX path separators and Windows-reserved characters (important for
 /// WebDAV clients accessing via rclone on Windows/SMB).
-fn sanitize_filename(name: &str) -> String {
+pub(crate) fn sanitize_filename(name: &str) -> String {
     let mut replaced = String::with_capacity(name.len());
     for c in name.chars() {
         match c {
@@ -727,6 +1764,56 @@ pub fn is_video_file(path: &str) -> bool {
     VIDEO_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
 }
 
+/// Regex matching sample clips, which are never exposed, not even as extras.
+static SAMPLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\bsamples?\b").unwrap());
+
+/// Regex matching a trailer in a filename, to tell trailers from other bonus videos.
+static TRAILER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\btrailers?\b").unwrap());
+
+/// Jellyfin extras folder for bonus content directories, checked case-insensitively.
+/// Directories without a dedicated Jellyfin folder go to `featurettes`.
+const EXTRAS_DIRS: &[(&str, &str)] = &[
+    ("trailers", "trailers"),
+    ("featurettes", "featurettes"),
+    ("behind the scenes", "behind the scenes"),
+    ("deleted scenes", "deleted scenes"),
+    ("extras", "featurettes"),
+    ("bonus", "featurettes"),
+    ("interviews", "featurettes"),
+];
+
+/// The Jellyfin extras folder (`trailers`, `featurettes`, `behind the scenes` or
+/// `deleted scenes`) for a video `is_video_file` drops as bonus content, chosen by its
+/// nearest extras directory, else by its name. `None` for regular videos, samples,
+/// release-site promos and non-video files.
+pub fn extra_folder(path: &str) -> Option<&'static str> {
+    let lower = path.to_lowercase();
+    let filename = lower.rsplit('/').next().unwrap_or(&lower);
+    let stem = VIDEO_EXTENSIONS
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext))?;
+    if is_video_file(path) || EXCLUDED_PROMO_RE.is_match(stem) || SAMPLE_RE.is_match(&lower) {
+        return None;
+    }
+    let directories = lower.split('/').rev().skip(1);
+    if let Some(folder) = directories
+        .filter_map(|dir| EXTRAS_DIRS.iter().find(|(name, _)| *name == dir))
+        .map(|(_, folder)| *folder)
+        .next()
+    {
+        return Some(folder);
+    }
+    if TRAILER_RE.is_match(filename) {
+        Some("trailers")
+    } else {
+        Some("featurettes")
+    }
+}
+
+fn is_extra_file(path: &str) -> bool {
+    extra_folder(path).is_some()
+}
+
 fn xml_escape(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     for c in s.chars() {
@@ -947,6 +2034,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn torrent_paths_maps_ids_to_media_paths() {
+        let file = |id: u32, path: &str| TorrentFile {
+            id,
+            path: path.to_string(),
+            bytes: 100,
+            selected: 1,
+        };
+        let torrents = vec![(
+            TorrentInfo {
+                id: "show1".to_string(),
+                filename: "Show.S01".to_string(),
+                files: vec![file(1, "/Show.S01E01.mkv"), file(2, "/Show.S01E02.mkv")],
+                ..Default::default()
+            },
+            MediaMetadata {
+                title: "Show".to_string(),
+                year: None,
+                media_type: MediaType::Show,
+                external_id: Some("tmdb:1".to_string()),
+            },
+        )];
+        let vfs = DebridVfs::build(torrents);
+        let paths = vfs.torrent_paths();
+        let mut show_paths = paths.get("show1").cloned().unwrap();
+        show_paths.sort();
+        assert_eq!(
+            show_paths,
+            vec![
+                "Shows/Show [tmdbid-1]/Season 01/Show.S01E01.mkv".to_string(),
+                "Shows/Show [tmdbid-1]/Season 01/Show.S01E02.mkv".to_string(),
+            ]
+        );
+    }
+
     #[test]
     fn build_excludes_release_site_promo_from_movie_folder() {
         // A correctly-identified movie torrent that also ships a release-site
@@ -1191,7 +2313,7 @@ mod tests {
             media_type: MediaType::Movie,
             external_id: Some("tmdb:12345".to_string()),
         };
-        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata)).unwrap();
+        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata, None)).unwrap();
         assert!(content.contains("<movie>"));
         assert!(content.contains("<title>Test Movie</title>"));
         assert!(content.contains("<originaltitle>Test Movie</originaltitle>"));
@@ -1204,6 +2326,20 @@ mod tests {
         assert!(content.contains("<source>debridmoviemapper</source>"));
     }
 
+    #[test]
+    fn nfo_names_tvdb_ids_for_tvdb_keyed_shows() {
+        let metadata = MediaMetadata {
+            title: "Lost".to_string(),
+            year: Some("2004".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tvdb:73739".to_string()),
+        };
+        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata, None)).unwrap();
+        assert!(content.contains("<uniqueid type=\"tvdb\" default=\"true\">73739</uniqueid>"));
+        assert!(content.contains("<tvdbid>73739</tvdbid>"));
+        assert!(!content.contains("<tmdbid>"));
+    }
+
     #[test]
     fn nfo_premiered_only_emitted_for_valid_four_digit_year() {
         // A clean 4-digit year emits <premiered>.
@@ -1213,7 +2349,7 @@ mod tests {
             media_type: MediaType::Movie,
             external_id: None,
         };
-        let good_nfo = String::from_utf8(DebridVfs::generate_nfo(&good)).unwrap();
+        let good_nfo = String::from_utf8(DebridVfs::generate_nfo(&good, None)).unwrap();
         assert!(good_nfo.contains("<premiered>2024-01-01</premiered>"));
 
         // A malformed year still emits <year> (best-effort) but NOT an invalid <premiered>.
@@ -1224,7 +2360,7 @@ mod tests {
                 media_type: MediaType::Movie,
                 external_id: None,
             };
-            let nfo = String::from_utf8(DebridVfs::generate_nfo(&bad)).unwrap();
+            let nfo = String::from_utf8(DebridVfs::generate_nfo(&bad, None)).unwrap();
             assert!(
                 !nfo.contains("<premiered>"),
                 "year {:?} must not produce a <premiered> tag",
@@ -1550,7 +2686,7 @@ mod tests {
             media_type: MediaType::Movie,
             external_id: Some("tmdb:123".to_string()),
         };
-        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata)).unwrap();
+        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata, None)).unwrap();
         assert!(
             content.contains("<title>Test &amp; &lt;Movie&gt;</title>"),
             "Title should be XML-escaped"
@@ -1566,6 +2702,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_full_nfo_includes_details() {
+        let metadata = MediaMetadata {
+            title: "Inception".to_string(),
+            year: Some("2010".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:27205".to_string()),
+        };
+        let details = TitleDetails {
+            plot: Some("Dreams & heists.".to_string()),
+            genres: vec!["Action".to_string(), "Science Fiction".to_string()],
+            certification: Some("PG-13".to_string()),
+            rating: Some((8.37, 35000)),
+            poster_url: Some("https://image.tmdb.org/t/p/original/poster.jpg".to_string()),
+            cast: vec![crate::tmdb_client::CastMember {
+                name: "Leonardo DiCaprio".to_string(),
+                role: Some("Cobb".to_string()),
+                thumb_url: None,
+            }],
+            ..Default::default()
+        };
+        let content =
+            String::from_utf8(DebridVfs::generate_nfo(&metadata, Some(&details))).unwrap();
+        assert!(content.contains("<plot>Dreams &amp; heists.</plot>"));
+        assert!(content.contains("<genre>Science Fiction</genre>"));
+        assert!(content.contains("<mpaa>PG-13</mpaa>"));
+        assert!(content.contains("<value>8.4</value>"));
+        assert!(content.contains(
+            "<thumb aspect=\"poster\">https://image.tmdb.org/t/p/original/poster.jpg</thumb>"
+        ));
+        assert!(content.contains("<name>Leonardo DiCaprio</name>\n    <role>Cobb</role>"));
+        assert!(content.ends_with("</movie>\n"));
+
+        let plain = String::from_utf8(DebridVfs::generate_nfo(&metadata, None)).unwrap();
+        assert!(!plain.contains("<plot>"));
+    }
+
     #[test]
     fn test_nfo_strips_control_characters() {
         let metadata = MediaMetadata {
@@ -1574,7 +2747,7 @@ mod tests {
             media_type: MediaType::Movie,
             external_id: Some("tmdb:123".to_string()),
         };
-        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata)).unwrap();
+        let content = String::from_utf8(DebridVfs::generate_nfo(&metadata, None)).unwrap();
         assert!(
             content.contains("<title>MovieWithControlChars</title>"),
             "Control characters should be stripped from NFO XML"
@@ -1669,6 +2842,382 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_with_quality_profile_prefers_matching_release_over_size() {
+        let metadata = MediaMetadata {
+            title: "Movie".to_string(),
+            year: Some("2023".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:1".to_string()),
+        };
+        let torrent = |id: &str, filename: &str, bytes: u64| TorrentInfo {
+            id: id.to_string(),
+            filename: filename.to_string(),
+            bytes,
+            files: vec![TorrentFile {
+                id: 1,
+                path: format!("/{}.mkv", filename),
+                bytes,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let torrents = vec![
+            (
+                torrent("uhd", "Movie.2023.2160p.x265-GRP", 5000),
+                metadata.clone(),
+            ),
+            (
+                torrent("banned", "Movie.2023.1080p.x264-BAD", 3000),
+                metadata.clone(),
+            ),
+            (torrent("hd", "Movie.2023.1080p.x264-GRP", 1000), metadata),
+        ];
+        let picked = |options: VfsOptions| {
+            let vfs = DebridVfs::build_with(torrents.clone(), Arc::new(options));
+            let paths = vfs.torrent_paths();
+            assert_eq!(paths.len(), 1);
+            paths.into_keys().next().unwrap()
+        };
+
+        // Without a profile the largest torrent wins, as before.
+        assert_eq!(picked(VfsOptions::default()), "uhd");
+        let quality = QualityProfile {
+            resolution: Some(crate::quality::Resolution::P1080),
+            banned_groups: vec!["BAD".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            picked(VfsOptions::from_env(quality, None, None, None, None)),
+            "hd"
+        );
+    }
+
+    #[test]
+    fn build_with_movie_versions_emits_each_distinct_release() {
+        let metadata = MediaMetadata {
+            title: "Dune".to_string(),
+            year: Some("2021".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:438631".to_string()),
+        };
+        let torrent = |id: &str, filename: &str, bytes: u64| TorrentInfo {
+            id: id.to_string(),
+            filename: filename.to_string(),
+            bytes,
+            files: vec![
+                TorrentFile {
+                    id: 1,
+                    path: format!("/{}/{}.mkv", filename, filename),
+                    bytes,
+                    selected: 1,
+                },
+                TorrentFile {
+                    id: 2,
+                    path: format!("/{}/Sample.mkv", filename),
+                    bytes: 1,
+                    selected: 1,
+                },
+            ],
+            ..Default::default()
+        };
+        let torrents = vec![
+            (
+                torrent("uhd", "Dune.2021.2160p.HDR.x265", 5000),
+                metadata.clone(),
+            ),
+            (
+                torrent("hd", "Dune.2021.1080p.x264", 2000),
+                metadata.clone(),
+            ),
+            // Same label as "hd": the same version, so only the larger one is kept.
+            (
+                torrent("hd2", "Dune.2021.1080p.WEB", 1000),
+                metadata.clone(),
+            ),
+        ];
+        let options = VfsOptions {
+            movie_versions: true,
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(torrents.clone(), Arc::new(options));
+        let VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let Some(VfsNode::Directory { children: movies }) = children.get("Movies") else {
+            panic!("Movies missing");
+        };
+        let Some(VfsNode::Directory { children: files }) = movies.get("Dune [tmdbid-438631]")
+        else {
+            panic!("movie folder missing");
+        };
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![
+                "Dune [tmdbid-438631] - 1080p.mkv",
+                "Dune [tmdbid-438631] - 2160p HDR.mkv",
+                "movie.nfo"
+            ]
+        );
+        let Some(VfsNode::MediaFile { locator, .. }) =
+            files.get("Dune [tmdbid-438631] - 1080p.mkv")
+        else {
+            panic!("version missing");
+        };
+        assert_eq!(locator.torrent_id, "hd");
+
+        // Off by default: only the best release, under its own name.
+        let paths = DebridVfs::build(torrents).torrent_paths();
+        assert_eq!(paths.keys().collect::<Vec<_>>(), vec!["uhd"]);
+    }
+
+    #[test]
+    fn build_with_extras_fills_jellyfin_extras_folders() {
+        let metadata = MediaMetadata {
+            title: "Alien".to_string(),
+            year: Some("1979".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:348".to_string()),
+        };
+        let paths = [
+            "/Alien.1979/Alien.1979.1080p.mkv",
+            "/Alien.1979/Featurettes/The Beast Within.mkv",
+            "/Alien.1979/Extras/Behind The Scenes/Crew.mkv",
+            "/Alien.1979/Alien.1979.Trailer.mkv",
+            "/Alien.1979/Sample/Alien.1979.sample.mkv",
+            "/Alien.1979/RARBG.com.mp4",
+        ];
+        let torrent = TorrentInfo {
+            id: "t1".to_string(),
+            files: paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| TorrentFile {
+                    id: i as u32 + 1,
+                    path: path.to_string(),
+                    bytes: 100,
+                    selected: 1,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let options = VfsOptions {
+            extras: true,
+            ..Default::default()
+        };
+        let vfs =
+            DebridVfs::build_with(vec![(torrent.clone(), metadata.clone())], Arc::new(options));
+        let paths = vfs.torrent_paths();
+        let mut files = paths["t1"].clone();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "Movies/Alien [tmdbid-348]/Alien.1979.1080p.mkv",
+                "Movies/Alien [tmdbid-348]/behind the scenes/Crew.mkv",
+                "Movies/Alien [tmdbid-348]/featurettes/The Beast Within.mkv",
+                "Movies/Alien [tmdbid-348]/trailers/Alien.1979.Trailer.mkv",
+            ]
+        );
+        assert!(vfs
+            .timestamps
+            .contains_key("Movies/Alien [tmdbid-348]/trailers/Alien.1979.Trailer.mkv"));
+
+        // Off by default: extras are dropped.
+        let paths = DebridVfs::build(vec![(torrent, metadata)]).torrent_paths();
+        assert_eq!(
+            paths["t1"],
+            vec!["Movies/Alien [tmdbid-348]/Alien.1979.1080p.mkv"]
+        );
+    }
+
+    #[test]
+    fn build_exposes_disc_main_feature_under_title_name() {
+        let metadata = MediaMetadata {
+            title: "Arrival".to_string(),
+            year: Some("2016".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:329865".to_string()),
+        };
+        let files = [
+            ("/ARRIVAL/BDMV/index.bdmv", 1),
+            ("/ARRIVAL/BDMV/PLAYLIST/00800.mpls", 1),
+            ("/ARRIVAL/BDMV/STREAM/00000.m2ts", 500),
+            ("/ARRIVAL/BDMV/STREAM/00001.m2ts", 30000),
+            ("/ARRIVAL/BDMV/STREAM/00002.m2ts", 900),
+            ("/ARRIVAL/CERTIFICATE/id.bdmv", 1),
+        ];
+        let torrent = TorrentInfo {
+            id: "bd".to_string(),
+            files: files
+                .iter()
+                .enumerate()
+                .map(|(i, (path, bytes))| TorrentFile {
+                    id: i as u32 + 1,
+                    path: path.to_string(),
+                    bytes: *bytes,
+                    selected: 1,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let paths = DebridVfs::build(vec![(torrent.clone(), metadata.clone())]).torrent_paths();
+        assert_eq!(
+            paths["bd"],
+            vec!["Movies/Arrival [tmdbid-329865]/Arrival (2016).m2ts"]
+        );
+
+        let options = VfsOptions {
+            disc_folders: true,
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(vec![(torrent, metadata)], Arc::new(options));
+        let mut paths = vfs.torrent_paths()["bd"].clone();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "Movies/Arrival [tmdbid-329865]/Arrival (2016).m2ts",
+                "Movies/Arrival [tmdbid-329865]/BDMV/PLAYLIST/00800.mpls",
+                "Movies/Arrival [tmdbid-329865]/BDMV/STREAM/00000.m2ts",
+                "Movies/Arrival [tmdbid-329865]/BDMV/STREAM/00001.m2ts",
+                "Movies/Arrival [tmdbid-329865]/BDMV/STREAM/00002.m2ts",
+                "Movies/Arrival [tmdbid-329865]/BDMV/index.bdmv",
+            ]
+        );
+        // Disc files get their torrent's timestamp; files outside BDMV are left out.
+        assert!(vfs
+            .timestamps
+            .contains_key("Movies/Arrival [tmdbid-329865]/BDMV/PLAYLIST/00800.mpls"));
+    }
+
+    #[test]
+    fn build_with_movie_versions_exposes_disc_features() {
+        let metadata = MediaMetadata {
+            title: "Arrival".to_string(),
+            year: Some("2016".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:329865".to_string()),
+        };
+        let torrent = |id: &str, filename: &str, files: &[(&str, u64)]| TorrentInfo {
+            id: id.to_string(),
+            filename: filename.to_string(),
+            bytes: files.iter().map(|(_, bytes)| bytes).sum(),
+            files: files
+                .iter()
+                .enumerate()
+                .map(|(i, (path, bytes))| TorrentFile {
+                    id: i as u32 + 1,
+                    path: format!("/{}/{}", filename, path),
+                    bytes: *bytes,
+                    selected: 1,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let disc = [
+            ("BDMV/index.bdmv", 1),
+            ("BDMV/STREAM/00000.m2ts", 500),
+            ("BDMV/STREAM/00001.m2ts", 30000),
+        ];
+        let torrents = vec![
+            (
+                torrent("uhd", "Arrival.2016.2160p.UHD.BluRay", &disc),
+                metadata.clone(),
+            ),
+            (
+                torrent("bd", "Arrival.2016.1080p.BluRay", &disc),
+                metadata.clone(),
+            ),
+            (
+                torrent("web", "Arrival.2016.720p.WEB", &[("Arrival.mkv", 2000)]),
+                metadata.clone(),
+            ),
+        ];
+        let options = VfsOptions {
+            movie_versions: true,
+            disc_folders: true,
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(torrents, Arc::new(options));
+        let paths = vfs.torrent_paths();
+        let folder = "Movies/Arrival [tmdbid-329865]";
+        let mut uhd = paths["uhd"].clone();
+        uhd.sort();
+        assert_eq!(
+            uhd,
+            vec![
+                format!("{}/Arrival [tmdbid-329865] - 2160p.m2ts", folder),
+                format!("{}/BDMV/STREAM/00000.m2ts", folder),
+                format!("{}/BDMV/STREAM/00001.m2ts", folder),
+                format!("{}/BDMV/index.bdmv", folder),
+            ]
+        );
+        // Only one disc folder per movie folder.
+        assert_eq!(
+            paths["bd"],
+            vec![format!("{}/Arrival [tmdbid-329865] - 1080p.m2ts", folder)]
+        );
+        assert_eq!(
+            paths["web"],
+            vec![format!("{}/Arrival [tmdbid-329865] - 720p.mkv", folder)]
+        );
+        assert!(vfs
+            .timestamps
+            .contains_key(&format!("{}/Arrival [tmdbid-329865] - 1080p.m2ts", folder)));
+    }
+
+    #[test]
+    fn disc_feature_picks_largest_dvd_title_set_in_order() {
+        let file = |id: u32, path: &str, bytes: u64| TorrentFile {
+            id,
+            path: path.to_string(),
+            bytes,
+            selected: 1,
+        };
+        let torrent = TorrentInfo {
+            files: vec![
+                file(1, "/DVD/VIDEO_TS/VIDEO_TS.VOB", 10),
+                file(2, "/DVD/VIDEO_TS/VTS_01_0.VOB", 10),
+                file(3, "/DVD/VIDEO_TS/VTS_01_1.VOB", 500),
+                file(4, "/DVD/VIDEO_TS/VTS_02_2.VOB", 400),
+                file(5, "/DVD/VIDEO_TS/VTS_02_1.VOB", 1000),
+            ],
+            ..Default::default()
+        };
+        let ids: Vec<u32> = disc_feature(&torrent).iter().map(|(f, _)| f.id).collect();
+        assert_eq!(ids, vec![5, 4]);
+        // VOBs are video to the DVD path only.
+        assert!(streamable_files(&torrent).is_empty());
+        // Not a disc: no feature.
+        let plain = TorrentInfo {
+            files: vec![file(1, "/Movie.2020.mkv", 10)],
+            ..Default::default()
+        };
+        assert!(disc_feature(&plain).is_empty());
+    }
+
+    #[test]
+    fn extra_folder_classifies_bonus_videos() {
+        assert_eq!(extra_folder("/M/Trailers/Teaser.mkv"), Some("trailers"));
+        assert_eq!(extra_folder("/M/Movie-trailer.mp4"), Some("trailers"));
+        assert_eq!(
+            extra_folder("/M/Deleted Scenes/Cut.mkv"),
+            Some("deleted scenes")
+        );
+        assert_eq!(extra_folder("/M/Bonus/Interview.mkv"), Some("featurettes"));
+        assert_eq!(
+            extra_folder("/M/Making.Of.Featurette.mkv"),
+            Some("featurettes")
+        );
+        // Regular videos, samples, promos and non-videos are not extras.
+        assert_eq!(extra_folder("/M/Movie.2020.mkv"), None);
+        assert_eq!(extra_folder("/M/Trailers/sample.mkv"), None);
+        assert_eq!(extra_folder("/M/Samples/clip.mkv"), None);
+        assert_eq!(extra_folder("/M/Extras/RARBG.com.mp4"), None);
+        assert_eq!(extra_folder("/M/Extras/notes.txt"), None);
+    }
+
     #[test]
     fn is_archive_file_detects_archives() {
         assert!(is_archive_file("movie.rar"));
@@ -2948,6 +4497,303 @@ mod tests {
         assert_eq!(extract("Show.S03E05.1920x1080.mkv"), Some(3));
     }
 
+    #[test]
+    fn parse_episode_requires_season_and_episode() {
+        assert_eq!(parse_episode("Show.S03E05.1080p.mkv"), Some((3, 5)));
+        assert_eq!(parse_episode("show s01.e12.mkv"), Some((1, 12)));
+        assert_eq!(parse_episode("Show.2x07.mkv"), Some((2, 7)));
+        assert_eq!(parse_episode("Show.S01E01E02.mkv"), Some((1, 1)));
+        // Season-only markers and pixel resolutions name no episode.
+        assert_eq!(parse_episode("Show.S02.Complete.mkv"), None);
+        assert_eq!(parse_episode("Show.1920x1080.mkv"), None);
+    }
+
+    #[test]
+    fn build_with_plex_naming_renames_files_and_omits_nfo() {
+        let torrent = |id: &str, paths: &[&str]| TorrentInfo {
+            id: id.to_string(),
+            filename: id.to_string(),
+            files: paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| TorrentFile {
+                    id: i as u32 + 1,
+                    path: path.to_string(),
+                    bytes: 1000,
+                    selected: 1,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let movie = MediaMetadata {
+            title: "Dune".to_string(),
+            year: Some("2021".to_string()),
+            media_type: MediaType::Movie,
+            external_id: Some("tmdb:438631".to_string()),
+        };
+        let show = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:95396".to_string()),
+        };
+        let torrents = vec![
+            (
+                torrent("m", &["/Dune.2021.2160p/Dune.2021.2160p.mkv"]),
+                movie,
+            ),
+            (
+                torrent(
+                    "s",
+                    &["/Sev/Severance.S01E01.mkv", "/Sev/Severance.S01E02.mkv"],
+                ),
+                show,
+            ),
+        ];
+        let options = VfsOptions {
+            naming: Arc::new(crate::naming::PlexNaming),
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(torrents, Arc::new(options));
+        let paths: Vec<String> = vfs.torrent_paths().into_values().flatten().collect();
+
+        assert!(paths.contains(&"Movies/Dune (2021) {tmdb-438631}/Dune (2021).mkv".to_string()));
+        assert!(paths.contains(
+            &"Shows/Severance (2022) {tmdb-95396}/Season 01/Severance (2022) - s01e02.mkv"
+                .to_string()
+        ));
+        assert!(!vfs.timestamps.keys().any(|path| path.ends_with(".nfo")));
+    }
+
+    #[test]
+    fn build_with_rename_episodes_collapses_by_episode() {
+        let torrent = |id: &str, path: &str, bytes: u64| TorrentInfo {
+            id: id.to_string(),
+            filename: id.to_string(),
+            bytes,
+            files: vec![TorrentFile {
+                id: 1,
+                path: path.to_string(),
+                bytes,
+                selected: 1,
+            }],
+            ..Default::default()
+        };
+        let show = MediaMetadata {
+            title: "Severance".to_string(),
+            year: Some("2022".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:95396".to_string()),
+        };
+        // Two releases of the same episode under different names collapse into one file.
+        let torrents = vec![
+            (
+                torrent("big", "/a/Severance.S01E02.2160p.mkv", 2000),
+                show.clone(),
+            ),
+            (torrent("small", "/b/severance.1x02.hdtv.mp4", 1000), show),
+        ];
+        let options = VfsOptions {
+            rename_episodes: true,
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build_with(torrents, Arc::new(options));
+        let paths = vfs.torrent_paths();
+        assert_eq!(
+            paths.get("big").unwrap(),
+            &vec!["Shows/Severance [tmdbid-95396]/Season 01/Severance - S01E02.mkv".to_string()]
+        );
+        assert!(!paths.contains_key("small"));
+    }
+
+    #[test]
+    fn build_exposes_subtitles_next_to_their_video() {
+        let files = |paths: &[&str]| -> Vec<TorrentFile> {
+            paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| TorrentFile {
+                    id: i as u32 + 1,
+                    path: path.to_string(),
+                    bytes: 100,
+                    selected: 1,
+                })
+                .collect()
+        };
+        let movie = TorrentInfo {
+            id: "movie".to_string(),
+            files: files(&[
+                "/Heat.1995.1080p/Heat.1995.1080p.mkv",
+                "/Heat.1995.1080p/Subs/English.srt",
+                "/Heat.1995.1080p/Subs/French.srt",
+                "/Heat.1995.1080p/Heat.1995.1080p.nfo",
+            ]),
+            ..Default::default()
+        };
+        let show = TorrentInfo {
+            id: "show".to_string(),
+            files: files(&[
+                "/Severance.S01/Severance.S01E01.mkv",
+                "/Severance.S01/Severance.S01E02.mkv",
+                "/Severance.S01/Subs/Severance.S01E01/2_English.srt",
+                "/Severance.S01/Severance.S01E02.eng.forced.ass",
+            ]),
+            ..Default::default()
+        };
+        let metadata = |title: &str, media_type: MediaType, id: &str| MediaMetadata {
+            title: title.to_string(),
+            year: None,
+            media_type,
+            external_id: Some(id.to_string()),
+        };
+        let vfs = DebridVfs::build(vec![
+            (movie, metadata("Heat", MediaType::Movie, "tmdb:949")),
+            (show, metadata("Severance", MediaType::Show, "tmdb:95396")),
+        ]);
+        let mut paths = vfs.torrent_paths();
+        let mut movie_paths = paths.remove("movie").unwrap();
+        movie_paths.sort();
+        assert_eq!(
+            movie_paths,
+            vec![
+                "Movies/Heat [tmdbid-949]/Heat.1995.1080p.en.srt",
+                "Movies/Heat [tmdbid-949]/Heat.1995.1080p.fr.srt",
+                "Movies/Heat [tmdbid-949]/Heat.1995.1080p.mkv",
+            ]
+        );
+        let mut show_paths = paths.remove("show").unwrap();
+        show_paths.sort();
+        let season = "Shows/Severance [tmdbid-95396]/Season 01";
+        assert_eq!(
+            show_paths,
+            vec![
+                format!("{}/Severance.S01E01.en.srt", season),
+                format!("{}/Severance.S01E01.mkv", season),
+                format!("{}/Severance.S01E02.en.forced.ass", season),
+                format!("{}/Severance.S01E02.mkv", season),
+            ]
+        );
+    }
+
+    #[test]
+    fn canonical_episode_name_includes_sanitized_title() {
+        assert_eq!(
+            canonical_episode_name("Severance", 1, 2, 2, Some("Half Loop"), "x.mkv"),
+            "Severance - S01E02 - Half Loop.mkv"
+        );
+        assert_eq!(
+            canonical_episode_name("Show: Name", 10, 1, 1, Some("Who? Me/You"), "x.mp4"),
+            "Show - Name - S10E01 - Who Me-You.mp4"
+        );
+        assert_eq!(
+            canonical_episode_name("Show", 1, 1, 1, None, "x.mkv"),
+            "Show - S01E01.mkv"
+        );
+        assert_eq!(
+            canonical_episode_name("Show", 1, 1, 3, Some("A & B & C"), "x.mkv"),
+            "Show - S01E01-E03 - A & B & C.mkv"
+        );
+    }
+
+    #[test]
+    fn parse_absolute_episode_reads_anime_numbering() {
+        assert_eq!(parse_absolute_episode("One Piece - 137.mkv"), Some(137));
+        assert_eq!(
+            parse_absolute_episode("[Sub] Frieren - 05v2 [1080p][ABCD1234].mkv"),
+            Some(5)
+        );
+        assert_eq!(parse_absolute_episode("Naruto.E220.mkv"), Some(220));
+        assert_eq!(parse_absolute_episode("Show Episode 12.mp4"), Some(12));
+        // SxxEyy files, resolutions and years aren't absolute numbers.
+        assert_eq!(parse_absolute_episode("Show - S01E05 - 1080p.mkv"), None);
+        assert_eq!(parse_absolute_episode("Show - 1080p.mkv"), None);
+        assert_eq!(parse_absolute_episode("Show - 2019.mkv"), None);
+    }
+
+    #[test]
+    fn parse_episode_range_reads_multi_episode_markers() {
+        assert_eq!(parse_episode_range("Show.S01E02.mkv"), Some((1, 2, 2)));
+        assert_eq!(parse_episode_range("Show.S01E01E02.mkv"), Some((1, 1, 2)));
+        assert_eq!(parse_episode_range("Show.S01E01-E03.mkv"), Some((1, 1, 3)));
+        assert_eq!(
+            parse_episode_range("Show.S02E05-06.720p.mkv"),
+            Some((2, 5, 6))
+        );
+        // A resolution or a descending number after the marker is not a range.
+        assert_eq!(
+            parse_episode_range("Show.S01E01-1080p.mkv"),
+            Some((1, 1, 1))
+        );
+        assert_eq!(parse_episode_range("Show.S01E05-E02.mkv"), Some((1, 5, 5)));
+        assert_eq!(parse_episode_range("Show.Special.mkv"), None);
+    }
+
+    #[test]
+    fn build_routes_specials_and_describes_multi_episode_files() {
+        let show = MediaMetadata {
+            title: "Doctor Who".to_string(),
+            year: Some("2005".to_string()),
+            media_type: MediaType::Show,
+            external_id: Some("tmdb:57243".to_string()),
+        };
+        let info = TorrentInfo {
+            id: "t1".to_string(),
+            files: [
+                "/DW/Doctor.Who.S01E01E02.mkv",
+                "/DW/Doctor.Who.S00E05.mkv",
+                "/DW/Doctor.Who.Christmas.Special.mkv",
+                "/DW/Specials/Doctor.Who.Children.in.Need.mkv",
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, path)| TorrentFile {
+                id: i as u32 + 1,
+                path: path.to_string(),
+                bytes: 1,
+                selected: 1,
+            })
+            .collect(),
+            ..Default::default()
+        };
+        let vfs = DebridVfs::build(vec![(info, show)]);
+        let show_dir = "Shows/Doctor Who [tmdbid-57243]";
+        let mut paths = vfs.torrent_paths().remove("t1").unwrap();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/Season 00/Doctor.Who.Children.in.Need.mkv", show_dir),
+                format!("{}/Season 00/Doctor.Who.Christmas.Special.mkv", show_dir),
+                format!("{}/Season 00/Doctor.Who.S00E05.mkv", show_dir),
+                format!("{}/Season 01/Doctor.Who.S01E01E02.mkv", show_dir),
+            ]
+        );
+
+        let nfo_path = format!("{}/Season 01/Doctor.Who.S01E01E02.nfo", show_dir);
+        assert!(vfs.timestamps.contains_key(&nfo_path));
+        let VfsNode::Directory { children } = &vfs.root else {
+            panic!("root is a directory");
+        };
+        let mut node = children.get("Shows").unwrap();
+        for part in [
+            "Doctor Who [tmdbid-57243]",
+            "Season 01",
+            "Doctor.Who.S01E01E02.nfo",
+        ] {
+            let VfsNode::Directory { children } = node else {
+                panic!("{} is not in a directory", part);
+            };
+            node = children.get(part).unwrap();
+        }
+        let VfsNode::VirtualFile { content } = node else {
+            panic!("episode NFO is a virtual file");
+        };
+        let nfo = String::from_utf8(content.clone()).unwrap();
+        assert_eq!(nfo.matches("<episodedetails>").count(), 2);
+        assert!(nfo.contains("<episode>1</episode>") && nfo.contains("<episode>2</episode>"));
+        assert!(nfo.contains("<showtitle>Doctor Who</showtitle>"));
+    }
+
     /// Test that archive-only torrents produce an empty movie folder (no media files).
     #[test]
     fn build_skips_archive_only_torrents() {
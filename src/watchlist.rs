@@ -0,0 +1,259 @@
+//! Trakt watchlist auto-population: movies and shows on the watchlist that aren't in
+//! the library are searched on the Torznab indexer and the first cached release is added
+//! to the debrid account (season or series packs for shows). Started by the scan loop at
+//! most once per sync interval, in a task of its own so grabs never delay a scan.
+
+use crate::acquire::{add_if_cached, load_grabs, record_grab, Grab};
+use crate::indexer::{rank_releases, SearchQuery, TorrentIndexer};
+use crate::provider::DebridProvider;
+use crate::quality::QualityProfile;
+use crate::rd_client::TorrentInfo;
+use crate::trakt_client::{TraktClient, WatchlistItem};
+use crate::vfs::{MediaMetadata, MediaType};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 3600;
+/// Releases tried per watchlist item before giving up until the next sync.
+const MAX_CANDIDATES: usize = 3;
+
+/// Key under which a grab is recorded, e.g. `movie:27205`.
+pub fn grab_key(media_type: &MediaType, tmdb_id: u64) -> String {
+    match media_type {
+        MediaType::Movie => format!("movie:{}", tmdb_id),
+        MediaType::Show => format!("show:{}", tmdb_id),
+    }
+}
+
+pub struct WatchlistSync {
+    trakt: TraktClient,
+    indexer: Arc<dyn TorrentIndexer>,
+    interval: Duration,
+    profile: QualityProfile,
+    last_run: Mutex<Option<Instant>>,
+}
+
+impl WatchlistSync {
+    pub fn new(
+        trakt: TraktClient,
+        indexer: Arc<dyn TorrentIndexer>,
+        profile: QualityProfile,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            trakt,
+            indexer,
+            profile,
+            interval,
+            last_run: Mutex::new(None),
+        }
+    }
+
+    /// Enabled when both Trakt and the indexer are configured. The interval comes from
+    /// `WATCHLIST_SYNC_INTERVAL_SECS` (default one hour).
+    pub fn from_env(
+        indexer: Option<Arc<dyn TorrentIndexer>>,
+        profile: QualityProfile,
+    ) -> Option<Self> {
+        let trakt = TraktClient::from_env()?;
+        let Some(indexer) = indexer else {
+            warn!("Trakt is configured but TORZNAB_URL is not set; watchlist sync disabled");
+            return None;
+        };
+        let secs = match std::env::var("WATCHLIST_SYNC_INTERVAL_SECS") {
+            Ok(s) => s.parse().unwrap_or_else(|_| {
+                warn!(
+                    "Invalid WATCHLIST_SYNC_INTERVAL_SECS value '{}', falling back to {}",
+                    s, DEFAULT_SYNC_INTERVAL_SECS
+                );
+                DEFAULT_SYNC_INTERVAL_SECS
+            }),
+            Err(_) => DEFAULT_SYNC_INTERVAL_SECS,
+        };
+        Some(Self::new(
+            trakt,
+            indexer,
+            profile,
+            Duration::from_secs(secs),
+        ))
+    }
+
+    /// Sync if the interval has elapsed since the last run. Returns the number of
+    /// releases added.
+    pub async fn run_if_due(
+        &self,
+        provider: &Arc<dyn DebridProvider>,
+        db: &Arc<redb::Database>,
+        library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    ) -> usize {
+        {
+            let mut last_run = self.last_run.lock().await;
+            if last_run.is_some_and(|t| t.elapsed() < self.interval) {
+                return 0;
+            }
+            *last_run = Some(Instant::now());
+        }
+        let watchlist = match self.trakt.watchlist().await {
+            Ok(items) => items,
+            Err(e) => {
+                error!("Failed to fetch Trakt watchlist: {}", e);
+                return 0;
+            }
+        };
+        let grabs = load_grabs(db).await;
+        let missing = missing_items(&watchlist, library, &grabs);
+        if missing.is_empty() {
+            return 0;
+        }
+        info!("Watchlist: {} item(s) missing from library", missing.len());
+
+        let mut added = 0;
+        for (key, item) in missing {
+            if self.acquire(provider, db, &key, item).await {
+                added += 1;
+            }
+        }
+        added
+    }
+
+    async fn acquire(
+        &self,
+        provider: &Arc<dyn DebridProvider>,
+        db: &Arc<redb::Database>,
+        key: &str,
+        item: &WatchlistItem,
+    ) -> bool {
+        let query = SearchQuery {
+            media_type: item.media_type.clone(),
+            title: item.title.clone(),
+            year: item.year,
+            tmdb_id: item.tmdb_id,
+            imdb_id: item.imdb_id.clone(),
+            // For a show, no season or episode: only full-show and season packs match.
+            season: None,
+            episode: None,
+        };
+        let releases = match self.indexer.search(&query).await {
+            Ok(releases) => releases,
+            Err(e) => {
+                error!("Indexer search for '{}' failed: {}", query.text(), e);
+                return false;
+            }
+        };
+        for release in rank_releases(&query, &releases, &self.profile)
+            .into_iter()
+            .take(MAX_CANDIDATES)
+        {
+            match add_if_cached(provider, release).await {
+                Ok(Some(info)) => {
+                    info!(
+                        "Watchlist: added {} for {} ({})",
+                        release.title, item.title, key
+                    );
+                    let grab = Grab {
+                        release: release.title.clone(),
+                        hash: info.hash.to_lowercase(),
+                        torrent_id: info.id,
+                        grabbed_at: chrono::Utc::now().timestamp(),
+                    };
+                    if let Err(e) = record_grab(db, key, &grab).await {
+                        error!("Failed to record grab for {}: {}", key, e);
+                    }
+                    return true;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to add release {}: {}", release.title, e),
+            }
+        }
+        info!("Watchlist: no cached release found for {}", item.title);
+        false
+    }
+}
+
+/// Watchlist items with a TMDB id that are neither in the library nor already grabbed,
+/// paired with their grab key.
+fn missing_items<'a>(
+    watchlist: &'a [WatchlistItem],
+    library: &HashMap<String, (TorrentInfo, MediaMetadata)>,
+    grabs: &HashMap<String, Grab>,
+) -> Vec<(String, &'a WatchlistItem)> {
+    let present: HashSet<String> = library
+        .values()
+        .filter_map(|(_, metadata)| {
+            let id = metadata.external_id.as_deref()?.strip_prefix("tmdb:")?;
+            Some(grab_key(&metadata.media_type, id.parse().ok()?))
+        })
+        .collect();
+    watchlist
+        .iter()
+        .filter_map(|item| Some((grab_key(&item.media_type, item.tmdb_id?), item)))
+        .filter(|(key, _)| !present.contains(key) && !grabs.contains_key(key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(title: &str, year: u32, tmdb_id: u64) -> WatchlistItem {
+        WatchlistItem {
+            media_type: MediaType::Movie,
+            title: title.to_string(),
+            year: Some(year),
+            tmdb_id: Some(tmdb_id),
+            imdb_id: None,
+        }
+    }
+
+    fn entry(media_type: MediaType, external_id: &str) -> (TorrentInfo, MediaMetadata) {
+        (
+            TorrentInfo::default(),
+            MediaMetadata {
+                title: "x".to_string(),
+                year: None,
+                media_type,
+                external_id: Some(external_id.to_string()),
+            },
+        )
+    }
+
+    #[test]
+    fn missing_items_skips_library_grabbed_and_unidentifiable() {
+        let watchlist = vec![
+            movie("Inception", 2010, 27205),
+            movie("Heat", 1995, 949),
+            movie("Alien", 1979, 348),
+            WatchlistItem {
+                tmdb_id: None,
+                ..movie("No Id", 2000, 0)
+            },
+            // Same TMDB id as a movie in the library, but a show: ids are per type.
+            WatchlistItem {
+                media_type: MediaType::Show,
+                ..movie("Show", 2000, 27205)
+            },
+        ];
+        let library = HashMap::from([("t1".to_string(), entry(MediaType::Movie, "tmdb:27205"))]);
+        let grabs = HashMap::from([(
+            "movie:949".to_string(),
+            Grab {
+                release: "Heat".to_string(),
+                hash: "h".to_string(),
+                torrent_id: "t".to_string(),
+                grabbed_at: 0,
+            },
+        )]);
+        let keys: Vec<String> = missing_items(&watchlist, &library, &grabs)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["movie:348".to_string(), "show:27205".to_string()]
+        );
+    }
+}
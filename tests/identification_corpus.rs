@@ -0,0 +1,196 @@
+//! Identification accuracy over a corpus of real release names with known TMDB ids,
+//! measured offline against recorded TMDB responses.
+//!
+//! `tests/fixtures/identification_corpus.json` lists the cases. Record the responses they
+//! need from live TMDB, which writes `tests/fixtures/tmdb_responses.json`:
+//!
+//! ```text
+//! TMDB_API_KEY=... cargo test --test identification_corpus record -- --ignored
+//! ```
+//!
+//! then replay them without network access:
+//!
+//! ```text
+//! cargo test --test identification_corpus replay -- --ignored
+//! ```
+//!
+//! Replaying fails when no responses have been recorded. It stays ignored until they
+//! are committed.
+
+use debridmoviemapper::identification::identify_torrent_with_nfo;
+use debridmoviemapper::rd_client::{TorrentFile, TorrentInfo};
+use debridmoviemapper::tmdb_client::{FixtureMode, TmdbClient};
+use debridmoviemapper::vfs::{MediaMetadata, MediaType};
+use serde::Deserialize;
+
+const CORPUS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/identification_corpus.json"
+);
+const RESPONSES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/tmdb_responses.json"
+);
+
+/// One release name and what it must identify as.
+#[derive(Debug, Deserialize)]
+struct Case {
+    name: String,
+    /// The torrent's files; a single file named after the release when omitted.
+    #[serde(default)]
+    files: Vec<CaseFile>,
+    /// Text of a bundled NFO.
+    nfo: Option<String>,
+    /// The external id it must match, e.g. `tmdb:603`.
+    expected: Option<String>,
+    /// An id it must not match (a known past mismatch).
+    not: Option<String>,
+    title: Option<String>,
+    media_type: Option<MediaType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaseFile {
+    path: String,
+    bytes: u64,
+}
+
+impl Case {
+    fn torrent(&self) -> TorrentInfo {
+        let files: Vec<TorrentFile> = if self.files.is_empty() {
+            vec![TorrentFile {
+                id: 1,
+                path: self.name.clone(),
+                bytes: 2_000_000_000,
+                selected: 1,
+            }]
+        } else {
+            self.files
+                .iter()
+                .enumerate()
+                .map(|(i, f)| TorrentFile {
+                    id: i as u32 + 1,
+                    path: f.path.clone(),
+                    bytes: f.bytes,
+                    selected: 1,
+                })
+                .collect()
+        };
+        TorrentInfo {
+            id: "corpus".to_string(),
+            filename: self.name.clone(),
+            original_filename: self.name.clone(),
+            hash: "corpus".to_string(),
+            bytes: files.iter().map(|f| f.bytes).sum(),
+            status: "downloaded".to_string(),
+            files,
+            ..Default::default()
+        }
+    }
+
+    /// Why `metadata` does not satisfy the case, if it doesn't.
+    fn mismatch(&self, metadata: &MediaMetadata) -> Option<String> {
+        let got = metadata.external_id.as_deref().unwrap_or("unidentified");
+        if let Some(expected) = &self.expected {
+            if got != expected {
+                return Some(format!("got {}, expected {}", got, expected));
+            }
+        }
+        if self.not.as_deref() == Some(got) {
+            return Some(format!("matched known mismatch {}", got));
+        }
+        if let Some(title) = &self.title {
+            if &metadata.title != title {
+                return Some(format!("titled {:?}, expected {:?}", metadata.title, title));
+            }
+        }
+        if let Some(media_type) = &self.media_type {
+            if &metadata.media_type != media_type {
+                return Some(format!(
+                    "identified as {:?}, expected {:?}",
+                    metadata.media_type, media_type
+                ));
+            }
+        }
+        None
+    }
+}
+
+fn load_corpus() -> Vec<Case> {
+    let content = std::fs::read(CORPUS).expect("identification corpus is readable");
+    serde_json::from_slice(&content).expect("identification corpus is valid")
+}
+
+/// Identify every case, print the accuracy and return the failures.
+async fn run_corpus(tmdb: &TmdbClient) -> Vec<String> {
+    let corpus = load_corpus();
+    let mut failures = Vec::new();
+    for case in &corpus {
+        let metadata = identify_torrent_with_nfo(&case.torrent(), case.nfo.as_deref(), tmdb).await;
+        if let Some(reason) = case.mismatch(&metadata) {
+            failures.push(format!("{}: {}", case.name, reason));
+        }
+    }
+    let passed = corpus.len() - failures.len();
+    println!(
+        "Identification corpus: {}/{} correct ({:.1}%)",
+        passed,
+        corpus.len(),
+        passed as f64 / corpus.len() as f64 * 100.0
+    );
+    failures
+}
+
+#[test]
+fn corpus_cases_state_an_expectation() {
+    let corpus = load_corpus();
+    assert!(!corpus.is_empty());
+    for case in &corpus {
+        assert!(
+            case.expected.is_some()
+                || case.not.is_some()
+                || case.title.is_some()
+                || case.media_type.is_some(),
+            "corpus case {:?} checks nothing",
+            case.name
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs tests/fixtures/tmdb_responses.json recorded from live TMDB"]
+async fn replay_identification_corpus() {
+    assert!(
+        std::path::Path::new(RESPONSES).exists(),
+        "No recorded TMDB responses at {}; record them with \
+         `cargo test --test identification_corpus record -- --ignored`",
+        RESPONSES
+    );
+    let tmdb = TmdbClient::new(String::new())
+        .unwrap()
+        .with_fixtures(RESPONSES, FixtureMode::Replay)
+        .unwrap();
+    let failures = run_corpus(&tmdb).await;
+    assert!(
+        failures.is_empty(),
+        "Identification regressed for {} case(s):\n  {}",
+        failures.len(),
+        failures.join("\n  ")
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn record_identification_corpus() {
+    dotenvy::dotenv().ok();
+    let tmdb_api_key = std::env::var("TMDB_API_KEY").expect("TMDB_API_KEY must be set");
+    let tmdb = TmdbClient::new(tmdb_api_key)
+        .unwrap()
+        .with_fixtures(RESPONSES, FixtureMode::Record)
+        .unwrap();
+    let failures = run_corpus(&tmdb).await;
+    tmdb.save_fixtures().expect("recorded responses are saved");
+    for failure in &failures {
+        println!("✗ {}", failure);
+    }
+}
@@ -401,6 +401,9 @@ fn collect_links(node: &VfsNode, links: &mut std::collections::HashSet<String>)
                 links.insert(link.clone());
             }
         }
-        VfsNode::VirtualFile { .. } => {}
+        VfsNode::VirtualFile { .. }
+        | VfsNode::Artwork { .. }
+        | VfsNode::CachedFile { .. }
+        | VfsNode::Link { .. } => {}
     }
 }
@@ -32,7 +32,10 @@ fn vfs_has_media_file(node: &VfsNode, suffix: &str) -> bool {
     match node {
         VfsNode::Directory { children } => children.values().any(|c| vfs_has_media_file(c, suffix)),
         VfsNode::MediaFile { locator, .. } => locator.file_path.to_lowercase().ends_with(suffix),
-        VfsNode::VirtualFile { .. } => false,
+        VfsNode::VirtualFile { .. }
+        | VfsNode::Artwork { .. }
+        | VfsNode::CachedFile { .. }
+        | VfsNode::Link { .. } => false,
     }
 }
 
@@ -332,7 +332,10 @@ fn collect_media_files(
                 *file_size,
             ));
         }
-        VfsNode::VirtualFile { .. } => {}
+        VfsNode::VirtualFile { .. }
+        | VfsNode::Artwork { .. }
+        | VfsNode::CachedFile { .. }
+        | VfsNode::Link { .. } => {}
     }
 }
 
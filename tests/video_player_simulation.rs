@@ -383,7 +383,10 @@ fn find_video_files(
             };
             files.push((full_path, *file_size));
         }
-        VfsNode::VirtualFile { .. } => {}
+        VfsNode::VirtualFile { .. }
+        | VfsNode::Artwork { .. }
+        | VfsNode::CachedFile { .. }
+        | VfsNode::Link { .. } => {}
     }
 }
 
@@ -406,7 +409,10 @@ fn find_nfo_files(
                 find_nfo_files(child, child_name, next_path.clone(), files);
             }
         }
-        VfsNode::MediaFile { .. } => {}
+        VfsNode::MediaFile { .. }
+        | VfsNode::Artwork { .. }
+        | VfsNode::CachedFile { .. }
+        | VfsNode::Link { .. } => {}
         VfsNode::VirtualFile { content } => {
             if name.ends_with(".nfo") {
                 let full_path = if current_path.is_empty() {